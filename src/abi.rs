@@ -0,0 +1,150 @@
+use essential_types::Word;
+use serde::Deserialize;
+
+use crate::parse_types::{Field, Type};
+
+#[cfg(test)]
+mod tests;
+
+/// The ABI of a compiled Pint contract.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct ContractAbi {
+    pub predicates: Vec<PredicateAbi>,
+    #[serde(default)]
+    pub storage: Vec<VarAbi>,
+}
+
+/// The ABI of a single predicate.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct PredicateAbi {
+    pub name: String,
+    /// Decision variables in the order they appear in the solution data.
+    #[serde(default)]
+    pub vars: Vec<VarAbi>,
+}
+
+/// The ABI of the predicate being debugged and the storage of its contract.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DebugAbi {
+    pub predicate: PredicateAbi,
+    pub storage: Vec<VarAbi>,
+}
+
+/// A named and typed variable.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct VarAbi {
+    pub name: String,
+    pub ty: TypeAbi,
+}
+
+/// A Pint type as described by the ABI.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub enum TypeAbi {
+    Bool,
+    Int,
+    Real,
+    String,
+    B256,
+    Optional(Box<TypeAbi>),
+    Tuple(Vec<TupleFieldAbi>),
+    Array {
+        ty: Box<TypeAbi>,
+        size: i64,
+    },
+    Map {
+        ty_from: Box<TypeAbi>,
+        ty_to: Box<TypeAbi>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct TupleFieldAbi {
+    pub name: Option<String>,
+    pub ty: TypeAbi,
+}
+
+/// Parse a contract ABI from the JSON produced by the Pint compiler.
+pub fn parse_abi(bytes: &[u8]) -> anyhow::Result<ContractAbi> {
+    serde_json::from_slice(bytes).map_err(|e| anyhow::anyhow!("Failed to parse ABI file: {}", e))
+}
+
+/// Names match with or without the leading `::`.
+fn same_name(a: &str, b: &str) -> bool {
+    a.trim_start_matches("::") == b.trim_start_matches("::")
+}
+
+impl ContractAbi {
+    /// Find a predicate by name.
+    pub fn predicate(&self, name: &str) -> Option<&PredicateAbi> {
+        self.predicates.iter().find(|p| same_name(&p.name, name))
+    }
+}
+
+impl DebugAbi {
+    /// Use the predicate and storage from the contract ABI.
+    pub fn new(contract: &ContractAbi, predicate: PredicateAbi) -> Self {
+        Self {
+            predicate,
+            storage: contract.storage.clone(),
+        }
+    }
+}
+
+impl PredicateAbi {
+    /// Find a decision variable and its index by name.
+    pub fn var(&self, name: &str) -> Option<(usize, &VarAbi)> {
+        self.vars
+            .iter()
+            .enumerate()
+            .find(|(_, v)| same_name(&v.name, name))
+    }
+}
+
+impl TypeAbi {
+    /// Convert to a type the debugger can decode.
+    ///
+    /// Returns `None` for types that have no fixed word layout.
+    pub fn to_type(&self) -> Option<Type> {
+        match self {
+            TypeAbi::Bool => Some(Type::Bool),
+            TypeAbi::Int => Some(Type::Int),
+            TypeAbi::B256 => Some(Type::B256),
+            TypeAbi::Tuple(fields) => fields
+                .iter()
+                .map(|f| {
+                    Some(Field {
+                        name: f.name.clone(),
+                        ty: f.ty.to_type()?,
+                    })
+                })
+                .collect::<Option<Vec<_>>>()
+                .map(Type::Tuple),
+            TypeAbi::Array { ty, size } => Some(Type::Array(
+                Box::new(ty.to_type()?),
+                usize::try_from(*size).ok()?,
+            )),
+            TypeAbi::Real | TypeAbi::String | TypeAbi::Optional(_) | TypeAbi::Map { .. } => None,
+        }
+    }
+}
+
+impl VarAbi {
+    /// Show the variable and its value decoded from the words.
+    ///
+    /// Falls back to the raw words if they can't be decoded.
+    /// e.g. `::amount: int = 5`
+    pub fn show(&self, words: &[Word]) -> String {
+        match self.ty.to_type() {
+            Some(ty) => format!("{}: {} = {}", self.name, ty, self.value(words)),
+            None => format!("{} = {}", self.name, self.value(words)),
+        }
+    }
+
+    /// The value decoded from the words or the raw words if they can't be decoded.
+    pub fn value(&self, words: &[Word]) -> String {
+        match self.ty.to_type().map(|ty| ty.decode(words, false)) {
+            Some(Ok(value)) => value,
+            _ => format!("{:?}", words),
+        }
+    }
+}
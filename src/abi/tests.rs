@@ -0,0 +1,72 @@
+use super::*;
+
+const ABI: &str = r#"{
+  "predicates": [
+    {
+      "name": "::Transfer",
+      "vars": [
+        { "name": "::key", "ty": "B256" },
+        { "name": "::amount", "ty": "Int" },
+        { "name": "::to", "ty": { "Tuple": [
+          { "name": "addr", "ty": "B256" },
+          { "name": null, "ty": "Bool" }
+        ] } },
+        { "name": "::ids", "ty": { "Array": { "ty": "Int", "size": 2 } } },
+        { "name": "::r", "ty": "Real" }
+      ],
+      "pub_vars": []
+    }
+  ],
+  "storage": [
+    { "name": "balances", "ty": { "Map": { "ty_from": "B256", "ty_to": "Int" } } }
+  ]
+}"#;
+
+#[test]
+fn test_parse_abi() {
+    let abi = parse_abi(ABI.as_bytes()).unwrap();
+    assert_eq!(abi.predicates.len(), 1);
+    assert_eq!(abi.storage.len(), 1);
+    let predicate = abi.predicate("Transfer").unwrap();
+    assert_eq!(abi.predicate("::Transfer"), Some(predicate));
+    assert!(abi.predicate("Mint").is_none());
+
+    let (i, var) = predicate.var("amount").unwrap();
+    assert_eq!(i, 1);
+    assert_eq!(var.ty, TypeAbi::Int);
+    assert_eq!(predicate.var("::to").unwrap().0, 2);
+    assert!(predicate.var("from").is_none());
+
+    assert!(parse_abi(b"[]").is_err());
+}
+
+#[test]
+fn test_to_type() {
+    let abi = parse_abi(ABI.as_bytes()).unwrap();
+    let types = abi.predicates[0]
+        .vars
+        .iter()
+        .map(|v| v.ty.to_type().map(|t| t.to_string()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        types,
+        [
+            Some("b256".to_string()),
+            Some("int".to_string()),
+            Some("{addr: b256, bool}".to_string()),
+            Some("int[2]".to_string()),
+            None,
+        ]
+    );
+    assert_eq!(abi.storage[0].ty.to_type(), None);
+}
+
+#[test]
+fn test_show() {
+    let abi = parse_abi(ABI.as_bytes()).unwrap();
+    let vars = &abi.predicates[0].vars;
+    assert_eq!(vars[1].show(&[5]), "::amount: int = 5");
+    assert_eq!(vars[3].show(&[1, 2]), "::ids: int[2] = [1, 2]");
+    assert_eq!(vars[1].show(&[]), "::amount: int = []");
+    assert_eq!(vars[4].show(&[7]), "::r = [7]");
+}
@@ -0,0 +1,320 @@
+use std::{collections::BTreeMap, fmt::Display, str::FromStr};
+
+use anyhow::bail;
+use essential_constraint_asm::{Op, Opcode, ToOpcode};
+
+use crate::{
+    condition::{Condition, Context},
+    opcode::OpName,
+};
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BreakpointKind {
+    /// Break on every step. Only useful with a condition.
+    Any,
+    /// Break when the step position reaches this value.
+    Pos(usize),
+    /// Break when the program counter reaches this value.
+    Pc(usize),
+    /// Break before an op matching any of these filters is played.
+    Op(Vec<OpFilter>),
+}
+
+/// Matches ops by category and optionally variant.
+///
+/// e.g. `Access::State`, `Pred::Eq`, `Crypto::*` or `Crypto`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpFilter {
+    pub category: String,
+    pub variant: Option<String>,
+    /// The opcodes this filter matches.
+    opcodes: Vec<Opcode>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub kind: BreakpointKind,
+    /// Only break if this condition holds.
+    pub condition: Option<Condition>,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Breakpoints {
+    next_id: usize,
+    breakpoints: BTreeMap<usize, Breakpoint>,
+    /// A breakpoint was hit before the first op was played.
+    pub(crate) hit_at_start: bool,
+}
+
+impl Breakpoints {
+    /// Add an enabled breakpoint and return its id.
+    pub fn add(&mut self, kind: BreakpointKind) -> usize {
+        self.add_with_condition(kind, None)
+    }
+
+    /// Add an enabled breakpoint that only breaks when
+    /// the condition holds and return its id.
+    pub fn add_with_condition(
+        &mut self,
+        kind: BreakpointKind,
+        condition: Option<Condition>,
+    ) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.breakpoints.insert(
+            id,
+            Breakpoint {
+                kind,
+                condition,
+                enabled: true,
+            },
+        );
+        id
+    }
+
+    /// Remove a breakpoint. Returns false if it doesn't exist.
+    pub fn remove(&mut self, id: usize) -> bool {
+        self.breakpoints.remove(&id).is_some()
+    }
+
+    /// Enable or disable a breakpoint. Returns false if it doesn't exist.
+    pub fn set_enabled(&mut self, id: usize, enabled: bool) -> bool {
+        match self.breakpoints.get_mut(&id) {
+            Some(bp) => {
+                bp.enabled = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Breakpoint> {
+        self.breakpoints.get(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &Breakpoint)> {
+        self.breakpoints.iter().map(|(id, bp)| (*id, bp))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.breakpoints.is_empty()
+    }
+
+    /// Does any enabled breakpoint have a condition that reads memory.
+    pub fn reads_memory(&self) -> bool {
+        self.iter()
+            .any(|(_, bp)| bp.enabled && bp.condition.as_ref().is_some_and(Condition::reads_memory))
+    }
+
+    /// Find the first enabled breakpoint that matches the given context.
+    pub fn hit(&self, ctx: &Context) -> Option<usize> {
+        self.iter()
+            .find(|(_, bp)| bp.enabled && bp.hit(ctx))
+            .map(|(id, _)| id)
+    }
+
+    /// Run a `break` command and return the output.
+    pub fn command(&mut self, args: &str) -> String {
+        let (args, condition) = match args.split_once(" if ") {
+            Some((args, condition)) => (args, Some(condition)),
+            None => match args.strip_prefix("if ") {
+                Some(condition) => ("", Some(condition)),
+                None => (args, None),
+            },
+        };
+        let condition = match condition.map(|c| c.parse::<Condition>()).transpose() {
+            Ok(condition) => condition,
+            Err(e) => return format!("Invalid condition: {}", e),
+        };
+
+        let mut args = args.split(' ').filter(|s| !s.is_empty());
+        let parse_id = |id: Option<&str>| id.and_then(|id| id.parse::<usize>().ok());
+        let kind = match args.next() {
+            None if condition.is_some() => BreakpointKind::Any,
+            None | Some("l") | Some("list") => return self.to_string(),
+            Some("pc") => match parse_id(args.next()) {
+                Some(pc) => BreakpointKind::Pc(pc),
+                None => return "Expected a pc value. e.g. `break pc 57`".to_string(),
+            },
+            Some("pos") => match parse_id(args.next()) {
+                Some(pos) => BreakpointKind::Pos(pos),
+                None => return "Expected a position. e.g. `break pos 57`".to_string(),
+            },
+            Some("op") => {
+                let filters = args
+                    .flat_map(|s| s.split(','))
+                    .filter(|s| !s.is_empty())
+                    .map(|s| s.parse::<OpFilter>())
+                    .collect::<anyhow::Result<Vec<_>>>();
+                match filters {
+                    Ok(filters) if !filters.is_empty() => BreakpointKind::Op(filters),
+                    Ok(_) => {
+                        return "Expected an op kind. e.g. `break op Access::State`".to_string()
+                    }
+                    Err(e) => return e.to_string(),
+                }
+            }
+            Some(cmd @ ("d" | "delete" | "e" | "enable" | "dis" | "disable")) => {
+                let Some(id) = parse_id(args.next()) else {
+                    return format!("Expected a breakpoint id. e.g. `break {} 0`", cmd);
+                };
+                let found = match cmd {
+                    "d" | "delete" => self.remove(id),
+                    "e" | "enable" => self.set_enabled(id, true),
+                    _ => self.set_enabled(id, false),
+                };
+                return if found {
+                    match cmd {
+                        "d" | "delete" => format!("Breakpoint {} deleted", id),
+                        "e" | "enable" => format!("Breakpoint {} enabled", id),
+                        _ => format!("Breakpoint {} disabled", id),
+                    }
+                } else {
+                    format!("No breakpoint with id {}", id)
+                };
+            }
+            Some(pos) => match pos.parse::<usize>() {
+                Ok(pos) => BreakpointKind::Pos(pos),
+                Err(_) => return format!("Unknown break command: {}", pos),
+            },
+        };
+        let id = self.add_with_condition(kind, condition);
+        format!("Breakpoint {} set: {}", id, self.breakpoints[&id])
+    }
+}
+
+impl Breakpoint {
+    /// Does this breakpoint match the given context.
+    /// Ignores whether the breakpoint is enabled.
+    pub fn hit(&self, ctx: &Context) -> bool {
+        let at = match &self.kind {
+            BreakpointKind::Any => true,
+            BreakpointKind::Pos(p) => *p == ctx.pos,
+            BreakpointKind::Pc(p) => *p == ctx.pc,
+            BreakpointKind::Op(filters) => ctx
+                .op
+                .as_ref()
+                .is_some_and(|op| filters.iter().any(|f| f.matches(op))),
+        };
+        match &self.condition {
+            Some(condition) => at && condition.eval(ctx),
+            None => at,
+        }
+    }
+}
+
+impl OpFilter {
+    pub fn matches(&self, op: &Op) -> bool {
+        self.opcodes.contains(&op.to_opcode())
+    }
+}
+
+impl FromStr for OpFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (category, variant) = match s.split_once("::") {
+            Some((category, "*")) => (category, None),
+            Some((category, variant)) => (category, Some(variant)),
+            None => (s, None),
+        };
+        let opcodes = Opcode::by_name();
+        let named = opcodes
+            .iter()
+            .filter_map(|(name, opcode)| {
+                let (c, v) = name.split_once("::")?;
+                Some((c, v, *opcode))
+            })
+            .collect::<Vec<_>>();
+
+        let Some((category, ..)) = named
+            .iter()
+            .find(|(c, ..)| c.eq_ignore_ascii_case(category))
+        else {
+            let mut categories = named.iter().map(|(c, ..)| *c).collect::<Vec<_>>();
+            categories.dedup();
+            bail!(
+                "Unknown op category `{}`. Expected one of: {:?}",
+                category,
+                categories
+            );
+        };
+        let in_category = named.iter().filter(|(c, ..)| c == category);
+        let (variant, opcodes) = match variant {
+            None => (None, in_category.map(|(.., o)| *o).collect()),
+            Some(variant) => {
+                let Some((_, v, opcode)) = in_category
+                    .clone()
+                    .find(|(_, v, _)| v.eq_ignore_ascii_case(variant))
+                else {
+                    bail!(
+                        "Unknown op `{}::{}`. Expected one of: {:?}",
+                        category,
+                        variant,
+                        in_category.map(|(_, v, _)| *v).collect::<Vec<_>>()
+                    );
+                };
+                (Some(v.to_string()), vec![*opcode])
+            }
+        };
+        Ok(OpFilter {
+            category: category.to_string(),
+            variant,
+            opcodes,
+        })
+    }
+}
+
+impl Display for OpFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.variant {
+            Some(variant) => write!(f, "{}::{}", self.category, variant),
+            None => write!(f, "{}::*", self.category),
+        }
+    }
+}
+
+impl Display for BreakpointKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BreakpointKind::Any => write!(f, "any step"),
+            BreakpointKind::Pos(pos) => write!(f, "position {}", pos),
+            BreakpointKind::Pc(pc) => write!(f, "pc {}", pc),
+            BreakpointKind::Op(filters) => {
+                let filters = filters
+                    .iter()
+                    .map(|f| f.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "op {}", filters)
+            }
+        }
+    }
+}
+
+impl Display for Breakpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(condition) = &self.condition {
+            write!(f, " if {}", condition)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Breakpoints {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return write!(f, "No breakpoints set.");
+        }
+        for (id, bp) in self.iter() {
+            let state = if bp.enabled { "enabled" } else { "disabled" };
+            writeln!(f, "{}: {} ({})", id, bp, state)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,114 @@
+use super::*;
+
+fn ctx<'a>(pos: usize, pc: usize, stack: &'a [i64]) -> Context<'a> {
+    Context {
+        pos,
+        pc,
+        stack,
+        memory: &[],
+        decision_vars: &[],
+        op: None,
+    }
+}
+
+#[test]
+fn test_breakpoints() {
+    let mut bps = Breakpoints::default();
+    let a = bps.add(BreakpointKind::Pos(3));
+    let b = bps.add(BreakpointKind::Pc(7));
+    assert_eq!(bps.hit(&ctx(3, 0, &[])), Some(a));
+    assert_eq!(bps.hit(&ctx(0, 7, &[])), Some(b));
+    assert_eq!(bps.hit(&ctx(1, 1, &[])), None);
+
+    assert!(bps.set_enabled(a, false));
+    assert_eq!(bps.hit(&ctx(3, 0, &[])), None);
+    assert!(bps.set_enabled(a, true));
+    assert_eq!(bps.hit(&ctx(3, 0, &[])), Some(a));
+
+    assert!(bps.remove(b));
+    assert!(!bps.remove(b));
+    assert_eq!(bps.hit(&ctx(0, 7, &[])), None);
+}
+
+#[test]
+fn test_conditional_breakpoints() {
+    let mut bps = Breakpoints::default();
+    let a = bps.add_with_condition(BreakpointKind::Any, Some("top == 0".parse().unwrap()));
+    let b = bps.add_with_condition(BreakpointKind::Pc(2), Some("depth > 1".parse().unwrap()));
+    assert_eq!(bps.hit(&ctx(0, 0, &[1])), None);
+    assert_eq!(bps.hit(&ctx(0, 0, &[1, 0])), Some(a));
+    assert_eq!(bps.hit(&ctx(0, 2, &[1, 1])), Some(b));
+    assert_eq!(bps.hit(&ctx(0, 2, &[1])), None);
+    assert!(!bps.reads_memory());
+
+    let c = bps.add_with_condition(
+        BreakpointKind::Any,
+        Some("top == 1 || memlen > 0".parse().unwrap()),
+    );
+    assert!(bps.reads_memory());
+    bps.set_enabled(c, false);
+    assert!(!bps.reads_memory());
+}
+
+#[test]
+fn test_op_breakpoints() {
+    use essential_constraint_asm as asm;
+
+    let mut bps = Breakpoints::default();
+    let a = bps.add(BreakpointKind::Op(vec!["Access::State".parse().unwrap()]));
+    let b = bps.add(BreakpointKind::Op(vec![
+        "pred::eq".parse().unwrap(),
+        "Crypto::*".parse().unwrap(),
+    ]));
+    let with_op = |op: Op| Context {
+        op: Some(op),
+        ..ctx(0, 0, &[])
+    };
+    assert_eq!(bps.hit(&with_op(asm::Access::State.into())), Some(a));
+    assert_eq!(bps.hit(&with_op(asm::Access::StateLen.into())), None);
+    assert_eq!(bps.hit(&with_op(asm::Pred::Eq.into())), Some(b));
+    assert_eq!(bps.hit(&with_op(asm::Pred::EqRange.into())), None);
+    assert_eq!(bps.hit(&with_op(asm::Crypto::Sha256.into())), Some(b));
+    assert_eq!(bps.hit(&with_op(asm::Stack::Push(1).into())), None);
+    assert_eq!(bps.hit(&ctx(0, 0, &[])), None);
+
+    assert!("Foo::Bar".parse::<OpFilter>().is_err());
+    assert!("Stack::".parse::<OpFilter>().is_err());
+    let e = "Pred::Eqq".parse::<OpFilter>().unwrap_err();
+    assert!(e.to_string().starts_with("Unknown op `Pred::Eqq`"), "{}", e);
+
+    // Names are shown as the opcodes spell them.
+    let filter: OpFilter = "stack::push".parse().unwrap();
+    assert_eq!(filter.to_string(), "Stack::Push");
+    assert!(filter.matches(&asm::Stack::Push(7).into()));
+    assert!(!filter.matches(&asm::Stack::Pop.into()));
+}
+
+#[test]
+fn test_break_command() {
+    let mut bps = Breakpoints::default();
+    assert_eq!(bps.command("57"), "Breakpoint 0 set: position 57");
+    assert_eq!(bps.command("pc 12"), "Breakpoint 1 set: pc 12");
+    assert_eq!(
+        bps.command("if stack[1] != 3"),
+        "Breakpoint 2 set: any step if stack[1] != 3"
+    );
+    assert_eq!(
+        bps.command("pc 4 if top==0"),
+        "Breakpoint 3 set: pc 4 if top == 0"
+    );
+    assert_eq!(
+        bps.command("op Access::State, Crypto"),
+        "Breakpoint 4 set: op Access::State, Crypto::*"
+    );
+    assert_eq!(bps.command("delete 4"), "Breakpoint 4 deleted");
+    assert_eq!(bps.command("disable 0"), "Breakpoint 0 disabled");
+    assert_eq!(
+        bps.command("list"),
+        "0: position 57 (disabled)\n1: pc 12 (enabled)\n2: any step if stack[1] != 3 (enabled)\n3: pc 4 if top == 0 (enabled)\n"
+    );
+    assert_eq!(bps.command("delete 1"), "Breakpoint 1 deleted");
+    assert_eq!(bps.command("enable 1"), "No breakpoint with id 1");
+    assert_eq!(bps.command("foo"), "Unknown break command: foo");
+    assert!(bps.command("if top =").starts_with("Invalid condition"));
+}
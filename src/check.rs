@@ -0,0 +1,78 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+};
+
+use essential_types::{
+    predicate::Predicate,
+    solution::{Solution, SolutionDataIndex},
+    ContentAddress, Key, Value, Word,
+};
+
+use crate::{ConstraintDebugger, GasConfig, OutOfGas};
+
+/// How a constraint finished when played to the end.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckOutcome {
+    /// The program ended with `[1]` on the stack.
+    Pass,
+    /// The program ended with `[0]` on the stack.
+    False,
+    /// The program panicked with this error.
+    Panic(String),
+    /// The program ended with something other than a single bool on the stack.
+    UnexpectedStack(Vec<Word>),
+    /// An op would have spent more than the gas limit.
+    OutOfGas(OutOfGas),
+}
+
+impl CheckOutcome {
+    pub fn is_pass(&self) -> bool {
+        matches!(self, CheckOutcome::Pass)
+    }
+}
+
+/// Play every constraint of the predicate to the end.
+///
+/// Returns one outcome per constraint in order.
+/// State is read once and shared by every constraint.
+pub async fn check_predicate(
+    solution: &Solution,
+    index: SolutionDataIndex,
+    predicate: &Predicate,
+    state: &HashMap<ContentAddress, BTreeMap<Key, Value>>,
+    gas: &GasConfig,
+) -> anyhow::Result<Vec<CheckOutcome>> {
+    if predicate.constraints.is_empty() {
+        return Ok(vec![]);
+    }
+    let mut debugger = ConstraintDebugger::new_with_gas(
+        solution.clone(),
+        index,
+        predicate.clone(),
+        0,
+        state.clone(),
+        gas.clone(),
+    )
+    .await?;
+    predicate
+        .constraints
+        .iter()
+        .map(|code| {
+            debugger.set_constraint(code.clone())?;
+            debugger.start_session().play_till_end()
+        })
+        .collect()
+}
+
+impl Display for CheckOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CheckOutcome::Pass => write!(f, "pass"),
+            CheckOutcome::False => write!(f, "false"),
+            CheckOutcome::Panic(e) => write!(f, "panic: {}", e),
+            CheckOutcome::UnexpectedStack(stack) => write!(f, "unexpected stack: {:?}", stack),
+            CheckOutcome::OutOfGas(e) => write!(f, "{}", e),
+        }
+    }
+}
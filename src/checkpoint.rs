@@ -0,0 +1,166 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use essential_constraint_asm::Op;
+use essential_constraint_vm::{Memory, Stack};
+use essential_types::Word;
+
+use crate::{Gas, RepeatSlot};
+
+#[cfg(test)]
+mod tests;
+
+/// Default memory budget for checkpoints in bytes.
+pub const DEFAULT_BUDGET: usize = 16 * 1024 * 1024;
+
+/// Default number of steps between checkpoints.
+const DEFAULT_INTERVAL: usize = 16;
+
+/// A snapshot of the VM taken during forward execution.
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    pub pos: usize,
+    pub pc: usize,
+    pub stack: Stack,
+    pub memory: Vec<Word>,
+    pub repeat: Vec<RepeatSlot>,
+    pub last_op: Option<Op>,
+    /// Gas spent up to this position.
+    pub gas: Gas,
+}
+
+/// A snapshot that can be kept in [`Checkpoints`].
+pub trait Snapshot {
+    /// The position the snapshot was taken at.
+    fn pos(&self) -> usize;
+
+    /// Approximate number of bytes used by this snapshot.
+    fn size(&self) -> usize;
+}
+
+/// Periodic snapshots used to travel back in time without replaying from the start.
+///
+/// A checkpoint is taken every `interval` steps. When the snapshots exceed
+/// the memory budget every other checkpoint is dropped and the interval doubles.
+#[derive(Debug, Clone)]
+pub struct Checkpoints<C = Checkpoint> {
+    checkpoints: BTreeMap<usize, C>,
+    interval: usize,
+    budget: usize,
+    size: usize,
+}
+
+impl<C: Snapshot> Default for Checkpoints<C> {
+    fn default() -> Self {
+        Self::with_budget(DEFAULT_BUDGET)
+    }
+}
+
+impl<C: Snapshot> Checkpoints<C> {
+    pub fn with_budget(budget: usize) -> Self {
+        Self {
+            checkpoints: BTreeMap::new(),
+            interval: DEFAULT_INTERVAL,
+            budget,
+            size: 0,
+        }
+    }
+
+    /// Should a checkpoint be taken at this position.
+    pub fn wants(&self, pos: usize) -> bool {
+        pos > 0 && pos.is_multiple_of(self.interval) && !self.checkpoints.contains_key(&pos)
+    }
+
+    /// Record a checkpoint.
+    pub fn insert(&mut self, checkpoint: C) {
+        let size = checkpoint.size();
+        if size > self.budget {
+            return;
+        }
+        if let Some(old) = self.checkpoints.insert(checkpoint.pos(), checkpoint) {
+            self.size -= old.size();
+        }
+        self.size += size;
+        while self.size > self.budget {
+            self.thin();
+        }
+    }
+
+    /// The latest checkpoint at or before this position.
+    pub fn nearest(&self, pos: usize) -> Option<&C> {
+        self.checkpoints.range(..=pos).next_back().map(|(_, c)| c)
+    }
+
+    pub fn len(&self) -> usize {
+        self.checkpoints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.checkpoints.is_empty()
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Change the memory budget, dropping checkpoints if needed.
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        while self.size > self.budget {
+            self.thin();
+        }
+    }
+
+    /// Drop every other checkpoint and double the interval.
+    fn thin(&mut self) {
+        self.interval = self.interval.saturating_mul(2);
+        let interval = self.interval;
+        self.checkpoints
+            .retain(|pos, _| pos.is_multiple_of(interval));
+        self.size = self.checkpoints.values().map(C::size).sum();
+    }
+}
+
+impl Snapshot for Checkpoint {
+    fn pos(&self) -> usize {
+        self.pos
+    }
+
+    fn size(&self) -> usize {
+        let words = self.stack.len() + self.memory.len();
+        std::mem::size_of::<Self>()
+            + words * std::mem::size_of::<Word>()
+            + self.repeat.len() * std::mem::size_of::<RepeatSlot>()
+    }
+}
+
+impl Checkpoint {
+    /// Restore the VM to this checkpoint.
+    pub fn restore(&self, stack: &mut Stack, memory: &mut Memory, repeat: &mut Vec<RepeatSlot>) {
+        *stack = self.stack.clone();
+        *memory = restore_memory(&self.memory);
+        repeat.clone_from(&self.repeat);
+    }
+}
+
+pub(crate) fn restore_memory(words: &[Word]) -> Memory {
+    let mut memory = Memory::new();
+    // The words were read out of a valid memory so these can't fail.
+    let _ = memory.alloc(words.len() as Word);
+    for (i, w) in words.iter().enumerate() {
+        let _ = memory.store(i as Word, *w);
+    }
+    memory
+}
+
+impl<C> Display for Checkpoints<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} checkpoints every {} steps using {} of {} bytes",
+            self.checkpoints.len(),
+            self.interval,
+            self.size,
+            self.budget
+        )
+    }
+}
@@ -0,0 +1,44 @@
+use super::*;
+
+fn checkpoint(pos: usize, words: usize) -> Checkpoint {
+    Checkpoint {
+        pos,
+        pc: pos,
+        stack: vec![0; words].into(),
+        memory: vec![],
+        repeat: vec![],
+        last_op: None,
+        gas: 0,
+    }
+}
+
+#[test]
+fn test_restore_memory() {
+    let mut memory = restore_memory(&[1, 2, 3]);
+    assert_eq!(memory.len().unwrap(), 3);
+    assert_eq!(memory.load(2).unwrap(), 3);
+}
+
+#[test]
+fn test_checkpoints_budget() {
+    let size = checkpoint(0, 0).size();
+    let mut cps = Checkpoints::with_budget(size * 4);
+    assert!(!cps.wants(0));
+    assert!(!cps.wants(1));
+    for pos in (1..=6).map(|i| i * DEFAULT_INTERVAL) {
+        assert!(cps.wants(pos));
+        cps.insert(checkpoint(pos, 0));
+    }
+    // Thinned to every other checkpoint.
+    assert_eq!(cps.len(), 3);
+    assert!(!cps.wants(DEFAULT_INTERVAL * 3));
+    assert!(cps.wants(DEFAULT_INTERVAL * 8));
+    assert_eq!(
+        cps.nearest(DEFAULT_INTERVAL * 3).unwrap().pos,
+        DEFAULT_INTERVAL * 2
+    );
+    assert!(cps.nearest(DEFAULT_INTERVAL - 1).is_none());
+
+    cps.set_budget(size);
+    assert_eq!(cps.len(), 1);
+}
@@ -0,0 +1,339 @@
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::bail;
+use essential_constraint_asm::Op;
+use essential_types::{Value, Word};
+
+#[cfg(test)]
+mod tests;
+
+/// The state a condition is evaluated against.
+pub struct Context<'a> {
+    pub pos: usize,
+    pub pc: usize,
+    pub stack: &'a [Word],
+    pub memory: &'a [Word],
+    pub decision_vars: &'a [Value],
+    /// The op that will be played next.
+    pub op: Option<Op>,
+}
+
+/// A boolean expression over the current stack, memory and solution data.
+///
+/// e.g. `depth > 8`, `top == 0`, `memory[3] != 42 && dv[2][0] == stack[1]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+    Cmp(Operand, CmpOp, Operand),
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    Lit(Word),
+    /// Number of words on the stack.
+    Depth,
+    /// Number of words in memory.
+    MemoryLen,
+    Pos,
+    Pc,
+    /// Index from the bottom of the stack.
+    /// Negative indices count back from the top, so `-1` is the top.
+    Stack(isize),
+    Memory(usize),
+    /// Decision variable and word index.
+    DecisionVar(usize, usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Int(Word),
+    Minus,
+    Open,
+    Close,
+    Cmp(CmpOp),
+    And,
+    Or,
+}
+
+impl Condition {
+    /// Evaluate the condition.
+    ///
+    /// Comparisons against values that don't exist (e.g. reading past the
+    /// top of the stack) are false.
+    pub fn eval(&self, ctx: &Context) -> bool {
+        match self {
+            Condition::Cmp(lhs, op, rhs) => match (lhs.eval(ctx), rhs.eval(ctx)) {
+                (Some(lhs), Some(rhs)) => op.eval(lhs, rhs),
+                _ => false,
+            },
+            Condition::And(lhs, rhs) => lhs.eval(ctx) && rhs.eval(ctx),
+            Condition::Or(lhs, rhs) => lhs.eval(ctx) || rhs.eval(ctx),
+        }
+    }
+
+    /// Does the condition read memory, so it needs a copy of it to be evaluated.
+    pub fn reads_memory(&self) -> bool {
+        match self {
+            Condition::Cmp(lhs, _, rhs) => lhs.reads_memory() || rhs.reads_memory(),
+            Condition::And(lhs, rhs) | Condition::Or(lhs, rhs) => {
+                lhs.reads_memory() || rhs.reads_memory()
+            }
+        }
+    }
+}
+
+impl Operand {
+    fn reads_memory(&self) -> bool {
+        matches!(self, Operand::MemoryLen | Operand::Memory(_))
+    }
+
+    pub fn eval(&self, ctx: &Context) -> Option<Word> {
+        match self {
+            Operand::Lit(w) => Some(*w),
+            Operand::Depth => Word::try_from(ctx.stack.len()).ok(),
+            Operand::MemoryLen => Word::try_from(ctx.memory.len()).ok(),
+            Operand::Pos => Word::try_from(ctx.pos).ok(),
+            Operand::Pc => Word::try_from(ctx.pc).ok(),
+            Operand::Stack(i) => {
+                let i = if *i < 0 {
+                    ctx.stack.len().checked_sub(i.unsigned_abs())?
+                } else {
+                    *i as usize
+                };
+                ctx.stack.get(i).copied()
+            }
+            Operand::Memory(i) => ctx.memory.get(*i).copied(),
+            Operand::DecisionVar(i, j) => ctx.decision_vars.get(*i)?.get(*j).copied(),
+        }
+    }
+}
+
+impl CmpOp {
+    pub fn eval(&self, lhs: Word, rhs: Word) -> bool {
+        match self {
+            CmpOp::Eq => lhs == rhs,
+            CmpOp::Ne => lhs != rhs,
+            CmpOp::Lt => lhs < rhs,
+            CmpOp::Le => lhs <= rhs,
+            CmpOp::Gt => lhs > rhs,
+            CmpOp::Ge => lhs >= rhs,
+        }
+    }
+}
+
+impl FromStr for Condition {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(s)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let condition = parser.or()?;
+        if let Some(t) = parser.peek() {
+            bail!("Unexpected token {:?} in condition", t);
+        }
+        Ok(condition)
+    }
+}
+
+fn tokenize(s: &str) -> anyhow::Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '[' => Token::Open,
+            ']' => Token::Close,
+            '-' => Token::Minus,
+            '=' | '!' | '<' | '>' => {
+                let eq = chars.next_if_eq(&'=').is_some();
+                match (c, eq) {
+                    ('=', true) => Token::Cmp(CmpOp::Eq),
+                    ('!', true) => Token::Cmp(CmpOp::Ne),
+                    ('<', true) => Token::Cmp(CmpOp::Le),
+                    ('>', true) => Token::Cmp(CmpOp::Ge),
+                    ('<', false) => Token::Cmp(CmpOp::Lt),
+                    ('>', false) => Token::Cmp(CmpOp::Gt),
+                    _ => bail!("Expected `{}=` in condition", c),
+                }
+            }
+            '&' | '|' => {
+                if chars.next_if_eq(&c).is_none() {
+                    bail!("Expected `{}{}` in condition", c, c);
+                }
+                if c == '&' {
+                    Token::And
+                } else {
+                    Token::Or
+                }
+            }
+            c if c.is_ascii_alphanumeric() || c == '_' => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                    word.push(c);
+                }
+                if word.starts_with(|c: char| c.is_ascii_digit()) {
+                    Token::Int(parse_int(&word)?)
+                } else {
+                    Token::Ident(word)
+                }
+            }
+            _ => bail!("Unexpected character `{}` in condition", c),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn parse_int(s: &str) -> anyhow::Result<Word> {
+    let r = match s.strip_prefix("0x") {
+        Some(hex) => Word::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    r.map_err(|_| anyhow::anyhow!("Invalid number `{}` in condition", s))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let t = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        t
+    }
+
+    fn expect(&mut self, token: Token) -> anyhow::Result<()> {
+        match self.next() {
+            Some(t) if t == token => Ok(()),
+            t => bail!("Expected {:?} in condition but found {:?}", token, t),
+        }
+    }
+
+    fn or(&mut self) -> anyhow::Result<Condition> {
+        let mut lhs = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            lhs = Condition::Or(Box::new(lhs), Box::new(self.and()?));
+        }
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> anyhow::Result<Condition> {
+        let mut lhs = self.cmp()?;
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            lhs = Condition::And(Box::new(lhs), Box::new(self.cmp()?));
+        }
+        Ok(lhs)
+    }
+
+    fn cmp(&mut self) -> anyhow::Result<Condition> {
+        let lhs = self.operand()?;
+        let op = match self.next() {
+            Some(Token::Cmp(op)) => op,
+            t => bail!("Expected a comparison in condition but found {:?}", t),
+        };
+        let rhs = self.operand()?;
+        Ok(Condition::Cmp(lhs, op, rhs))
+    }
+
+    fn index(&mut self) -> anyhow::Result<isize> {
+        self.expect(Token::Open)?;
+        let negative = self.peek() == Some(&Token::Minus);
+        if negative {
+            self.pos += 1;
+        }
+        let i = match self.next() {
+            Some(Token::Int(i)) => i as isize,
+            t => bail!("Expected an index in condition but found {:?}", t),
+        };
+        self.expect(Token::Close)?;
+        Ok(if negative { -i } else { i })
+    }
+
+    fn unsigned_index(&mut self) -> anyhow::Result<usize> {
+        let i = self.index()?;
+        usize::try_from(i).map_err(|_| anyhow::anyhow!("Index {} can't be negative", i))
+    }
+
+    fn operand(&mut self) -> anyhow::Result<Operand> {
+        let operand = match self.next() {
+            Some(Token::Int(w)) => Operand::Lit(w),
+            Some(Token::Minus) => match self.next() {
+                Some(Token::Int(w)) => Operand::Lit(w.wrapping_neg()),
+                t => bail!("Expected a number after `-` but found {:?}", t),
+            },
+            Some(Token::Ident(ident)) => match ident.as_str() {
+                "depth" | "len" => Operand::Depth,
+                "memlen" => Operand::MemoryLen,
+                "top" => Operand::Stack(-1),
+                "pos" => Operand::Pos,
+                "pc" => Operand::Pc,
+                "stack" => Operand::Stack(self.index()?),
+                "memory" | "mem" => Operand::Memory(self.unsigned_index()?),
+                "dv" => Operand::DecisionVar(self.unsigned_index()?, self.unsigned_index()?),
+                _ => bail!("Unknown operand `{}` in condition", ident),
+            },
+            t => bail!("Expected an operand in condition but found {:?}", t),
+        };
+        Ok(operand)
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Condition::Cmp(lhs, op, rhs) => write!(f, "{} {} {}", lhs, op, rhs),
+            Condition::And(lhs, rhs) => write!(f, "{} && {}", lhs, rhs),
+            Condition::Or(lhs, rhs) => write!(f, "{} || {}", lhs, rhs),
+        }
+    }
+}
+
+impl Display for Operand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operand::Lit(w) => write!(f, "{}", w),
+            Operand::Depth => write!(f, "depth"),
+            Operand::MemoryLen => write!(f, "memlen"),
+            Operand::Pos => write!(f, "pos"),
+            Operand::Pc => write!(f, "pc"),
+            Operand::Stack(-1) => write!(f, "top"),
+            Operand::Stack(i) => write!(f, "stack[{}]", i),
+            Operand::Memory(i) => write!(f, "memory[{}]", i),
+            Operand::DecisionVar(i, j) => write!(f, "dv[{}][{}]", i, j),
+        }
+    }
+}
+
+impl Display for CmpOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            CmpOp::Eq => "==",
+            CmpOp::Ne => "!=",
+            CmpOp::Lt => "<",
+            CmpOp::Le => "<=",
+            CmpOp::Gt => ">",
+            CmpOp::Ge => ">=",
+        };
+        write!(f, "{}", s)
+    }
+}
@@ -0,0 +1,52 @@
+use super::*;
+
+#[test]
+fn test_parse_condition() {
+    let c: Condition = "dv[2][0] == stack[1]".parse().unwrap();
+    assert_eq!(
+        c,
+        Condition::Cmp(Operand::DecisionVar(2, 0), CmpOp::Eq, Operand::Stack(1))
+    );
+    let c: Condition = "depth>8 || top == 0 && memory[3] != 0x2A".parse().unwrap();
+    assert_eq!(c.to_string(), "depth > 8 || top == 0 && memory[3] != 42");
+    assert!(matches!(c, Condition::Or(_, _)));
+
+    assert!("top".parse::<Condition>().is_err());
+    assert!("foo == 1".parse::<Condition>().is_err());
+    assert!("top = 1".parse::<Condition>().is_err());
+    assert!("memory[-1] == 1".parse::<Condition>().is_err());
+    assert!("top == 1 1".parse::<Condition>().is_err());
+}
+
+#[test]
+fn test_eval_condition() {
+    let decision_vars = vec![vec![5, 6]];
+    let ctx = Context {
+        pos: 3,
+        pc: 4,
+        stack: &[1, 2, 3],
+        memory: &[42],
+        decision_vars: &decision_vars,
+        op: None,
+    };
+    let eval = |s: &str| s.parse::<Condition>().unwrap().eval(&ctx);
+    assert!(eval("depth == 3"));
+    assert!(eval("top == 3"));
+    assert!(eval("stack[-3] == 1"));
+    assert!(eval("stack[0] < stack[1]"));
+    assert!(eval("memory[0] == 42"));
+    assert!(eval("dv[0][1] == 6"));
+    assert!(eval("pos == 3 && pc >= 4"));
+    assert!(eval("top == -1 || pc <= 4"));
+    assert!(!eval("stack[3] == 0"));
+    assert!(!eval("stack[3] != 0"));
+    assert!(!eval("dv[1][0] == 0"));
+}
+
+#[test]
+fn test_reads_memory() {
+    let reads = |s: &str| s.parse::<Condition>().unwrap().reads_memory();
+    assert!(reads("mem[2] == 0"));
+    assert!(reads("top == 1 && pc == 2 || memlen > 3"));
+    assert!(!reads("top == 1 && dv[0][0] == stack[-2]"));
+}
@@ -0,0 +1,550 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
+    path::Path,
+};
+
+use anyhow::bail;
+use essential_constraint_vm::OpAccess;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{
+    breakpoint::{BreakpointKind, OpFilter},
+    condition::Condition,
+    launch::LaunchArgs,
+    memory_words,
+    source::Source,
+    Outcome, Session,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// A constraint runs on a single thread.
+const THREAD_ID: i64 = 1;
+/// Source reference of the listing of the constraint's ops.
+const OPS_REFERENCE: i64 = 1;
+/// Variable references of the scopes.
+const STACK: i64 = 1;
+const MEMORY: i64 = 2;
+const DECISION_VARS: i64 = 3;
+const PRE_STATE: i64 = 4;
+const POST_STATE: i64 = 5;
+/// The largest message body that will be read.
+const MAX_MESSAGE_LEN: usize = 16 * 1024 * 1024;
+/// Debugger commands that move the position and so need the client to refresh.
+const MOVES: &[&str] = &[
+    "n", "next", "b", "back", "p", "play", "e", "end", "co", "continue",
+];
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    seq: i64,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    command: String,
+    #[serde(default)]
+    arguments: Value,
+}
+
+/// Read a message framed by a `Content-Length` header.
+///
+/// Returns `None` at the end of the input.
+/// Messages longer than 16 MiB are rejected before they are read.
+pub fn read_message(input: &mut impl BufRead) -> anyhow::Result<Option<Value>> {
+    let mut len = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            match len {
+                Some(len) => {
+                    let mut body = vec![0; len];
+                    input.read_exact(&mut body)?;
+                    return Ok(Some(serde_json::from_slice(&body)?));
+                }
+                None => continue,
+            }
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("Content-Length") {
+                let value = value.trim().parse::<usize>().map_err(|e| {
+                    anyhow::anyhow!("Invalid Content-Length `{}`: {}", value.trim(), e)
+                })?;
+                if value > MAX_MESSAGE_LEN {
+                    bail!(
+                        "Content-Length {} is over the limit of {} bytes",
+                        value,
+                        MAX_MESSAGE_LEN
+                    );
+                }
+                len = Some(value);
+            }
+        }
+    }
+}
+
+/// Write a message framed by a `Content-Length` header.
+pub fn write_message(output: &mut impl Write, message: &Value) -> anyhow::Result<()> {
+    let body = serde_json::to_string(message)?;
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()?;
+    Ok(())
+}
+
+/// Serve the Debug Adapter Protocol until the client disconnects or the input ends.
+///
+/// The client must send `launch` before any other request except `initialize`.
+/// `initialized` is sent once the constraint is loaded.
+pub async fn run_dap(mut input: impl BufRead, output: impl Write) -> anyhow::Result<()> {
+    let mut conn = Connection { output, seq: 0 };
+
+    let launch = loop {
+        let Some(request) = next_request(&mut input)? else {
+            return Ok(());
+        };
+        match request.command.as_str() {
+            "initialize" => conn.respond(&request, capabilities())?,
+            "launch" => break request,
+            "disconnect" => return conn.respond(&request, json!({})),
+            _ => conn.fail(&request, "The debugger has not been launched")?,
+        }
+    };
+    let loaded = match serde_json::from_value::<LaunchArgs>(launch.arguments.clone()) {
+        Ok(args) => args
+            .debugger()
+            .await
+            .map(|(debugger, source)| (debugger, source, args.stop_on_entry.unwrap_or(true))),
+        Err(e) => Err(anyhow::anyhow!("Invalid launch arguments: {}", e)),
+    };
+    let (mut debugger, source, stop_on_entry) = match loaded {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            conn.fail(&launch, &format!("Failed to launch: {}", e))?;
+            return conn.event("terminated", json!({}));
+        }
+    };
+    conn.respond(&launch, json!({}))?;
+    conn.event("initialized", json!({}))?;
+
+    let mut session = debugger.start_session();
+    let mut adapter = Adapter {
+        session: &mut session,
+        source,
+        stop_on_entry,
+        source_breakpoints: HashMap::new(),
+        events: Vec::new(),
+    };
+    while let Some(request) = next_request(&mut input)? {
+        let disconnect = matches!(request.command.as_str(), "disconnect" | "terminate");
+        match adapter.handle(&request) {
+            Ok(body) => conn.respond(&request, body)?,
+            Err(e) => conn.fail(&request, &e.to_string())?,
+        }
+        for (event, body) in adapter.events.drain(..) {
+            conn.event(event, body)?;
+        }
+        if disconnect {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Read the next request, skipping any other messages.
+fn next_request(input: &mut impl BufRead) -> anyhow::Result<Option<Request>> {
+    while let Some(message) = read_message(input)? {
+        let request: Request = serde_json::from_value(message)?;
+        if request.kind == "request" {
+            return Ok(Some(request));
+        }
+    }
+    Ok(None)
+}
+
+fn capabilities() -> Value {
+    json!({
+        "supportsConfigurationDoneRequest": true,
+        "supportsFunctionBreakpoints": true,
+        "supportsConditionalBreakpoints": true,
+        "supportsStepBack": true,
+        "supportsTerminateRequest": true,
+    })
+}
+
+struct Connection<W> {
+    output: W,
+    seq: i64,
+}
+
+impl<W: Write> Connection<W> {
+    fn send(&mut self, mut message: Value) -> anyhow::Result<()> {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+        write_message(&mut self.output, &message)
+    }
+
+    fn respond(&mut self, request: &Request, body: Value) -> anyhow::Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request.seq,
+            "success": true,
+            "command": request.command,
+            "body": body,
+        }))
+    }
+
+    fn fail(&mut self, request: &Request, message: &str) -> anyhow::Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request.seq,
+            "success": false,
+            "command": request.command,
+            "message": message,
+        }))
+    }
+
+    fn event(&mut self, event: &str, body: Value) -> anyhow::Result<()> {
+        self.send(json!({
+            "type": "event",
+            "event": event,
+            "body": body,
+        }))
+    }
+}
+
+/// Handles requests once the constraint is loaded.
+struct Adapter<'s, 'a> {
+    session: &'s mut Session<'a>,
+    source: Option<Source>,
+    stop_on_entry: bool,
+    /// Ids of the breakpoints set for each source so they can be replaced.
+    source_breakpoints: HashMap<String, Vec<usize>>,
+    /// Events to send after the response.
+    events: Vec<(&'static str, Value)>,
+}
+
+impl Adapter<'_, '_> {
+    fn handle(&mut self, request: &Request) -> anyhow::Result<Value> {
+        let args = &request.arguments;
+        match request.command.as_str() {
+            "initialize" => Ok(capabilities()),
+            "configurationDone" => {
+                if self.stop_on_entry {
+                    self.stopped("entry", None, vec![]);
+                } else {
+                    self.run()?;
+                }
+                Ok(json!({}))
+            }
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "constraint" }] })),
+            "setBreakpoints" => self.set_breakpoints(args),
+            "setFunctionBreakpoints" => self.set_function_breakpoints(args),
+            "setExceptionBreakpoints" => Ok(json!({})),
+            "stackTrace" => Ok(self.stack_trace()),
+            "scopes" => Ok(scopes()),
+            "variables" => Ok(self.variables(args["variablesReference"].as_i64().unwrap_or(0))),
+            "source" => Ok(json!({ "content": self.ops_listing() })),
+            "next" | "stepIn" | "stepOut" => {
+                let outcome = self.session.step_forward()?;
+                self.report(outcome);
+                Ok(json!({}))
+            }
+            "stepBack" => {
+                self.session.back(&mut String::new())?;
+                self.stopped("step", None, vec![]);
+                Ok(json!({}))
+            }
+            "continue" => {
+                self.run()?;
+                Ok(json!({ "allThreadsContinued": true }))
+            }
+            "pause" => Ok(json!({})),
+            "evaluate" => self.evaluate(args),
+            "disconnect" | "terminate" => Ok(json!({})),
+            command => bail!("Unsupported request `{}`", command),
+        }
+    }
+
+    fn stopped(&mut self, reason: &str, description: Option<String>, breakpoints: Vec<usize>) {
+        self.events.push((
+            "stopped",
+            json!({
+                "reason": reason,
+                "description": description,
+                "text": description,
+                "threadId": THREAD_ID,
+                "allThreadsStopped": true,
+                "hitBreakpointIds": breakpoints,
+            }),
+        ));
+    }
+
+    fn output(&mut self, output: String) {
+        self.events.push((
+            "output",
+            json!({ "category": "console", "output": format!("{}\n", output) }),
+        ));
+    }
+
+    fn report(&mut self, outcome: Outcome) {
+        match outcome {
+            Outcome::Step => self.stopped("step", None, vec![]),
+            Outcome::ProgramEnd => {
+                let outcome = self.session.end_outcome();
+                self.output(format!("Program ended: {}", outcome));
+                self.stopped("step", Some("Program ended".to_string()), vec![]);
+            }
+            Outcome::Panic(e) => {
+                let description = format!("Program panic: {:?}", e);
+                self.output(description.clone());
+                self.stopped("exception", Some(description), vec![]);
+            }
+            Outcome::OutOfGas(e) => {
+                let description = format!("Program ran {}", e);
+                self.output(description.clone());
+                self.stopped("exception", Some(description), vec![]);
+            }
+        }
+    }
+
+    /// Play until a breakpoint is hit or the program ends.
+    fn run(&mut self) -> anyhow::Result<()> {
+        match self.session.run_to_breakpoint()? {
+            (_, Some(id)) => self.stopped("breakpoint", None, vec![id]),
+            (outcome, None) => self.report(outcome),
+        }
+        Ok(())
+    }
+
+    fn set_breakpoints(&mut self, args: &Value) -> anyhow::Result<Value> {
+        let source = &args["source"];
+        let reference = source["sourceReference"].as_i64().unwrap_or(0);
+        let path = source["path"].as_str();
+        let key = match path {
+            Some(path) => path.to_string(),
+            None => format!("reference {}", reference),
+        };
+        for id in self.source_breakpoints.remove(&key).unwrap_or_default() {
+            self.session.breakpoints.remove(id);
+        }
+        let is_predicate_source = match (path, &self.source) {
+            (Some(path), Some(source)) => source.is_path(Path::new(path)),
+            _ => false,
+        };
+        let ops = self.ops_len();
+
+        let mut ids = Vec::new();
+        let mut breakpoints = Vec::new();
+        for bp in args["breakpoints"].as_array().into_iter().flatten() {
+            let Some(line) = bp["line"].as_u64().map(|l| l as usize) else {
+                continue;
+            };
+            let pc = if reference == OPS_REFERENCE {
+                line.checked_sub(1).filter(|pc| *pc < ops)
+            } else if is_predicate_source {
+                self.source
+                    .as_ref()
+                    .and_then(|source| source.first_op_on_line(line.saturating_sub(1)))
+            } else {
+                None
+            };
+            let Some(pc) = pc else {
+                breakpoints.push(json!({
+                    "verified": false,
+                    "line": line,
+                    "message": "No op found on this line",
+                }));
+                continue;
+            };
+            match parse_condition(&bp["condition"]) {
+                Ok(condition) => {
+                    let id = self
+                        .session
+                        .breakpoints
+                        .add_with_condition(BreakpointKind::Pc(pc), condition);
+                    ids.push(id);
+                    breakpoints.push(json!({ "id": id, "verified": true, "line": line }));
+                }
+                Err(e) => breakpoints.push(json!({
+                    "verified": false,
+                    "line": line,
+                    "message": e.to_string(),
+                })),
+            }
+        }
+        self.source_breakpoints.insert(key, ids);
+        Ok(json!({ "breakpoints": breakpoints }))
+    }
+
+    /// Function breakpoints break on ops, e.g. `Access::State, Pred::Eq`.
+    fn set_function_breakpoints(&mut self, args: &Value) -> anyhow::Result<Value> {
+        let key = "functions".to_string();
+        for id in self.source_breakpoints.remove(&key).unwrap_or_default() {
+            self.session.breakpoints.remove(id);
+        }
+        let mut ids = Vec::new();
+        let mut breakpoints = Vec::new();
+        for bp in args["breakpoints"].as_array().into_iter().flatten() {
+            let filters = bp["name"]
+                .as_str()
+                .unwrap_or_default()
+                .split(',')
+                .map(|f| f.trim().parse::<OpFilter>())
+                .collect::<anyhow::Result<Vec<_>>>();
+            let added = filters.and_then(|filters| {
+                let condition = parse_condition(&bp["condition"])?;
+                Ok(self
+                    .session
+                    .breakpoints
+                    .add_with_condition(BreakpointKind::Op(filters), condition))
+            });
+            match added {
+                Ok(id) => {
+                    ids.push(id);
+                    breakpoints.push(json!({ "id": id, "verified": true }));
+                }
+                Err(e) => breakpoints.push(json!({
+                    "verified": false,
+                    "message": e.to_string(),
+                })),
+            }
+        }
+        self.source_breakpoints.insert(key, ids);
+        Ok(json!({ "breakpoints": breakpoints }))
+    }
+
+    /// A single frame at the op about to be played.
+    fn stack_trace(&self) -> Value {
+        let pc = *self.session.pc;
+        let name = match (&*self.session.code).op_access(pc) {
+            Some(Ok(op)) => format!("{:?}", op),
+            _ => "Program end".to_string(),
+        };
+        let location = self.source.as_ref().and_then(|source| {
+            let (line, column) = source.location(pc)?;
+            Some((source.path.as_ref()?, line, column))
+        });
+        let frame = match location {
+            Some((path, line, column)) => json!({
+                "id": 0,
+                "name": name,
+                "line": line + 1,
+                "column": column + 1,
+                "source": { "name": path.file_name().map(|n| n.to_string_lossy()), "path": path },
+                "instructionPointerReference": pc.to_string(),
+            }),
+            None => json!({
+                "id": 0,
+                "name": name,
+                "line": pc + 1,
+                "column": 1,
+                "source": { "name": "constraint.ops", "sourceReference": OPS_REFERENCE },
+                "instructionPointerReference": pc.to_string(),
+            }),
+        };
+        json!({ "stackFrames": [frame], "totalFrames": 1 })
+    }
+
+    fn variables(&mut self, reference: i64) -> Value {
+        let words = |words: &[essential_types::Word]| {
+            words
+                .iter()
+                .enumerate()
+                .map(|(i, w)| variable(format!("[{}]", i), w.to_string()))
+                .collect::<Vec<_>>()
+        };
+        let slots = |slots: &[Vec<essential_types::Word>]| {
+            slots
+                .iter()
+                .enumerate()
+                .map(|(i, v)| variable(format!("[{}]", i), format!("{:?}", v)))
+                .collect::<Vec<_>>()
+        };
+        let variables = match reference {
+            STACK => words(&self.session.stack[..]),
+            MEMORY => words(&memory_words(self.session.memory)),
+            DECISION_VARS => {
+                let data = self.session.data;
+                data.decision_variables
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| {
+                        match self.session.abi.and_then(|abi| abi.predicate.vars.get(i)) {
+                            Some(var) => variable(var.name.clone(), var.value(v)),
+                            None => variable(format!("[{}]", i), format!("{:?}", v)),
+                        }
+                    })
+                    .collect()
+            }
+            PRE_STATE => slots(self.session.pre),
+            POST_STATE => slots(self.session.post),
+            _ => vec![],
+        };
+        json!({ "variables": variables })
+    }
+
+    /// Evaluate runs a debugger command, e.g. `t dv[0] int`.
+    fn evaluate(&mut self, args: &Value) -> anyhow::Result<Value> {
+        let expression = args["expression"].as_str().unwrap_or_default().trim();
+        let mut out = String::new();
+        crate::execute(self.session, &self.source, expression, &mut out, None)?;
+        let command = expression.split(' ').next().unwrap_or_default();
+        if MOVES.contains(&command) {
+            self.stopped("step", None, vec![]);
+        }
+        Ok(json!({ "result": out, "variablesReference": 0 }))
+    }
+
+    fn ops_len(&self) -> usize {
+        self.session
+            .code
+            .ops_from(0)
+            .map(|ops| ops.ops().count())
+            .unwrap_or_default()
+    }
+
+    /// Each op on its own line so line `n` is the op at pc `n - 1`.
+    fn ops_listing(&self) -> String {
+        self.session
+            .code
+            .ops_from(0)
+            .map(|ops| {
+                ops.ops()
+                    .enumerate()
+                    .map(|(pc, op)| format!("{}: {:?}", pc, op))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .unwrap_or_default()
+    }
+}
+
+fn scopes() -> Value {
+    let scope = |name: &str, reference: i64| json!({ "name": name, "variablesReference": reference, "expensive": false });
+    json!({
+        "scopes": [
+            scope("Stack", STACK),
+            scope("Memory", MEMORY),
+            scope("Decision variables", DECISION_VARS),
+            scope("Pre state", PRE_STATE),
+            scope("Post state", POST_STATE),
+        ]
+    })
+}
+
+fn variable(name: String, value: String) -> Value {
+    json!({ "name": name, "value": value, "variablesReference": 0 })
+}
+
+fn parse_condition(condition: &Value) -> anyhow::Result<Option<Condition>> {
+    match condition.as_str().map(str::trim) {
+        Some(c) if !c.is_empty() => Ok(Some(c.parse()?)),
+        _ => Ok(None),
+    }
+}
@@ -0,0 +1,43 @@
+use super::*;
+
+#[test]
+fn test_messages() {
+    let mut buf = Vec::new();
+    write_message(&mut buf, &json!({ "seq": 1, "type": "request" })).unwrap();
+    write_message(&mut buf, &json!({ "seq": 2, "body": "é" })).unwrap();
+    assert!(buf.starts_with(b"Content-Length: 26\r\n\r\n{"));
+
+    let mut input = &buf[..];
+    assert_eq!(
+        read_message(&mut input).unwrap(),
+        Some(json!({ "seq": 1, "type": "request" }))
+    );
+    assert_eq!(
+        read_message(&mut input).unwrap(),
+        Some(json!({ "seq": 2, "body": "é" }))
+    );
+    assert_eq!(read_message(&mut input).unwrap(), None);
+
+    // Other headers are ignored.
+    let mut input = &b"Content-Type: json\r\ncontent-length: 2\r\n\r\n{}"[..];
+    assert_eq!(read_message(&mut input).unwrap(), Some(json!({})));
+
+    let mut input = &b"Content-Length: x\r\n\r\n{}"[..];
+    assert!(read_message(&mut input).is_err());
+
+    // The body isn't allocated if it's too long.
+    let input = format!("Content-Length: {}\r\n\r\n{{}}", usize::MAX);
+    let e = read_message(&mut input.as_bytes()).unwrap_err();
+    assert!(e.to_string().contains("over the limit"), "{}", e);
+}
+
+#[test]
+fn test_parse_condition() {
+    assert_eq!(parse_condition(&Value::Null).unwrap(), None);
+    assert_eq!(parse_condition(&json!(" ")).unwrap(), None);
+    assert_eq!(
+        parse_condition(&json!("top == 1")).unwrap(),
+        Some("top == 1".parse().unwrap())
+    );
+    assert!(parse_condition(&json!("top ==")).is_err());
+}
@@ -0,0 +1,102 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use dialoguer::console::style;
+use essential_types::{ContentAddress, Key, Value, Word};
+
+use crate::{abi::VarAbi, storage};
+
+#[cfg(test)]
+mod tests;
+
+/// Diff the pre and post state of each contract.
+///
+/// Only the keys that were added, removed or changed are shown.
+/// Keys of the contract being solved are named by `storage` if they can be decoded.
+pub fn diff_state(
+    pre: &HashMap<ContentAddress, BTreeMap<Key, Value>>,
+    post: &HashMap<ContentAddress, BTreeMap<Key, Value>>,
+    solving: &ContentAddress,
+    storage: &[VarAbi],
+) -> String {
+    let empty = BTreeMap::new();
+    let contracts: BTreeSet<_> = pre.keys().chain(post.keys()).collect();
+    let mut lines = vec![];
+    for contract in contracts {
+        let storage = if contract == solving { storage } else { &[] };
+        let changes = diff_set(
+            pre.get(contract).unwrap_or(&empty),
+            post.get(contract).unwrap_or(&empty),
+            storage,
+        );
+        if !changes.is_empty() {
+            lines.push(format!("Contract {}", contract));
+            lines.extend(changes.into_iter().map(|c| format!("  {}", c)));
+        }
+    }
+    if lines.is_empty() {
+        return "No state changes".to_string();
+    }
+    lines.join("\n")
+}
+
+fn diff_set(
+    pre: &BTreeMap<Key, Value>,
+    post: &BTreeMap<Key, Value>,
+    storage: &[VarAbi],
+) -> Vec<String> {
+    let keys: BTreeSet<_> = pre.keys().chain(post.keys()).collect();
+    keys.into_iter()
+        .filter_map(|key| {
+            let name = match storage::decode_key(storage, key) {
+                Some((path, _)) => format!("{} {:?}", path, key),
+                None => format!("{:?}", key),
+            };
+            match (pre.get(key), post.get(key)) {
+                (None, Some(v)) => Some(style(format!("+ {}: {:?}", name, v)).green().to_string()),
+                (Some(v), None) => Some(style(format!("- {}: {:?}", name, v)).red().to_string()),
+                (Some(a), Some(b)) if a != b => Some(format!("~ {}: {}", name, diff_words(a, b))),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Diff each pre state slot against the post state slot at the same index.
+///
+/// Slots that are the same are shown too so the indices line up.
+pub fn diff_slots(pre: &[Vec<Word>], post: &[Vec<Word>]) -> String {
+    if pre.is_empty() && post.is_empty() {
+        return "No state slots".to_string();
+    }
+    (0..pre.len().max(post.len()))
+        .map(|i| match (pre.get(i), post.get(i)) {
+            (Some(a), Some(b)) if a == b => format!("  Slot {}: {:?}", i, a),
+            (Some(a), Some(b)) => format!("~ Slot {}: {}", i, diff_words(a, b)),
+            (Some(a), None) => style(format!("- Slot {}: {:?}", i, a)).red().to_string(),
+            (None, Some(b)) => style(format!("+ Slot {}: {:?}", i, b)).green().to_string(),
+            (None, None) => unreachable!(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Show words as `[1, 2] -> [1, 3]` with the words that differ coloured.
+fn diff_words(pre: &[Word], post: &[Word]) -> String {
+    let pre_words = pre
+        .iter()
+        .enumerate()
+        .map(|(i, w)| match post.get(i) == Some(w) {
+            true => w.to_string(),
+            false => style(w).red().to_string(),
+        })
+        .collect::<Vec<_>>();
+    let post_words = post
+        .iter()
+        .enumerate()
+        .map(|(i, w)| match pre.get(i) == Some(w) {
+            true => w.to_string(),
+            false => style(w).green().to_string(),
+        })
+        .collect::<Vec<_>>();
+    format!("[{}] -> [{}]", pre_words.join(", "), post_words.join(", "))
+}
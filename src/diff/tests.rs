@@ -0,0 +1,62 @@
+use super::*;
+use crate::abi::parse_abi;
+
+#[test]
+fn test_diff_state() {
+    dialoguer::console::set_colors_enabled(false);
+    let solving = ContentAddress([0; 32]);
+    let other = ContentAddress([1; 32]);
+    let pre = HashMap::from([
+        (
+            solving.clone(),
+            BTreeMap::from([
+                (vec![0], vec![1]),
+                (vec![1, 5], vec![7, 8]),
+                (vec![2], vec![3]),
+            ]),
+        ),
+        (other.clone(), BTreeMap::from([(vec![0], vec![1])])),
+    ]);
+    let mut post = pre.clone();
+    let set = post.get_mut(&solving).unwrap();
+    set.remove(&vec![0]);
+    set.insert(vec![1, 5], vec![7, 9, 10]);
+    set.insert(vec![3], vec![4]);
+
+    let storage = parse_abi(
+        br#"{
+        "predicates": [],
+        "storage": [
+            { "name": "nonce", "ty": "Int" },
+            { "name": "balances", "ty": { "Map": { "ty_from": "Int", "ty_to": "Int" } } }
+        ]
+    }"#,
+    )
+    .unwrap()
+    .storage;
+    assert_eq!(
+        diff_state(&pre, &post, &solving, &storage),
+        format!(
+            "Contract {}\n  - storage::nonce [0]: [1]\n  ~ storage::balances[5] [1, 5]: [7, 8] -> [7, 9, 10]\n  + [3]: [4]",
+            solving
+        )
+    );
+    assert_eq!(diff_state(&pre, &pre, &solving, &[]), "No state changes");
+}
+
+#[test]
+fn test_diff_slots() {
+    dialoguer::console::set_colors_enabled(false);
+    assert_eq!(
+        diff_slots(
+            &[vec![1], vec![], vec![2, 3]],
+            &[vec![1], vec![4], vec![2, 5]]
+        ),
+        "  Slot 0: [1]\n~ Slot 1: [] -> [4]\n~ Slot 2: [2, 3] -> [2, 5]"
+    );
+    assert_eq!(
+        diff_slots(&[vec![1]], &[vec![1], vec![2]]),
+        "  Slot 0: [1]\n+ Slot 1: [2]"
+    );
+    assert_eq!(diff_slots(&[], &[]), "No state slots");
+}
@@ -0,0 +1,239 @@
+use std::collections::{BTreeMap, HashMap};
+
+use essential_constraint_asm::Op;
+use essential_constraint_vm::OpAccess;
+use essential_state_read_vm::StateRead;
+use essential_types::{
+    predicate::Predicate,
+    solution::{Solution, SolutionDataIndex},
+    ContentAddress, Key, Value, Word,
+};
+
+use crate::{
+    memory_words, Breakpoints, CheckOutcome, Checkpoints, ConstraintDebugger, DebugAbi, Gas,
+    GasConfig, RepeatSlot, Session, Trace, TraceOutcome, TypeError,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// An owned debugger for driving a constraint from Rust without the REPL.
+///
+/// Unlike [`Session`] it doesn't borrow from a [`ConstraintDebugger`],
+/// so it can be stored and moved between tests and tools.
+/// Breakpoints, checkpoints and the trace are kept between calls.
+pub struct Engine {
+    debugger: ConstraintDebugger,
+    last_outcome: Option<TraceOutcome>,
+    /// Memory as of the last call, as the VM can only load from memory mutably.
+    memory: Vec<Word>,
+}
+
+/// Where [`Engine::run_to_breakpoint`] stopped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stop {
+    pub outcome: TraceOutcome,
+    /// The id of the breakpoint that was hit.
+    pub breakpoint: Option<usize>,
+}
+
+impl From<ConstraintDebugger> for Engine {
+    fn from(debugger: ConstraintDebugger) -> Self {
+        let mut debugger = debugger;
+        // Start a fresh session the engine keeps resuming.
+        debugger.start_session();
+        Self {
+            memory: memory_words(&mut debugger.memory),
+            debugger,
+            last_outcome: None,
+        }
+    }
+}
+
+impl Engine {
+    pub async fn new(
+        solution: Solution,
+        index: SolutionDataIndex,
+        predicate: Predicate,
+        constraint: usize,
+        state: HashMap<ContentAddress, BTreeMap<Key, Value>>,
+    ) -> anyhow::Result<Self> {
+        ConstraintDebugger::new(solution, index, predicate, constraint, state)
+            .await
+            .map(Self::from)
+    }
+
+    /// Read the pre state from any state read implementation instead of a map.
+    ///
+    /// See [`ConstraintDebugger::new_with_state_read`].
+    pub async fn new_with_state_read<S: StateRead>(
+        solution: Solution,
+        index: SolutionDataIndex,
+        predicate: Predicate,
+        constraint: usize,
+        pre_state: &S,
+        gas: GasConfig,
+    ) -> anyhow::Result<Self> {
+        ConstraintDebugger::new_with_state_read(
+            solution, index, predicate, constraint, pre_state, gas,
+        )
+        .await
+        .map(Self::from)
+    }
+
+    /// Use the ABI to name decision variables and storage keys.
+    pub fn with_abi(mut self, abi: DebugAbi) -> Self {
+        self.debugger = self.debugger.with_abi(abi);
+        self
+    }
+
+    /// Borrow a [`Session`] at the current position for anything
+    /// the engine doesn't cover. Changes to it are kept.
+    pub fn session<R>(&mut self, f: impl FnOnce(&mut Session) -> R) -> R {
+        let result = f(&mut self.debugger.resume_session());
+        self.memory = memory_words(&mut self.debugger.memory);
+        result
+    }
+
+    /// Play the next op.
+    pub fn step(&mut self) -> anyhow::Result<TraceOutcome> {
+        let outcome = self.session(|s| s.step_forward())?;
+        Ok(self.set_outcome((&outcome).into()))
+    }
+
+    /// Go back to before the last op was played.
+    pub fn back(&mut self) -> anyhow::Result<TraceOutcome> {
+        self.seek(self.pos().saturating_sub(1))
+    }
+
+    /// Play to the ith op, starting from the nearest checkpoint.
+    pub fn seek(&mut self, pos: usize) -> anyhow::Result<TraceOutcome> {
+        let outcome = self.session(|s| s.seek(pos))?;
+        Ok(self.set_outcome((&outcome).into()))
+    }
+
+    /// Play until a breakpoint is hit or the program ends or panics.
+    pub fn run_to_breakpoint(&mut self) -> anyhow::Result<Stop> {
+        let (outcome, breakpoint) = self.session(|s| s.run_to_breakpoint())?;
+        Ok(Stop {
+            outcome: self.set_outcome((&outcome).into()),
+            breakpoint,
+        })
+    }
+
+    /// Play until the program ends or panics and classify the result.
+    pub fn play_till_end(&mut self) -> anyhow::Result<CheckOutcome> {
+        let check = self.session(|s| s.play_till_end())?;
+        self.last_outcome = Some(match &check {
+            CheckOutcome::Panic(e) => TraceOutcome::Panic(e.clone()),
+            CheckOutcome::OutOfGas(e) => TraceOutcome::OutOfGas(e.clone()),
+            _ => TraceOutcome::ProgramEnd,
+        });
+        Ok(check)
+    }
+
+    /// Go back to the start of the program. Breakpoints are kept.
+    pub fn reset(&mut self) {
+        self.session(|s| {
+            s.reset_session();
+            *s.last_op = None;
+        });
+        self.last_outcome = None;
+    }
+
+    /// Decode words as a type, e.g. `0 int`, `dv[1] b256` or `::amount`.
+    pub fn decode(&mut self, input: &str) -> Result<String, TypeError> {
+        self.session(|s| s.parse_type(input))
+    }
+
+    fn set_outcome(&mut self, outcome: TraceOutcome) -> TraceOutcome {
+        self.last_outcome = Some(outcome.clone());
+        outcome
+    }
+
+    /// The number of ops played.
+    pub fn pos(&self) -> usize {
+        self.debugger.pos
+    }
+
+    pub fn pc(&self) -> usize {
+        self.debugger.pc
+    }
+
+    /// The op that will be played next, if the program hasn't ended.
+    pub fn op(&self) -> Option<Op> {
+        (&self.debugger.code)
+            .op_access(self.debugger.pc)
+            .and_then(|op| op.ok())
+    }
+
+    /// Gas spent by the constraint so far.
+    pub fn gas_spent(&self) -> Gas {
+        self.debugger.gas_spent
+    }
+
+    /// The op that was played last.
+    pub fn last_op(&self) -> Option<Op> {
+        self.debugger.last_op
+    }
+
+    /// The outcome of the last step, if anything has been played.
+    pub fn last_outcome(&self) -> Option<&TraceOutcome> {
+        self.last_outcome.as_ref()
+    }
+
+    /// Every op of the constraint.
+    pub fn ops(&self) -> Vec<Op> {
+        self.debugger
+            .code
+            .ops_from(0)
+            .map(|ops| ops.ops().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn stack(&self) -> &[Word] {
+        &self.debugger.stack[..]
+    }
+
+    /// All words in memory.
+    pub fn memory(&self) -> &[Word] {
+        &self.memory
+    }
+
+    /// The loops being repeated, outermost first.
+    pub fn repeat(&self) -> Vec<RepeatSlot> {
+        self.debugger.repeat.clone()
+    }
+
+    pub fn pre_state(&self) -> &[Vec<Word>] {
+        &self.debugger.pre_state
+    }
+
+    pub fn post_state(&self) -> &[Vec<Word>] {
+        &self.debugger.post_state
+    }
+
+    pub fn breakpoints(&self) -> &Breakpoints {
+        &self.debugger.breakpoints
+    }
+
+    pub fn breakpoints_mut(&mut self) -> &mut Breakpoints {
+        &mut self.debugger.breakpoints
+    }
+
+    pub fn checkpoints(&self) -> &Checkpoints {
+        &self.debugger.checkpoints
+    }
+
+    pub fn checkpoints_mut(&mut self) -> &mut Checkpoints {
+        &mut self.debugger.checkpoints
+    }
+
+    pub fn trace(&self) -> &Trace {
+        &self.debugger.trace
+    }
+
+    pub fn trace_mut(&mut self) -> &mut Trace {
+        &mut self.debugger.trace
+    }
+}
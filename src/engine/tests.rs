@@ -0,0 +1,162 @@
+use super::*;
+use crate::BreakpointKind;
+use essential_constraint_asm as asm;
+use essential_types::{
+    predicate::Directive,
+    solution::{Mutation, SolutionData},
+    PredicateAddress,
+};
+
+async fn engine() -> Engine {
+    let predicate = Predicate {
+        state_read: vec![],
+        constraints: vec![asm::to_bytes([
+            asm::Stack::Push(0).into(),
+            asm::Stack::Push(3).into(),
+            asm::Stack::Push(1).into(),
+            asm::Stack::Repeat.into(),
+            asm::Access::RepeatCounter.into(),
+            asm::Alu::Add.into(),
+            asm::Stack::RepeatEnd.into(),
+            asm::Stack::Push(1).into(),
+            asm::Temporary::Alloc.into(),
+            asm::Stack::Swap.into(),
+            asm::Temporary::Store.into(),
+            asm::Stack::Push(3).into(),
+            asm::Stack::Push(0).into(),
+            asm::Temporary::Load.into(),
+            asm::Pred::Eq.into(),
+        ])
+        .collect()],
+        directive: Directive::Satisfy,
+    };
+    let solution = Solution {
+        data: vec![SolutionData {
+            predicate_to_solve: PredicateAddress {
+                contract: ContentAddress([0; 32]),
+                predicate: ContentAddress([0; 32]),
+            },
+            decision_variables: vec![vec![4, 5]],
+            state_mutations: vec![Mutation {
+                key: vec![0],
+                value: vec![1],
+            }],
+            transient_data: vec![],
+        }],
+    };
+    Engine::new(solution, 0, predicate, 0, Default::default())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_step() {
+    let mut engine = engine().await;
+    assert_eq!(engine.pos(), 0);
+    assert_eq!(engine.op(), Some(asm::Stack::Push(0).into()));
+    assert_eq!(engine.last_op(), None);
+    assert_eq!(engine.last_outcome(), None);
+    assert_eq!(engine.ops().len(), 15);
+
+    for _ in 0..5 {
+        assert_eq!(engine.step().unwrap(), TraceOutcome::Step);
+    }
+    assert_eq!(engine.pc(), 5);
+    assert_eq!(engine.stack(), &[0, 0]);
+    assert_eq!(engine.last_op(), Some(asm::Access::RepeatCounter.into()));
+    assert_eq!(
+        engine.repeat(),
+        vec![RepeatSlot {
+            counter: 0,
+            limit: Some(3),
+            repeat_index: 4,
+        }]
+    );
+
+    assert_eq!(engine.back().unwrap(), TraceOutcome::Step);
+    assert_eq!(engine.pos(), 4);
+    assert_eq!(engine.stack(), &[0]);
+
+    assert_eq!(engine.play_till_end().unwrap(), CheckOutcome::Pass);
+    assert_eq!(engine.last_outcome(), Some(&TraceOutcome::ProgramEnd));
+    assert_eq!(engine.op(), None);
+    assert_eq!(engine.memory(), vec![3]);
+    assert_eq!(engine.repeat(), vec![]);
+    assert_eq!(engine.step().unwrap(), TraceOutcome::ProgramEnd);
+
+    engine.reset();
+    assert_eq!(engine.pos(), 0);
+    assert_eq!(engine.stack(), &[] as &[Word]);
+    assert_eq!(engine.memory(), &[] as &[Word]);
+    assert_eq!(engine.last_op(), None);
+    assert_eq!(engine.last_outcome(), None);
+}
+
+#[tokio::test]
+async fn test_breakpoints_are_kept() {
+    let mut engine = engine().await;
+    let id = engine.breakpoints_mut().add(BreakpointKind::Pc(6));
+    engine.trace_mut().set_enabled(true);
+
+    let stop = engine.run_to_breakpoint().unwrap();
+    assert_eq!(
+        stop,
+        Stop {
+            outcome: TraceOutcome::Step,
+            breakpoint: Some(id),
+        }
+    );
+    assert_eq!(engine.stack(), &[0]);
+    let stop = engine.run_to_breakpoint().unwrap();
+    assert_eq!(stop.breakpoint, Some(id));
+    assert_eq!(engine.stack(), &[1]);
+
+    engine.breakpoints_mut().remove(id);
+    let stop = engine.run_to_breakpoint().unwrap();
+    assert_eq!(stop.outcome, TraceOutcome::ProgramEnd);
+    assert_eq!(stop.breakpoint, None);
+    assert_eq!(engine.trace().steps().len(), engine.pos());
+    assert!(!engine.checkpoints().is_empty());
+}
+
+#[tokio::test]
+async fn test_break_at_start() {
+    let mut engine = engine().await;
+    let filter = "Stack::Push".parse().unwrap();
+    let id = engine
+        .breakpoints_mut()
+        .add(BreakpointKind::Op(vec![filter]));
+
+    // The op at pc 0 is checked before anything is played.
+    let stop = engine.run_to_breakpoint().unwrap();
+    assert_eq!(stop.breakpoint, Some(id));
+    assert_eq!(engine.pos(), 0);
+
+    // Continuing moves on to the next push.
+    engine.run_to_breakpoint().unwrap();
+    assert_eq!(engine.pos(), 1);
+    assert_eq!(engine.pc(), 1);
+
+    engine.reset();
+    assert_eq!(engine.run_to_breakpoint().unwrap().breakpoint, Some(id));
+    assert_eq!(engine.pos(), 0);
+}
+
+#[tokio::test]
+async fn test_session() {
+    let mut engine = engine().await;
+    engine.seek(2).unwrap();
+    assert_eq!(engine.decode("1 int").unwrap(), "3");
+    assert_eq!(engine.decode("dv[0] 1 int").unwrap(), "5");
+    assert!(engine.decode("pre[0] int").is_err());
+    assert_eq!(engine.pre_state(), &[] as &[Vec<Word>]);
+    assert_eq!(engine.post_state(), &[] as &[Vec<Word>]);
+
+    let shown = engine.session(|s| {
+        s.next(&mut String::new()).unwrap();
+        s.show(&["dv", "0"])
+    });
+    assert_eq!(shown, "Decision variable 0: [4, 5]");
+    assert_eq!(engine.pos(), 3);
+    assert_eq!(engine.stack(), &[0, 3, 1]);
+}
@@ -0,0 +1,187 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+};
+
+use essential_constraint_asm::{self as constraint_asm, ToOpcode};
+use essential_state_asm as state_asm;
+use essential_state_read_vm::{error::OutOfGasError, GasLimit};
+use serde::{Deserialize, Serialize};
+
+use crate::opcode::OpName;
+
+pub use essential_state_read_vm::Gas;
+
+#[cfg(test)]
+mod tests;
+
+/// What each op costs and how much gas a program may spend.
+///
+/// The limit applies to each state read program and constraint on its own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GasConfig {
+    /// The cost of any op not in the tables.
+    pub default_cost: Gas,
+    pub constraint: HashMap<constraint_asm::Opcode, Gas>,
+    /// Constraint ops in state read programs cost what they
+    /// cost in the constraint table unless they are listed here.
+    pub state_read: HashMap<state_asm::Opcode, Gas>,
+    pub limit: Gas,
+}
+
+/// An op would have spent more than the gas limit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OutOfGas {
+    /// Program counter of the op that wasn't played.
+    pub pc: usize,
+    /// Gas spent before the op.
+    pub spent: Gas,
+    pub op_gas: Gas,
+    pub limit: Gas,
+}
+
+/// Gas spent by a state read program reading the pre and post state.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct StateReadGas {
+    pub pre: Gas,
+    pub post: Gas,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GasCostsFile {
+    default: Option<Gas>,
+    #[serde(default)]
+    constraint: HashMap<String, Gas>,
+    #[serde(default)]
+    state_read: HashMap<String, Gas>,
+}
+
+/// Parse op costs from JSON, e.g.
+/// `{ "default": 1, "constraint": { "Alu::Add": 2 }, "state_read": { "StateRead::KeyRange": 100 } }`.
+///
+/// Ops are named `Category::Variant` as in op breakpoints.
+/// The limit is left unlimited.
+pub fn parse_gas_costs(bytes: &[u8]) -> anyhow::Result<GasConfig> {
+    let file: GasCostsFile = serde_json::from_slice(bytes)
+        .map_err(|e| anyhow::anyhow!("Failed to parse gas costs file: {}", e))?;
+    let constraint = constraint_asm::Opcode::by_name();
+    let state_read = state_asm::Opcode::by_name();
+    Ok(GasConfig {
+        default_cost: file.default.unwrap_or(GasConfig::default().default_cost),
+        constraint: costs(file.constraint, &constraint)?,
+        state_read: costs(file.state_read, &state_read)?,
+        ..Default::default()
+    })
+}
+
+fn costs<O: Copy + Eq + std::hash::Hash>(
+    costs: HashMap<String, Gas>,
+    opcodes: &BTreeMap<String, O>,
+) -> anyhow::Result<HashMap<O, Gas>> {
+    costs
+        .into_iter()
+        .map(|(name, cost)| match opcodes.get(&name) {
+            Some(o) => Ok((*o, cost)),
+            None => Err(anyhow::anyhow!("Unknown op `{}` in gas costs", name)),
+        })
+        .collect()
+}
+
+impl Default for GasConfig {
+    fn default() -> Self {
+        Self {
+            default_cost: 1,
+            constraint: Default::default(),
+            state_read: Default::default(),
+            limit: Gas::MAX,
+        }
+    }
+}
+
+impl GasConfig {
+    pub fn with_limit(mut self, limit: Gas) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn constraint_cost(&self, op: &constraint_asm::Op) -> Gas {
+        self.constraint
+            .get(&op.to_opcode())
+            .copied()
+            .unwrap_or(self.default_cost)
+    }
+
+    pub fn state_read_cost(&self, op: &state_asm::Op) -> Gas {
+        let opcode = op.to_opcode();
+        match (self.state_read.get(&opcode), opcode) {
+            (Some(cost), _) => *cost,
+            (None, state_asm::Opcode::Constraint(c)) => self
+                .constraint
+                .get(&c)
+                .copied()
+                .unwrap_or(self.default_cost),
+            (None, _) => self.default_cost,
+        }
+    }
+
+    /// The gas spent after playing an op costing `op_gas` at `pc`.
+    pub(crate) fn spend(&self, pc: usize, spent: Gas, op_gas: Gas) -> Result<Gas, OutOfGas> {
+        spent
+            .checked_add(op_gas)
+            .filter(|&s| s <= self.limit)
+            .ok_or(OutOfGas {
+                pc,
+                spent,
+                op_gas,
+                limit: self.limit,
+            })
+    }
+
+    /// The limit for a state read program that has already spent `spent`.
+    pub(crate) fn state_read_limit(&self, spent: Gas) -> GasLimit {
+        GasLimit {
+            per_yield: GasLimit::DEFAULT_PER_YIELD,
+            total: self.limit.saturating_sub(spent),
+        }
+    }
+}
+
+/// The gas spent by each state read program, one per line.
+pub(crate) fn show_state_read(gas: &[StateReadGas]) -> String {
+    gas.iter()
+        .enumerate()
+        .map(|(i, g)| format!("State read program {}: pre {}, post {}\n", i, g.pre, g.post))
+        .collect()
+}
+
+/// Gas spent out of the limit.
+pub(crate) fn show_spent(spent: Gas, limit: Gas) -> String {
+    match limit {
+        Gas::MAX => format!("{} (no limit)", spent),
+        _ => format!("{} of {}", spent, limit),
+    }
+}
+
+impl OutOfGas {
+    /// Where a state read program that had spent `spent` before
+    /// it was run ran out of gas.
+    pub(crate) fn from_state_read(pc: usize, spent: Gas, e: &OutOfGasError) -> Self {
+        Self {
+            pc,
+            spent: spent + e.spent,
+            op_gas: e.op_gas,
+            limit: spent + e.limit,
+        }
+    }
+}
+
+impl Display for OutOfGas {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "out of gas at op {}: {} spent + {} for the op > limit {}",
+            self.pc, self.spent, self.op_gas, self.limit
+        )
+    }
+}
@@ -0,0 +1,158 @@
+use super::*;
+use crate::{CheckOutcome, ConstraintDebugger, StateReadDebugger, TraceOutcome};
+use essential_types::{
+    predicate::{Directive, Predicate},
+    solution::{Solution, SolutionData},
+    ContentAddress, PredicateAddress,
+};
+
+const COSTS: &str = r#"{
+    "default": 2,
+    "constraint": { "Alu::Add": 5 },
+    "state_read": { "StateRead::KeyRange": 100 }
+}"#;
+
+fn predicate() -> Predicate {
+    Predicate {
+        state_read: vec![state_asm::to_bytes([
+            state_asm::Stack::Push(1).into(),
+            state_asm::StateSlots::AllocSlots.into(),
+            state_asm::Stack::Push(0).into(),
+            state_asm::Stack::Push(1).into(),
+            state_asm::Stack::Push(1).into(),
+            state_asm::Stack::Push(0).into(),
+            state_asm::StateRead::KeyRange,
+            state_asm::TotalControlFlow::Halt.into(),
+        ])
+        .collect()],
+        constraints: vec![constraint_asm::to_bytes([
+            constraint_asm::Stack::Push(1).into(),
+            constraint_asm::Stack::Push(2).into(),
+            constraint_asm::Alu::Add.into(),
+            constraint_asm::Stack::Push(3).into(),
+            constraint_asm::Pred::Eq.into(),
+        ])
+        .collect()],
+        directive: Directive::Satisfy,
+    }
+}
+
+fn solution() -> Solution {
+    Solution {
+        data: vec![SolutionData {
+            predicate_to_solve: PredicateAddress {
+                contract: ContentAddress([0; 32]),
+                predicate: ContentAddress([0; 32]),
+            },
+            decision_variables: vec![],
+            state_mutations: vec![],
+            transient_data: vec![],
+        }],
+    }
+}
+
+#[test]
+fn test_parse_gas_costs() {
+    let gas = parse_gas_costs(COSTS.as_bytes()).unwrap();
+    assert_eq!(gas.default_cost, 2);
+    assert_eq!(gas.limit, Gas::MAX);
+    assert_eq!(gas.constraint_cost(&constraint_asm::Alu::Add.into()), 5);
+    assert_eq!(gas.constraint_cost(&constraint_asm::Alu::Sub.into()), 2);
+    assert_eq!(gas.state_read_cost(&state_asm::StateRead::KeyRange), 100);
+    // Constraint ops cost the same in state read programs.
+    assert_eq!(gas.state_read_cost(&state_asm::Alu::Add.into()), 5);
+
+    let e = parse_gas_costs(br#"{ "constraint": { "Add": 1 } }"#).unwrap_err();
+    assert_eq!(e.to_string(), "Unknown op `Add` in gas costs");
+    assert!(parse_gas_costs(br#"{ "limit": 1 }"#).is_err());
+}
+
+#[tokio::test]
+async fn test_constraint_out_of_gas() {
+    let gas = parse_gas_costs(COSTS.as_bytes()).unwrap().with_limit(10);
+    let predicate = Predicate {
+        state_read: vec![],
+        ..predicate()
+    };
+    let mut debugger =
+        ConstraintDebugger::new_with_gas(solution(), 0, predicate, 0, Default::default(), gas)
+            .await
+            .unwrap();
+    let mut session = debugger.start_session();
+    session.seek(2).unwrap();
+    assert_eq!(session.gas_spent(), 4);
+    assert_eq!(session.show_gas(), "Constraint: 4 of 10");
+
+    let out_of_gas = OutOfGas {
+        pc: 3,
+        spent: 9,
+        op_gas: 2,
+        limit: 10,
+    };
+    assert_eq!(
+        session.play_till_end().unwrap(),
+        CheckOutcome::OutOfGas(out_of_gas.clone())
+    );
+    assert_eq!(
+        out_of_gas.to_string(),
+        "out of gas at op 3: 9 spent + 2 for the op > limit 10"
+    );
+    // The op that would go over the limit isn't played.
+    assert_eq!(&session.stack[..], &[3]);
+
+    session.seek(1).unwrap();
+    assert_eq!(session.gas_spent(), 2);
+}
+
+#[tokio::test]
+async fn test_state_read_out_of_gas() {
+    let gas = parse_gas_costs(COSTS.as_bytes()).unwrap();
+    let mut debugger = ConstraintDebugger::new_with_gas(
+        solution(),
+        0,
+        predicate(),
+        0,
+        Default::default(),
+        gas.clone(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        debugger.start_session().show_gas(),
+        "State read program 0: pre 114, post 114\nConstraint: 0 (no limit)"
+    );
+
+    let gas = gas.with_limit(50);
+    let e = ConstraintDebugger::new_with_gas(
+        solution(),
+        0,
+        predicate(),
+        0,
+        Default::default(),
+        gas.clone(),
+    )
+    .await
+    .err()
+    .unwrap();
+    assert_eq!(
+        e.to_string(),
+        "State read program 0 reading the pre state ran out of gas at op 6: 12 spent + 100 for the op > limit 50"
+    );
+
+    let mut debugger =
+        StateReadDebugger::new(solution(), 0, predicate(), 0, Default::default(), gas)
+            .await
+            .unwrap();
+    debugger.seek(6).await.unwrap();
+    assert_eq!(debugger.gas_spent(), 12);
+    assert_eq!(
+        debugger.step().await.unwrap(),
+        TraceOutcome::OutOfGas(OutOfGas {
+            pc: 6,
+            spent: 12,
+            op_gas: 100,
+            limit: 50,
+        })
+    );
+    assert_eq!(debugger.gas_spent(), 12);
+}
@@ -0,0 +1,195 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+
+use anyhow::bail;
+use essential_types::{
+    contract::{Contract, SignedContract},
+    predicate::Predicate,
+    solution::{Solution, SolutionData},
+    ContentAddress, Key, Value,
+};
+use serde::Deserialize;
+
+use crate::{abi::DebugAbi, gas::GasConfig, source::Source, ConstraintDebugger};
+
+#[cfg(test)]
+mod tests;
+
+/// The files to debug and which constraint to start at.
+///
+/// Paths are the same as the command line arguments. This is also
+/// the `launch` request of the Debug Adapter Protocol.
+/// Exactly one of `predicate`, `contract` or `signedContract` is required.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LaunchArgs {
+    pub solution: PathBuf,
+    pub predicate: Option<PathBuf>,
+    pub contract: Option<PathBuf>,
+    pub signed_contract: Option<PathBuf>,
+    pub solution_data_index: u16,
+    pub predicate_index: usize,
+    pub constraint_index: usize,
+    pub state: Option<PathBuf>,
+    pub abi: Option<PathBuf>,
+    pub abi_predicate: Option<String>,
+    pub predicate_source: Option<PathBuf>,
+    pub other_source: Option<PathBuf>,
+    /// Line number (starting at 1) of the constraint in the predicate source.
+    pub constraint_line: Option<usize>,
+    pub source_map: Option<PathBuf>,
+    /// Stop before the first op. Defaults to true.
+    pub stop_on_entry: Option<bool>,
+    /// The most gas each state read program and constraint may spend.
+    pub gas_limit: Option<u64>,
+    /// Path to a JSON file of op costs.
+    pub gas_costs: Option<PathBuf>,
+}
+
+/// The files named by [`LaunchArgs`], read and parsed.
+pub struct Launch {
+    pub solution: Solution,
+    /// Every predicate in the predicate, contract or signed contract file.
+    pub predicates: Vec<Predicate>,
+    pub state: HashMap<ContentAddress, BTreeMap<Key, Value>>,
+    pub abi: Option<DebugAbi>,
+    pub source: Option<Source>,
+    pub gas: GasConfig,
+}
+
+impl LaunchArgs {
+    /// Read and parse every file.
+    pub async fn load(&self) -> anyhow::Result<Launch> {
+        let predicates: Vec<Predicate> =
+            match (&self.predicate, &self.contract, &self.signed_contract) {
+                (Some(path), None, None) => vec![read_json(path).await?],
+                (None, Some(path), None) => read_json::<Contract>(path).await?.predicates,
+                (None, None, Some(path)) => {
+                    read_json::<SignedContract>(path).await?.contract.predicates
+                }
+                _ => bail!("Expected exactly one of predicate, contract or signed contract"),
+            };
+        let solution: Solution = read_json(&self.solution).await?;
+        let state = match &self.state {
+            Some(path) => crate::parse_state(&read(path).await?)?,
+            None => Default::default(),
+        };
+
+        let abi = match &self.abi {
+            Some(path) => {
+                let abi = crate::parse_abi(&read(path).await?)?;
+                let predicate = match &self.abi_predicate {
+                    Some(name) => abi.predicate(name),
+                    None => abi.predicates.get(self.predicate_index),
+                }
+                .ok_or_else(|| anyhow::anyhow!("Predicate not found in ABI"))?
+                .clone();
+                Some(DebugAbi::new(&abi, predicate))
+            }
+            None => None,
+        };
+
+        let source = match &self.predicate_source {
+            Some(path) => {
+                let predicate = read_to_string(path).await?;
+                let mut source = match self.constraint_line {
+                    Some(0) => bail!("Constraint line numbers start at 1"),
+                    Some(line) => Source::default()
+                        .with_predicate(predicate)
+                        .with_constraint_line_number(line - 1),
+                    None => {
+                        Source::default().with_predicate_find_line(predicate, self.constraint_index)
+                    }
+                }
+                .with_path(path);
+                if let Some(path) = &self.other_source {
+                    source = source.with_other_code(read_to_string(path).await?);
+                }
+                if let Some(path) = &self.source_map {
+                    let source_map = crate::parse_source_map(&read(path).await?)?;
+                    source = source.with_source_map(&source_map, self.constraint_index);
+                }
+                Some(source)
+            }
+            None => None,
+        };
+
+        let mut gas = match &self.gas_costs {
+            Some(path) => crate::parse_gas_costs(&read(path).await?)?,
+            None => GasConfig::default(),
+        };
+        if let Some(limit) = self.gas_limit {
+            gas = gas.with_limit(limit);
+        }
+
+        Ok(Launch {
+            solution,
+            predicates,
+            state,
+            abi,
+            source,
+            gas,
+        })
+    }
+
+    /// Load the files and start a debugger on the constraint.
+    pub async fn debugger(&self) -> anyhow::Result<(ConstraintDebugger, Option<Source>)> {
+        let launch = self.load().await?;
+        let predicate = launch.predicate(self.predicate_index)?;
+        let Launch {
+            solution,
+            state,
+            abi,
+            source,
+            gas,
+            ..
+        } = launch;
+        let mut debugger = ConstraintDebugger::new_with_gas(
+            solution,
+            self.solution_data_index,
+            predicate,
+            self.constraint_index,
+            state,
+            gas,
+        )
+        .await?;
+        if let Some(abi) = abi {
+            debugger = debugger.with_abi(abi);
+        }
+        Ok((debugger, source))
+    }
+}
+
+impl Launch {
+    /// The predicate at this index.
+    pub fn predicate(&self, index: usize) -> anyhow::Result<Predicate> {
+        self.predicates
+            .get(index)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Predicate not found"))
+    }
+
+    /// The solution data at this index.
+    pub fn solution_data(&self, index: u16) -> anyhow::Result<&SolutionData> {
+        crate::state::solution_data(&self.solution, index)
+    }
+}
+
+async fn read(path: &Path) -> anyhow::Result<Vec<u8>> {
+    tokio::fs::read(path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))
+}
+
+async fn read_to_string(path: &Path) -> anyhow::Result<String> {
+    tokio::fs::read_to_string(path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))
+}
+
+async fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> anyhow::Result<T> {
+    serde_json::from_slice(&read(path).await?)
+        .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path.display(), e))
+}
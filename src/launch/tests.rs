@@ -0,0 +1,52 @@
+use super::*;
+
+#[test]
+fn test_launch_args() {
+    let args: LaunchArgs = serde_json::from_value(serde_json::json!({
+        "solution": "solution.json",
+        "contract": "contract.json",
+        "predicateIndex": 1,
+        "constraintLine": 4,
+        "stopOnEntry": false,
+        "gasLimit": 100,
+        "gasCosts": "costs.json",
+    }))
+    .unwrap();
+    assert_eq!(args.solution, PathBuf::from("solution.json"));
+    assert_eq!(args.contract, Some(PathBuf::from("contract.json")));
+    assert_eq!(args.predicate, None);
+    assert_eq!(args.predicate_index, 1);
+    assert_eq!(args.constraint_index, 0);
+    assert_eq!(args.constraint_line, Some(4));
+    assert_eq!(args.stop_on_entry, Some(false));
+    assert_eq!(args.gas_limit, Some(100));
+    assert_eq!(args.gas_costs, Some(PathBuf::from("costs.json")));
+}
+
+#[tokio::test]
+async fn test_load_errors() {
+    let args = LaunchArgs {
+        solution: "solution.json".into(),
+        predicate: Some("predicate.json".into()),
+        contract: Some("contract.json".into()),
+        ..Default::default()
+    };
+    let e = args.load().await.err().unwrap();
+    assert_eq!(
+        e.to_string(),
+        "Expected exactly one of predicate, contract or signed contract"
+    );
+
+    let args = LaunchArgs {
+        solution: "solution.json".into(),
+        predicate: Some("missing/predicate.json".into()),
+        ..Default::default()
+    };
+    let e = args.load().await.err().unwrap();
+    assert!(
+        e.to_string()
+            .starts_with("Failed to read missing/predicate.json"),
+        "{}",
+        e
+    );
+}
@@ -1,6 +1,7 @@
 use std::{
     collections::{BTreeMap, HashMap, HashSet},
     fmt::Display,
+    io::{BufRead, Write},
     ops::Range,
 };
 
@@ -11,55 +12,120 @@ use essential_constraint_vm::{
     error::OpError, mut_keys_set, transient_data, Access, BytecodeMapped, OpAccess,
     ProgramControlFlow, Repeat, SolutionAccess, Stack, StateSlotSlice, StateSlots, TransientData,
 };
+use essential_state_read_vm::StateRead;
 use essential_types::{
     predicate::Predicate,
-    solution::{Solution, SolutionDataIndex},
+    solution::{Solution, SolutionData, SolutionDataIndex},
     ContentAddress, Key, Value, Word,
 };
 
-pub use source::Source;
+pub use abi::{parse_abi, ContractAbi, DebugAbi, PredicateAbi, TupleFieldAbi, TypeAbi, VarAbi};
+pub use breakpoint::{Breakpoint, BreakpointKind, Breakpoints, OpFilter};
+pub use check::{check_predicate, CheckOutcome};
+pub use checkpoint::{Checkpoint, Checkpoints, Snapshot};
+pub use condition::{Condition, Context};
+pub use dap::{read_message, run_dap, write_message};
+pub use engine::{Engine, Stop};
+pub use gas::{parse_gas_costs, Gas, GasConfig, OutOfGas, StateReadGas};
+pub use launch::{Launch, LaunchArgs};
+pub use parse_types::{decode_type, Field, Target, Type, TypeError};
+pub use repeat::RepeatSlot;
+pub use source::{parse_source_map, Source, SourceMap, Span};
+pub use state::{parse_state, SlotProvenance};
+pub use state_read::{run_state_read, run_state_read_script, StateReadDebugger, StateRun};
+pub use trace::{MemoryChange, Trace, TraceOutcome, TraceStep};
 
+mod abi;
+mod breakpoint;
+mod check;
+mod checkpoint;
+mod condition;
+mod dap;
+mod diff;
+mod engine;
+mod gas;
+mod launch;
+mod opcode;
 mod parse_types;
+mod repeat;
+mod rpc;
 mod source;
 mod state;
+mod state_read;
+mod storage;
+mod trace;
+mod tui;
 
 const PROMPT: &str = "<essential-dbg>";
 const PRIMITIVES: &[&str] = &["int", "bool", "b256"];
 const COMPOUND: &[&str] = &["array", "tuple"];
-const SHOW: &[&str] = &["transient", "pre state", "post state", "decision vars"];
+const SHOW: &[&str] = &[
+    "transient",
+    "pre state",
+    "post state",
+    "decision vars",
+    "storage",
+];
+const STORAGE: &[&str] = &["pre", "post", "mutations"];
 
 pub struct ConstraintDebugger {
     stack: Stack,
     memory: essential_constraint_vm::Memory,
-    repeat: Repeat,
+    repeat: Vec<RepeatSlot>,
     pc: usize,
     code: BytecodeMapped<Op>,
     solution: Solution,
     pre_state: Vec<Vec<Word>>,
     post_state: Vec<Vec<Word>>,
     index: SolutionDataIndex,
+    state: HashMap<ContentAddress, BTreeMap<Key, Value>>,
+    abi: Option<DebugAbi>,
+    gas: GasConfig,
+    state_read_gas: Vec<StateReadGas>,
+    pre_provenance: Vec<Option<SlotProvenance>>,
+    post_provenance: Vec<Option<SlotProvenance>>,
+    transient_data: TransientData,
+    last_op: Option<essential_constraint_asm::Constraint>,
+    pos: usize,
+    gas_spent: Gas,
+    breakpoints: Breakpoints,
+    checkpoints: Checkpoints,
+    trace: Trace,
 }
 
 pub struct Session<'a> {
     solution: &'a Solution,
     index: SolutionDataIndex,
+    /// The solution data at `index`.
+    data: &'a SolutionData,
     mutable_keys: HashSet<&'a [Word]>,
-    transient_data: TransientData,
+    transient_data: &'a TransientData,
     pre: &'a StateSlotSlice,
     post: &'a StateSlotSlice,
+    state: &'a HashMap<ContentAddress, BTreeMap<Key, Value>>,
+    abi: Option<&'a DebugAbi>,
+    gas: &'a GasConfig,
+    state_read_gas: &'a [StateReadGas],
+    pre_provenance: &'a [Option<SlotProvenance>],
+    post_provenance: &'a [Option<SlotProvenance>],
     code: &'a mut BytecodeMapped<Op>,
     stack: &'a mut Stack,
     memory: &'a mut essential_constraint_vm::Memory,
-    repeat: &'a mut Repeat,
+    repeat: &'a mut Vec<RepeatSlot>,
     pc: &'a mut usize,
-    last_op: Option<essential_constraint_asm::Constraint>,
-    pos: usize,
+    last_op: &'a mut Option<essential_constraint_asm::Constraint>,
+    pos: &'a mut usize,
+    gas_spent: &'a mut Gas,
+    breakpoints: &'a mut Breakpoints,
+    checkpoints: &'a mut Checkpoints,
+    trace: &'a mut Trace,
 }
 
 pub enum Outcome {
     ProgramEnd,
     Step,
     Panic(OpError),
+    OutOfGas(OutOfGas),
 }
 
 pub async fn run_with_source(
@@ -70,7 +136,40 @@ pub async fn run_with_source(
     state: HashMap<ContentAddress, BTreeMap<Key, Value>>,
     source: Source,
 ) -> anyhow::Result<()> {
-    run_inner(solution, index, predicate, constraint, state, Some(source)).await
+    run_inner(
+        solution,
+        index,
+        predicate,
+        constraint,
+        state,
+        Some(source),
+        None,
+        GasConfig::default(),
+    )
+    .await
+}
+
+/// Run the debugger with decision variables and storage named by the ABI.
+pub async fn run_with_abi(
+    solution: Solution,
+    index: SolutionDataIndex,
+    predicate: Predicate,
+    constraint: usize,
+    state: HashMap<ContentAddress, BTreeMap<Key, Value>>,
+    source: Option<Source>,
+    abi: DebugAbi,
+) -> anyhow::Result<()> {
+    run_inner(
+        solution,
+        index,
+        predicate,
+        constraint,
+        state,
+        source,
+        Some(abi),
+        GasConfig::default(),
+    )
+    .await
 }
 
 pub async fn run(
@@ -80,9 +179,144 @@ pub async fn run(
     constraint: usize,
     state: HashMap<ContentAddress, BTreeMap<Key, Value>>,
 ) -> anyhow::Result<()> {
-    run_inner(solution, index, predicate, constraint, state, None).await
+    run_inner(
+        solution,
+        index,
+        predicate,
+        constraint,
+        state,
+        None,
+        None,
+        GasConfig::default(),
+    )
+    .await
+}
+
+/// Run the debugger charging ops from the gas config.
+#[allow(clippy::too_many_arguments)]
+pub async fn run_with_gas(
+    solution: Solution,
+    index: SolutionDataIndex,
+    predicate: Predicate,
+    constraint: usize,
+    state: HashMap<ContentAddress, BTreeMap<Key, Value>>,
+    source: Option<Source>,
+    abi: Option<DebugAbi>,
+    gas: GasConfig,
+) -> anyhow::Result<()> {
+    run_inner(
+        solution, index, predicate, constraint, state, source, abi, gas,
+    )
+    .await
 }
 
+/// Run the debugger with commands read from `script` instead of an interactive terminal.
+///
+/// Each command is echoed to `output` followed by its result.
+/// Empty lines and lines starting with `#` are skipped.
+#[allow(clippy::too_many_arguments)]
+pub async fn run_script(
+    solution: Solution,
+    index: SolutionDataIndex,
+    predicate: Predicate,
+    constraint: usize,
+    state: HashMap<ContentAddress, BTreeMap<Key, Value>>,
+    source: Option<Source>,
+    abi: Option<DebugAbi>,
+    gas: GasConfig,
+    script: impl BufRead,
+    output: impl Write,
+) -> anyhow::Result<()> {
+    let mut debugger =
+        ConstraintDebugger::new_with_gas(solution, index, predicate, constraint, state, gas)
+            .await?;
+    if let Some(abi) = abi {
+        debugger = debugger.with_abi(abi);
+    }
+    let mut session = debugger.start_session();
+    script_loop(
+        &mut session,
+        script,
+        output,
+        |_| PROMPT.to_string(),
+        async |session: &mut Session, command: &str, out: &mut String| {
+            execute(session, &source, command, out, None)
+        },
+    )
+    .await
+}
+
+/// Serve newline delimited JSON-RPC 2.0 requests read from `input` until `quit`.
+///
+/// Methods are `state`, `step`, `back`, `play`, `continue`, `end`, `list`,
+/// `show`, `type`, `break`, `delete`, `breakpoints` and `quit`.
+/// Responses contain the stack, memory, ops and outcome as JSON values.
+/// Ops are an opcode such as `Stack::Push` and the operand if it has one.
+#[allow(clippy::too_many_arguments)]
+pub async fn run_rpc(
+    solution: Solution,
+    index: SolutionDataIndex,
+    predicate: Predicate,
+    constraint: usize,
+    state: HashMap<ContentAddress, BTreeMap<Key, Value>>,
+    abi: Option<DebugAbi>,
+    gas: GasConfig,
+    input: impl BufRead,
+    output: impl Write,
+) -> anyhow::Result<()> {
+    let mut debugger =
+        ConstraintDebugger::new_with_gas(solution, index, predicate, constraint, state, gas)
+            .await?;
+    if let Some(abi) = abi {
+        debugger = debugger.with_abi(abi);
+    }
+    let mut session = debugger.start_session();
+    rpc::serve(&mut session, input, output)
+}
+
+/// Run the debugger in a full screen terminal UI with panes for the ops,
+/// source, stack, memory, repeat counters and state slots.
+#[allow(clippy::too_many_arguments)]
+pub async fn run_tui(
+    solution: Solution,
+    index: SolutionDataIndex,
+    predicate: Predicate,
+    constraint: usize,
+    state: HashMap<ContentAddress, BTreeMap<Key, Value>>,
+    source: Option<Source>,
+    abi: Option<DebugAbi>,
+    gas: GasConfig,
+) -> anyhow::Result<()> {
+    let mut debugger =
+        ConstraintDebugger::new_with_gas(solution, index, predicate, constraint, state, gas)
+            .await?;
+    if let Some(abi) = abi {
+        debugger = debugger.with_abi(abi);
+    }
+    let mut session = debugger.start_session();
+    tui::run(&mut session, &source)
+}
+
+/// Play the constraint until it ends, panics or runs out of gas
+/// and return the recorded trace.
+pub async fn trace(
+    solution: Solution,
+    index: SolutionDataIndex,
+    predicate: Predicate,
+    constraint: usize,
+    state: HashMap<ContentAddress, BTreeMap<Key, Value>>,
+    gas: GasConfig,
+) -> anyhow::Result<Trace> {
+    let mut debugger =
+        ConstraintDebugger::new_with_gas(solution, index, predicate, constraint, state, gas)
+            .await?;
+    let mut session = debugger.start_session();
+    session.trace.set_enabled(true);
+    while let Outcome::Step = session.step_forward()? {}
+    Ok(std::mem::take(session.trace))
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn run_inner(
     solution: Solution,
     index: SolutionDataIndex,
@@ -90,261 +324,403 @@ async fn run_inner(
     constraint: usize,
     state: HashMap<ContentAddress, BTreeMap<Key, Value>>,
     source: Option<Source>,
+    abi: Option<DebugAbi>,
+    gas: GasConfig,
 ) -> anyhow::Result<()> {
     let mut debugger =
-        ConstraintDebugger::new(solution, index, predicate, constraint, state).await?;
+        ConstraintDebugger::new_with_gas(solution, index, predicate, constraint, state, gas)
+            .await?;
+    if let Some(abi) = abi {
+        debugger = debugger.with_abi(abi);
+    }
     let mut session = debugger.start_session();
+    prompt_loop(
+        &mut session,
+        |_| PROMPT.to_string(),
+        async |session: &mut Session,
+               command: &str,
+               out: &mut String,
+               history: &mut BasicHistory| {
+            execute(session, &source, command, out, Some(history))
+        },
+    )
+    .await
+}
 
+/// Prompt for commands with history until `execute` returns false.
+///
+/// The result of the last command is shown above the prompt.
+pub(crate) async fn prompt_loop<D>(
+    debugger: &mut D,
+    prompt: impl Fn(&D) -> String,
+    mut execute: impl AsyncFnMut(&mut D, &str, &mut String, &mut BasicHistory) -> anyhow::Result<bool>,
+) -> anyhow::Result<()> {
     let mut out = String::new();
-
     let mut history = BasicHistory::new().max_entries(20).no_duplicates(true);
-
     loop {
         let command: String = Input::with_theme(&ColorfulTheme::default())
-            .with_prompt(&format!("{}\n{}", out, PROMPT))
+            .with_prompt(format!("{}\n{}", out, prompt(debugger)))
             .history_with(&mut history)
             .interact_text()?;
+        if !execute(debugger, &command, &mut out, &mut history).await? {
+            return Ok(());
+        }
+    }
+}
 
-        match command.as_str() {
-            "n" | "next" => session.next(&mut out)?,
-            "b" | "back" => session.back(&mut out)?,
-            "e" | "end" => session.play_till_error(&mut out)?,
-            "q" | "quit" | "exit" => break,
-            "h" | "help" => {
-                out = help_msg();
-            }
-            "h t" | "help type" | "h type" | "help t" => {
-                out = types_msg();
+/// Run commands read from `script`, one per line, until one returns false.
+///
+/// Each command is echoed to `output` after the prompt, followed by its result.
+/// Empty lines and lines starting with `#` are skipped.
+pub(crate) async fn script_loop<D>(
+    debugger: &mut D,
+    script: impl BufRead,
+    mut output: impl Write,
+    prompt: impl Fn(&D) -> String,
+    mut execute: impl AsyncFnMut(&mut D, &str, &mut String) -> anyhow::Result<bool>,
+) -> anyhow::Result<()> {
+    let mut out = String::new();
+    for command in script.lines() {
+        let command = command?;
+        let command = command.trim();
+        if command.is_empty() || command.starts_with('#') {
+            continue;
+        }
+        writeln!(output, "{} {}", prompt(debugger), command)?;
+        out.clear();
+        if !execute(debugger, command, &mut out).await? {
+            break;
+        }
+        writeln!(output, "{}", out)?;
+    }
+    Ok(())
+}
+
+/// Run a single command, writing the result to `out`.
+///
+/// Interactive menus are only used when `history` is provided.
+/// Returns false if the command quits the debugger.
+fn execute(
+    session: &mut Session,
+    source: &Option<Source>,
+    command: &str,
+    out: &mut String,
+    history: Option<&mut BasicHistory>,
+) -> anyhow::Result<bool> {
+    match command {
+        "n" | "next" => session.next(out)?,
+        "b" | "back" => session.back(out)?,
+        "e" | "end" => session.play_till_error(out)?,
+        "co" | "continue" => session.continue_to_breakpoint(out)?,
+        "g" | "gas" => *out = session.show_gas(),
+        "q" | "quit" | "exit" => return Ok(false),
+        "h" | "help" => {
+            *out = help_msg();
+        }
+        "h t" | "help type" | "h type" | "help t" => {
+            *out = types_msg();
+        }
+        "h c" | "help code" | "h code" | "help c" => {
+            *out = help_code();
+        }
+        "h br" | "help break" | "h break" | "help br" => {
+            *out = help_break();
+        }
+        "s" | "show" => {
+            if history.is_none() {
+                *out = session.show(&[]);
+                return Ok(true);
             }
-            "h c" | "help code" | "h code" | "help c" => {
-                out = help_code();
+            let prompt = format!("{}::show", PROMPT);
+            let selection = FuzzySelect::with_theme(&ColorfulTheme::default())
+                .with_prompt(format!("What would you like to show?\n{}", prompt))
+                .default(0)
+                .items(SHOW)
+                .interact()?;
+            match SHOW[selection] {
+                "transient" => {
+                    let prompt = format!("{}::transient", prompt);
+                    let indices = (0..session.solution.data.len()).collect::<Vec<_>>();
+                    let selection = Select::with_theme(&ColorfulTheme::default())
+                        .with_prompt(format!("Which solution data?\n{}", prompt))
+                        .default(0)
+                        .items(&indices)
+                        .interact()?;
+
+                    let prompt = format!("{}::{}", prompt, selection);
+                    let t = session
+                        .transient_data
+                        .get(&(selection as u16))
+                        .expect("Can't be out of bounds");
+                    let keys: Vec<String> = t
+                        .keys()
+                        .map(|k| {
+                            k.iter()
+                                .map(|i| i.to_string())
+                                .collect::<Vec<String>>()
+                                .join(" ")
+                        })
+                        .collect();
+                    let selection = FuzzySelect::with_theme(&ColorfulTheme::default())
+                        .with_prompt(format!("Which key would you like to show?\n{}", prompt))
+                        .default(0)
+                        .items(&keys)
+                        .interact()?;
+                    let key = keys[selection]
+                        .split(' ')
+                        .map(|i| i.parse().unwrap())
+                        .collect::<Vec<_>>();
+                    let v = t.get(&key).unwrap();
+                    *out = format!("Transient data: {:?} => {:?}", key, v);
+                }
+                "pre state" => {
+                    let prompt = format!("{}::pre", prompt);
+                    let indices = (0..session.pre.len()).collect::<Vec<_>>();
+                    let selection = Select::with_theme(&ColorfulTheme::default())
+                        .with_prompt(format!("Which slot would you like to show?\n{}", prompt))
+                        .default(0)
+                        .items(&indices)
+                        .interact()?;
+                    let v = &session.pre[selection];
+                    *out = show_slot("Pre", selection, v, session.pre_provenance);
+                }
+                "post state" => {
+                    let prompt = format!("{}::post", prompt);
+                    let indices = (0..session.post.len()).collect::<Vec<_>>();
+                    let selection = Select::with_theme(&ColorfulTheme::default())
+                        .with_prompt(format!("Which slot would you like to show?\n{}", prompt))
+                        .default(0)
+                        .items(&indices)
+                        .interact()?;
+                    let v = &session.post[selection];
+                    *out = show_slot("Post", selection, v, session.post_provenance);
+                }
+                "decision vars" => {
+                    let prompt = format!("{}::decision_vars", prompt);
+                    let indices = (0..session.data.decision_variables.len())
+                        .map(
+                            |i| match session.abi.and_then(|abi| abi.predicate.vars.get(i)) {
+                                Some(var) => var.name.clone(),
+                                None => i.to_string(),
+                            },
+                        )
+                        .collect::<Vec<_>>();
+                    let selection = Select::with_theme(&ColorfulTheme::default())
+                        .with_prompt(format!(
+                            "Which solution data slot would you like to show?\n{}",
+                            prompt
+                        ))
+                        .default(0)
+                        .items(&indices)
+                        .interact()?;
+                    *out = session.show_decision_var(selection);
+                }
+                "storage" => {
+                    let prompt = format!("{}::storage", prompt);
+                    let selection = Select::with_theme(&ColorfulTheme::default())
+                        .with_prompt(format!("Which storage would you like to show?\n{}", prompt))
+                        .default(0)
+                        .items(STORAGE)
+                        .interact()?;
+                    *out = session.show_storage(STORAGE[selection]);
+                }
+                _ => unreachable!(),
             }
-            "s" | "show" => {
-                let prompt = format!("{}::show", PROMPT);
-                let selection = FuzzySelect::with_theme(&ColorfulTheme::default())
-                    .with_prompt(&format!("What would you like to show?\n{}", prompt))
-                    .default(0)
-                    .items(SHOW)
-                    .interact()?;
-                match SHOW[selection] {
-                    "transient" => {
-                        let prompt = format!("{}::transient", prompt);
-                        let indices = (0..session.solution.data.len()).collect::<Vec<_>>();
-                        let selection = Select::with_theme(&ColorfulTheme::default())
-                            .with_prompt(&format!("Which solution data?\n{}", prompt))
-                            .default(0)
-                            .items(&indices)
-                            .interact()?;
+        }
+        _ => {
+            let mut c = command.split(' ');
 
-                        let prompt = format!("{}::{}", prompt, selection);
-                        let t = session
-                            .transient_data
-                            .get(&(selection as u16))
-                            .expect("Can't be out of bounds");
-                        let keys: Vec<String> = t
-                            .keys()
-                            .map(|k| {
-                                k.iter()
-                                    .map(|i| i.to_string())
-                                    .collect::<Vec<String>>()
-                                    .join(" ")
-                            })
-                            .collect();
-                        let selection = FuzzySelect::with_theme(&ColorfulTheme::default())
-                            .with_prompt(&format!("Which key would you like to show?\n{}", prompt))
-                            .default(0)
-                            .items(&keys)
-                            .interact()?;
-                        let key = keys[selection]
-                            .split(' ')
-                            .map(|i| i.parse().unwrap())
-                            .collect::<Vec<_>>();
-                        let v = t.get(&key).unwrap();
-                        out = format!("Transient data: {:?} => {:?}", key, v);
-                    }
-                    "pre state" => {
-                        let prompt = format!("{}::pre", prompt);
-                        let indices = (0..session.pre.len()).collect::<Vec<_>>();
-                        let selection = Select::with_theme(&ColorfulTheme::default())
-                            .with_prompt(&format!("Which slot would you like to show?\n{}", prompt))
-                            .default(0)
-                            .items(&indices)
-                            .interact()?;
-                        let v = &session.pre[selection];
-                        out = format!("Pre state slot {}: {:?}", selection, v);
-                    }
-                    "post state" => {
-                        let prompt = format!("{}::post", prompt);
-                        let indices = (0..session.post.len()).collect::<Vec<_>>();
-                        let selection = Select::with_theme(&ColorfulTheme::default())
-                            .with_prompt(&format!("Which slot would you like to show?\n{}", prompt))
-                            .default(0)
-                            .items(&indices)
-                            .interact()?;
-                        let v = &session.post[selection];
-                        out = format!("Post state slot {}: {:?}", selection, v);
+            let Some(next_command) = c.next() else {
+                *out = format!("Unknown command: {}", command);
+                return Ok(true);
+            };
+            match next_command {
+                "p" | "play" => {
+                    let i = c
+                        .next()
+                        .and_then(|i| i.parse::<usize>().ok())
+                        .unwrap_or_default();
+                    session.play(i, out)?;
+                }
+                "l" | "list" => match c.next() {
+                    Some(i) => {
+                        let start = i.parse::<isize>().unwrap_or(0);
+                        let end = c.next().and_then(|i| i.parse::<isize>().ok()).unwrap_or(10);
+                        session.list_range(start..end, out);
                     }
-                    "decision vars" => {
-                        let prompt = format!("{}::decision_vars", prompt);
-                        let indices = (0..session.solution.data[index as usize]
-                            .decision_variables
-                            .len())
-                            .collect::<Vec<_>>();
-                        let selection = Select::with_theme(&ColorfulTheme::default())
-                            .with_prompt(&format!(
-                                "Which solution data slot would you like to show?\n{}",
-                                prompt
-                            ))
+                    None => session.list(out),
+                },
+                "t" | "type" => {
+                    let rest = c.filter(|s| !s.is_empty()).collect::<Vec<_>>().join(" ");
+                    if rest.is_empty() {
+                        let Some(history) = history else {
+                            *out =
+                                "Expected a type. e.g. `t 0 int`. See `help type` for more info."
+                                    .to_string();
+                            return Ok(true);
+                        };
+                        let prompt = format!("{}::type", PROMPT);
+                        let pos: String = Input::with_theme(&ColorfulTheme::default())
+                            .with_prompt(format!("Enter position\n{}", prompt))
+                            .default("0".to_string())
+                            .history_with(&mut *history)
+                            .interact_text()?;
+                        let pos: usize = pos.trim().parse().unwrap_or_default();
+
+                        let prompt = format!("{}::{}", prompt, pos);
+                        let mut options = PRIMITIVES.to_vec();
+                        options.extend_from_slice(COMPOUND);
+
+                        let selection = FuzzySelect::with_theme(&ColorfulTheme::default())
+                            .with_prompt(format!("Select type\n{}", prompt))
                             .default(0)
-                            .items(&indices)
+                            .items(&options[..])
                             .interact()?;
-                        let v =
-                            &session.solution.data[index as usize].decision_variables[selection];
-                        out = format!("Decision variable {}: {:?}", selection, v);
-                    }
-                    _ => unreachable!(),
-                }
-            }
-            _ => {
-                let mut c = command.split(' ');
-
-                let Some(next_command) = c.next() else {
-                    out = format!("Unknown command: {}", command);
-                    continue;
-                };
-                match next_command {
-                    "p" | "play" => {
-                        let i = c
-                            .next()
-                            .and_then(|i| i.parse::<usize>().ok())
-                            .unwrap_or_default();
-                        session.play(i, &mut out)?;
-                    }
-                    "l" | "list" => match c.next() {
-                        Some(i) => {
-                            let start = i.parse::<isize>().unwrap_or(0);
-                            let end = c.next().and_then(|i| i.parse::<isize>().ok()).unwrap_or(10);
-                            session.list_range(start..end, &mut out);
-                        }
-                        None => session.list(&mut out),
-                    },
-                    "t" | "type" => {
-                        let rest = c.filter(|s| !s.is_empty()).collect::<Vec<_>>().join(" ");
-                        if rest.is_empty() {
-                            let prompt = format!("{}::type", PROMPT);
-                            let pos: String = Input::with_theme(&ColorfulTheme::default())
-                                .with_prompt(&format!("Enter position\n{}", prompt))
-                                .default("0".to_string())
-                                .history_with(&mut history)
-                                .interact_text()?;
-                            let pos: usize = pos.trim().parse().unwrap_or_default();
-
-                            let prompt = format!("{}::{}", prompt, pos);
-                            let mut options = PRIMITIVES.to_vec();
-                            options.extend_from_slice(COMPOUND);
-
-                            let selection = FuzzySelect::with_theme(&ColorfulTheme::default())
-                                .with_prompt(&format!("Select type\n{}", prompt))
-                                .default(0)
-                                .items(&options[..])
-                                .interact()?;
-                            if PRIMITIVES.contains(&options[selection]) {
-                                let input = format!("{} {}", pos, &options[selection]);
-                                out = session.parse_type(&input);
-                            } else {
-                                let prompt = format!("{}::{}", prompt, options[selection]);
-                                let input =
-                                    match options[selection] {
-                                        "array" => {
-                                            let selection =
-                                                FuzzySelect::with_theme(&ColorfulTheme::default())
-                                                    .with_prompt(&format!(
-                                                        "Select array type\n{}",
-                                                        prompt
-                                                    ))
-                                                    .default(0)
-                                                    .items(PRIMITIVES)
-                                                    .interact()?;
-
-                                            let prompt =
-                                                format!("{}::{}", prompt, PRIMITIVES[selection]);
-                                            let len: String =
-                                                Input::with_theme(&ColorfulTheme::default())
-                                                    .with_prompt(&format!(
-                                                        "Enter array length\n{}",
-                                                        prompt
-                                                    ))
-                                                    .default("1".to_string())
-                                                    .history_with(&mut history)
-                                                    .interact_text()?;
-                                            let len: usize = len.trim().parse().unwrap_or_default();
-                                            format!("{} {}[{}]", pos, PRIMITIVES[selection], len)
-                                        }
-                                        "tuple" => {
-                                            let mut fields = String::new();
-                                            let mut add_field = true;
-                                            while add_field {
-                                                let p = format!("{}::{{ {} }}", prompt, fields);
-                                                let selection = FuzzySelect::with_theme(
-                                                    &ColorfulTheme::default(),
-                                                )
-                                                .with_prompt(&format!("Select field type\n{}", p))
+                        if PRIMITIVES.contains(&options[selection]) {
+                            let input = format!("{} {}", pos, &options[selection]);
+                            *out = session.parse_type(&input).unwrap_or_else(type_error);
+                        } else {
+                            let prompt = format!("{}::{}", prompt, options[selection]);
+                            let input = match options[selection] {
+                                "array" => {
+                                    let selection =
+                                        FuzzySelect::with_theme(&ColorfulTheme::default())
+                                            .with_prompt(format!("Select array type\n{}", prompt))
+                                            .default(0)
+                                            .items(PRIMITIVES)
+                                            .interact()?;
+
+                                    let prompt = format!("{}::{}", prompt, PRIMITIVES[selection]);
+                                    let len: String = Input::with_theme(&ColorfulTheme::default())
+                                        .with_prompt(format!("Enter array length\n{}", prompt))
+                                        .default("1".to_string())
+                                        .history_with(&mut *history)
+                                        .interact_text()?;
+                                    let len: usize = len.trim().parse().unwrap_or_default();
+                                    format!("{} {}[{}]", pos, PRIMITIVES[selection], len)
+                                }
+                                "tuple" => {
+                                    let mut fields = String::new();
+                                    let mut add_field = true;
+                                    while add_field {
+                                        let p = format!("{}::{{ {} }}", prompt, fields);
+                                        let selection =
+                                            FuzzySelect::with_theme(&ColorfulTheme::default())
+                                                .with_prompt(format!("Select field type\n{}", p))
                                                 .default(0)
                                                 .items(PRIMITIVES)
                                                 .interact()?;
 
-                                                fields.push_str(PRIMITIVES[selection]);
-
-                                                let p = format!("{}::{{ {} }}", prompt, fields);
-
-                                                add_field =
-                                                    Confirm::with_theme(&ColorfulTheme::default())
-                                                        .with_prompt(&format!(
-                                                            "Do you want to add another field?\n{}",
-                                                            p
-                                                        ))
-                                                        .default(true)
-                                                        .interact()?;
-                                                if add_field {
-                                                    fields.push_str(", ");
-                                                }
-                                            }
-                                            format!("{} {{ {} }}", pos, fields)
+                                        fields.push_str(PRIMITIVES[selection]);
+
+                                        let p = format!("{}::{{ {} }}", prompt, fields);
+
+                                        add_field = Confirm::with_theme(&ColorfulTheme::default())
+                                            .with_prompt(format!(
+                                                "Do you want to add another field?\n{}",
+                                                p
+                                            ))
+                                            .default(true)
+                                            .interact()?;
+                                        if add_field {
+                                            fields.push_str(", ");
                                         }
-                                        _ => unreachable!(),
-                                    };
-
-                                let force_hex = Confirm::with_theme(&ColorfulTheme::default())
-                                    .with_prompt(&format!(
-                                        "Do you want to force HEX formatting?\n{}",
-                                        prompt
-                                    ))
-                                    .default(false)
-                                    .interact()?;
-                                let input = if force_hex {
-                                    format!("{} HEX", input)
-                                } else {
-                                    input
-                                };
-                                history.write(&format!("t {}", input));
-                                out = session.parse_type(&input);
-                            }
-                        } else {
-                            out = session.parse_type(&rest);
+                                    }
+                                    format!("{} {{ {} }}", pos, fields)
+                                }
+                                _ => unreachable!(),
+                            };
+
+                            let force_hex = Confirm::with_theme(&ColorfulTheme::default())
+                                .with_prompt(format!(
+                                    "Do you want to force HEX formatting?\n{}",
+                                    prompt
+                                ))
+                                .default(false)
+                                .interact()?;
+                            let input = if force_hex {
+                                format!("{} HEX", input)
+                            } else {
+                                input
+                            };
+                            history.write(&format!("t {}", input));
+                            *out = session.parse_type(&input).unwrap_or_else(type_error);
                         }
+                    } else {
+                        *out = session.parse_type(&rest).unwrap_or_else(type_error);
                     }
-                    "c" | "code" => {
-                        out = source::show_code(&source, c.next().into());
-                    }
-                    _ => {
-                        out = format!("Unknown command: {}", command);
-                    }
+                }
+                "c" | "code" => {
+                    *out = source::show_code(source, c.next().into(), *session.pc);
+                }
+                "cp" | "checkpoints" => match (c.next(), c.next()) {
+                    (Some("budget"), Some(budget)) => match budget.parse::<usize>() {
+                        Ok(budget) => {
+                            session.checkpoints.set_budget(budget);
+                            *out = session.checkpoints.to_string();
+                        }
+                        Err(_) => *out = format!("Invalid budget: {}", budget),
+                    },
+                    _ => *out = session.checkpoints.to_string(),
+                },
+                "tr" | "trace" => {
+                    *out = match (c.next(), c.next()) {
+                        (Some("on"), _) => {
+                            session.trace.set_enabled(true);
+                            "Trace recording on".to_string()
+                        }
+                        (Some("off"), _) => {
+                            session.trace.set_enabled(false);
+                            "Trace recording off".to_string()
+                        }
+                        (Some("clear"), _) => {
+                            session.trace.clear();
+                            "Trace cleared".to_string()
+                        }
+                        (Some("save"), Some(path)) => {
+                            let saved = std::fs::File::create(path)
+                                .map_err(anyhow::Error::from)
+                                .and_then(|file| {
+                                    session.trace.write_jsonl(std::io::BufWriter::new(file))
+                                });
+                            match saved {
+                                Ok(()) => format!(
+                                    "Saved {} steps to {}",
+                                    session.trace.steps().len(),
+                                    path
+                                ),
+                                Err(e) => format!("Failed to save trace: {}", e),
+                            }
+                        }
+                        _ => format!(
+                            "Trace recording is {} with {} steps",
+                            if session.trace.is_enabled() {
+                                "on"
+                            } else {
+                                "off"
+                            },
+                            session.trace.steps().len()
+                        ),
+                    };
+                }
+                "s" | "show" => {
+                    let args = c.filter(|s| !s.is_empty()).collect::<Vec<_>>();
+                    *out = session.show(&args);
+                }
+                "d" | "diff" => {
+                    *out = session.show_diff(c.next().unwrap_or_default());
+                }
+                "br" | "break" => {
+                    let rest = c.filter(|s| !s.is_empty()).collect::<Vec<_>>().join(" ");
+                    *out = session.breakpoints.command(&rest);
+                }
+                _ => {
+                    *out = format!("Unknown command: {}", command);
                 }
             }
         }
     }
-
-    Ok(())
+    Ok(true)
 }
 
 fn end(out: &mut String) {
@@ -357,10 +733,20 @@ fn help_msg() -> String {
     b | back: Step back
     p | play [i]: Play to ith op
     e | end: Play till end or error is hit
+    co | continue: Play till a breakpoint, end or error is hit
+    br | break: Manage breakpoints. See `help break` for more info.
+    tr | trace [on | off | clear | save <path>]: Record steps and save them as JSON Lines
+    cp | checkpoints [budget <bytes>]: Show checkpoint usage or set the memory budget
+    g | gas: Show the gas spent by the state read programs and this constraint so far
     l | list [start] [end]: List ops from start to end
     s | show: Show transient data, pre state, or post state
+    s | show <pre | post | dv> <i>: Show the ith pre state slot, post state slot or decision var
+    s | show dv [name]: Show all decision vars or one by name if the ABI is known
+    s | show storage <pre | post | mutations>: Show the contract's state by storage name
+    s | show transient <i> <key..>: Show transient data at key for the ith solution data
+    d | diff [state | slots]: Diff the pre and post state of each contract and each pre and post state slot
     c | code: Show source code. See `help code` for more info.
-    t | type <i> [type]: Parse the ith word in the stack as the given type. See `help type` for more info.
+    t | type [target] <i> [type]: Parse the ith word in the stack or target as the given type. See `help type` for more info.
     q | quit | exit: Quit
     h | help: Show this message
     "#
@@ -369,11 +755,22 @@ fn help_msg() -> String {
 
 fn types_msg() -> String {
     r#"Primitives: int, bool, b256
-    Arrays: primitive[] (e.g. int[])
-    Tuple: { primitive, primitive, ... } (e.g. {int, bool, b256})
-    Note that nesting types is not currently supported.
+    Arrays: type[len] (e.g. int[3])
+        Multi-dimensional arrays list the outermost length first
+        (e.g. int[2][3] is 2 arrays of int[3])
+    Tuple: { type, type, ... } (e.g. {int, bool, b256})
+        Fields can be named (e.g. {contract: b256, addr: b256})
+    Types can be nested (e.g. {int, b256[2]}[3])
     To parse a section of the stack as a type, use `t <i> [type]` 
     e.g. `t 1 int[2]` to parse the second and third word as ints.
+    Words can also be parsed from a target instead of the stack:
+    dv[i]: Decision variable i
+    dv[name] | ::name: Decision variable by name if the ABI is known.
+        The type defaults to the one in the ABI (e.g. `t ::amount`)
+    pre[i] | post[i]: Pre or post state slot i
+    mem[i]: Memory starting at address i
+    transient[i][key]: Transient data of solution data i at key (e.g. transient[0][1, 2])
+    e.g. `t post[2] {b256, int}`, `t dv[0] 1 int[3]`
     `b256` is always printed as hex. 
     You can force hex formatting by adding `HEX` to the end of the command.
     e.g. `t 1 int HEX`
@@ -392,6 +789,40 @@ fn help_code() -> String {
         that is being debugged. Constraint line number is required.
     co | constraint_only: Show only the constraint line.
         Constraint line number is required.
+    If a source map is loaded, the expression the next op
+    belongs to is highlighted.
+    "#
+    .to_string()
+}
+
+fn help_break() -> String {
+    r#"Breakpoints stop `continue` when they are hit.
+    br | break: equivalent to `break list`.
+    `break` can be followed by:
+    Commands:
+    <i> | pos <i>: Break when the ith op has been played
+    pc <i>: Break when the program counter reaches i
+    op <kind>, ..: Break before an op of any of the given kinds is played.
+        e.g. `break op Access::State`, `break op Pred::Eq, Crypto::*`
+    Any of the above can be followed by `if <condition>` to only break
+    when the condition holds. `break if <condition>` checks every step.
+    l | list: List all breakpoints
+    d | delete <id>: Delete a breakpoint
+    e | enable <id>: Enable a breakpoint
+    dis | disable <id>: Disable a breakpoint
+    Conditions compare two operands with ==, !=, <, <=, >, >=
+    and can be combined with && and ||.
+    Operands:
+    <number>: A literal word (e.g. 42, -1, 0x2A)
+    depth: Number of words on the stack
+    top: The word on top of the stack
+    stack[i]: The ith word from the bottom of the stack.
+        Negative indices count from the top.
+    memory[i]: The ith word in memory
+    memlen: Number of words in memory
+    dv[i][j]: Word j of decision variable i
+    pos | pc: The current position or program counter
+    e.g. `break if top == 0`, `break pc 12 if dv[2][0] != stack[1]`
     "#
     .to_string()
 }
@@ -404,13 +835,69 @@ impl ConstraintDebugger {
         constraint: usize,
         state: HashMap<ContentAddress, BTreeMap<Key, Value>>,
     ) -> anyhow::Result<Self> {
-        let slots = state::read_state(&solution, index, &predicate, state.clone()).await?;
+        Self::new_with_gas(
+            solution,
+            index,
+            predicate,
+            constraint,
+            state,
+            GasConfig::default(),
+        )
+        .await
+    }
+
+    /// Charge ops from the gas config and stop when the limit is hit.
+    ///
+    /// State read programs that run out of gas fail to start the debugger.
+    pub async fn new_with_gas(
+        solution: Solution,
+        index: SolutionDataIndex,
+        predicate: Predicate,
+        constraint: usize,
+        state: HashMap<ContentAddress, BTreeMap<Key, Value>>,
+        gas: GasConfig,
+    ) -> anyhow::Result<Self> {
+        let slots = state::read_state(&solution, index, &predicate, state.clone(), &gas).await?;
+        Self::from_slots(solution, index, predicate, constraint, state, slots, gas)
+    }
+
+    /// Read the pre state from any state read implementation instead of a map,
+    /// such as a mock or snapshot backed store.
+    ///
+    /// The post state is the pre state with the solution's mutations layered on top.
+    /// Only the keys read by the state read programs or mutated by the solution
+    /// are known to the debugger so showing storage shows just those keys.
+    pub async fn new_with_state_read<S: StateRead>(
+        solution: Solution,
+        index: SolutionDataIndex,
+        predicate: Predicate,
+        constraint: usize,
+        pre_state: &S,
+        gas: GasConfig,
+    ) -> anyhow::Result<Self> {
+        let slots = state::read_state_from(&solution, index, &predicate, pre_state, &gas).await?;
+        let mut state = slots.pre_entries();
+        for (contract, set) in state::read_mutated(&solution, pre_state).await? {
+            state.entry(contract).or_default().extend(set);
+        }
+        Self::from_slots(solution, index, predicate, constraint, state, slots, gas)
+    }
 
+    fn from_slots(
+        solution: Solution,
+        index: SolutionDataIndex,
+        predicate: Predicate,
+        constraint: usize,
+        state: HashMap<ContentAddress, BTreeMap<Key, Value>>,
+        slots: state::Slots,
+        gas: GasConfig,
+    ) -> anyhow::Result<Self> {
         let Some(code) = predicate.constraints.get(constraint).cloned() else {
             bail!("No constraint found");
         };
 
         let code = BytecodeMapped::try_from_bytes(code)?;
+        let transient_data = transient_data(&solution);
         let s = Self {
             stack: Default::default(),
             memory: Default::default(),
@@ -421,37 +908,118 @@ impl ConstraintDebugger {
             pre_state: slots.pre,
             post_state: slots.post,
             index,
+            state,
+            abi: None,
+            gas,
+            state_read_gas: slots.gas,
+            pre_provenance: slots.pre_provenance,
+            post_provenance: slots.post_provenance,
+            transient_data,
+            last_op: None,
+            pos: 0,
+            gas_spent: 0,
+            breakpoints: Default::default(),
+            checkpoints: Default::default(),
+            trace: Default::default(),
         };
         Ok(s)
     }
 
+    /// Use the ABI to name decision variables and storage keys.
+    pub fn with_abi(mut self, abi: DebugAbi) -> Self {
+        self.abi = Some(abi);
+        self
+    }
+
+    /// Start over on another constraint's code, keeping the state slots already read.
+    pub(crate) fn set_constraint(&mut self, code: Vec<u8>) -> anyhow::Result<()> {
+        self.code = BytecodeMapped::try_from_bytes(code)?;
+        self.stack = Default::default();
+        self.memory = Default::default();
+        self.repeat = Default::default();
+        self.pc = 0;
+        Ok(())
+    }
+
+    /// Start a session with no breakpoints, checkpoints or trace.
     pub fn start_session(&mut self) -> Session<'_> {
-        let mutable_keys = mut_keys_set(&self.solution, self.index);
-        let transient_data = transient_data(&self.solution);
+        self.last_op = None;
+        self.pos = 0;
+        self.gas_spent = 0;
+        self.breakpoints = Default::default();
+        self.checkpoints = Default::default();
+        self.trace = Default::default();
+        self.resume_session()
+    }
+
+    /// Carry on from where the last session left off,
+    /// keeping its breakpoints, checkpoints and trace.
+    pub(crate) fn resume_session(&mut self) -> Session<'_> {
         Session {
             code: &mut self.code,
             stack: &mut self.stack,
             memory: &mut self.memory,
             repeat: &mut self.repeat,
             pc: &mut self.pc,
-            last_op: None,
+            last_op: &mut self.last_op,
             solution: &self.solution,
             index: self.index,
-            mutable_keys,
-            transient_data,
+            // The index was checked when the state was read.
+            data: &self.solution.data[self.index as usize],
+            mutable_keys: mut_keys_set(&self.solution, self.index),
+            transient_data: &self.transient_data,
             pre: &self.pre_state,
             post: &self.post_state,
-            pos: 0,
+            state: &self.state,
+            abi: self.abi.as_ref(),
+            gas: &self.gas,
+            state_read_gas: &self.state_read_gas,
+            pre_provenance: &self.pre_provenance,
+            post_provenance: &self.post_provenance,
+            pos: &mut self.pos,
+            gas_spent: &mut self.gas_spent,
+            breakpoints: &mut self.breakpoints,
+            checkpoints: &mut self.checkpoints,
+            trace: &mut self.trace,
         }
     }
 }
 
+/// Read all words out of memory.
+fn memory_words(memory: &mut essential_constraint_vm::Memory) -> Vec<Word> {
+    let len = memory.len().unwrap_or_default();
+    (0..len).filter_map(|i| memory.load(i).ok()).collect()
+}
+
+/// A state slot followed by where it was read from.
+fn show_slot(
+    which: &str,
+    i: usize,
+    value: &[Word],
+    provenance: &[Option<SlotProvenance>],
+) -> String {
+    match provenance.get(i).and_then(Option::as_ref) {
+        Some(p) => format!("{} state slot {}: {:?}\n  {}", which, i, value, p),
+        None => format!(
+            "{} state slot {}: {:?}\n  Not read from state",
+            which, i, value
+        ),
+    }
+}
+
+fn type_error(e: TypeError) -> String {
+    dialoguer::console::style(e).red().to_string()
+}
+
 fn handle_outcome(outcome: Outcome, out: &mut String) {
     match outcome {
         Outcome::ProgramEnd => end(out),
         Outcome::Panic(e) => {
             *out = format!("Program panic: {:?}\n{}", e, out);
         }
+        Outcome::OutOfGas(e) => {
+            *out = format!("Program ran {}\n{}", e, out);
+        }
         Outcome::Step => (),
     }
 }
@@ -462,7 +1030,9 @@ impl Session<'_> {
         *self.memory = Default::default();
         *self.repeat = Default::default();
         *self.pc = 0;
-        self.pos = 0;
+        *self.pos = 0;
+        *self.gas_spent = 0;
+        self.breakpoints.hit_at_start = false;
     }
 
     pub fn next(&mut self, out: &mut String) -> anyhow::Result<()> {
@@ -476,8 +1046,7 @@ impl Session<'_> {
 
     pub fn back(&mut self, out: &mut String) -> anyhow::Result<()> {
         let pos = self.pos.saturating_sub(1);
-        self.reset_session();
-        let outcome = self.play_to(pos)?;
+        let outcome = self.seek(pos)?;
         *out = format!("{}", self);
 
         handle_outcome(outcome, out);
@@ -485,8 +1054,7 @@ impl Session<'_> {
     }
 
     pub fn play(&mut self, i: usize, out: &mut String) -> anyhow::Result<()> {
-        self.reset_session();
-        let outcome = self.play_to(i)?;
+        let outcome = self.seek(i)?;
         *out = format!("{}", self);
 
         handle_outcome(outcome, out);
@@ -494,35 +1062,105 @@ impl Session<'_> {
     }
 
     pub fn play_till_error(&mut self, out: &mut String) -> anyhow::Result<()> {
+        *out = match self.play_till_end()? {
+            CheckOutcome::Pass => format!("Program ended successfully.\n{}", self),
+            CheckOutcome::False => format!("Program ended with false!\n{}", self),
+            CheckOutcome::UnexpectedStack(stack) => {
+                format!("Program ended with unexpected stack: {:?}\n{}", stack, self)
+            }
+            CheckOutcome::Panic(e) => format!("Program panic: {}\n{}", e, self),
+            CheckOutcome::OutOfGas(e) => format!("Program ran {}\n{}", e, self),
+        };
+        Ok(())
+    }
+
+    /// Play until the program ends or panics and classify the result.
+    pub fn play_till_end(&mut self) -> anyhow::Result<CheckOutcome> {
         loop {
             match self.step_forward()? {
                 Outcome::Step => (),
-                Outcome::ProgramEnd => match &self.stack[..] {
-                    [1] => {
-                        *out = format!("Program ended successfully.\n{}", self);
-                        break;
-                    }
-                    [0] => {
-                        *out = format!("Program ended with false!\n{}", self);
-                        break;
-                    }
-                    _ => {
-                        *out = format!(
-                            "Program ended with unexpected stack: {:?}\n{}",
-                            self.stack, self
-                        );
-                        break;
-                    }
-                },
-                Outcome::Panic(e) => {
-                    *out = format!("Program panic: {:?}\n{}", e, self);
-                    break;
-                }
+                Outcome::ProgramEnd => return Ok(self.end_outcome()),
+                Outcome::Panic(e) => return Ok(CheckOutcome::Panic(format!("{:?}", e))),
+                Outcome::OutOfGas(e) => return Ok(CheckOutcome::OutOfGas(e)),
+            }
+        }
+    }
+
+    /// The outcome of a program that has ended, judged by what is left on the stack.
+    fn end_outcome(&self) -> CheckOutcome {
+        match &self.stack[..] {
+            [1] => CheckOutcome::Pass,
+            [0] => CheckOutcome::False,
+            stack => CheckOutcome::UnexpectedStack(stack.to_vec()),
+        }
+    }
+
+    pub fn continue_to_breakpoint(&mut self, out: &mut String) -> anyhow::Result<()> {
+        match self.run_to_breakpoint()? {
+            (_, Some(id)) => {
+                let bp = self.breakpoints.get(id).expect("Breakpoint was hit");
+                *out = format!("Breakpoint {} hit at {}.\n{}", id, bp, self);
+            }
+            (outcome, None) => {
+                *out = format!("{}", self);
+                handle_outcome(outcome, out);
             }
         }
         Ok(())
     }
 
+    /// Play until a breakpoint is hit or the program ends or panics.
+    ///
+    /// Returns the outcome of the last step and the id of the breakpoint if one was hit.
+    pub fn run_to_breakpoint(&mut self) -> anyhow::Result<(Outcome, Option<usize>)> {
+        // The op at the start is only checked once so continuing moves on.
+        if *self.pos == 0 && !self.breakpoints.hit_at_start {
+            if let Some(id) = self.hit_breakpoint() {
+                self.breakpoints.hit_at_start = true;
+                return Ok((Outcome::Step, Some(id)));
+            }
+        }
+        loop {
+            let outcome = self.step_forward()?;
+            if let Outcome::Step = outcome {
+                if let Some(id) = self.hit_breakpoint() {
+                    return Ok((outcome, Some(id)));
+                }
+                continue;
+            }
+            return Ok((outcome, None));
+        }
+    }
+
+    fn hit_breakpoint(&mut self) -> Option<usize> {
+        if self.breakpoints.is_empty() {
+            return None;
+        }
+        // Copying memory is only worth it if a condition reads it.
+        let memory = if self.breakpoints.reads_memory() {
+            memory_words(self.memory)
+        } else {
+            vec![]
+        };
+        let ctx = Context {
+            pos: *self.pos,
+            pc: *self.pc,
+            stack: &self.stack[..],
+            memory: &memory,
+            decision_vars: &self.data.decision_variables,
+            op: (&*self.code).op_access(*self.pc).and_then(|op| op.ok()),
+        };
+        self.breakpoints.hit(&ctx)
+    }
+
+    pub fn breakpoints(&self) -> &Breakpoints {
+        self.breakpoints
+    }
+
+    pub fn breakpoints_mut(&mut self) -> &mut Breakpoints {
+        self.breakpoints
+    }
+
     pub fn step_forward(&mut self) -> anyhow::Result<Outcome> {
         let Self {
             code,
@@ -533,11 +1171,22 @@ impl Session<'_> {
             last_op,
             solution,
             index,
+            data: _,
             mutable_keys,
             transient_data,
             pre,
             post,
+            state: _,
+            abi: _,
+            gas,
+            state_read_gas: _,
+            pre_provenance: _,
+            post_provenance: _,
             pos,
+            gas_spent,
+            breakpoints: _,
+            checkpoints: _,
+            trace,
         } = self;
 
         let access = Access {
@@ -561,27 +1210,124 @@ impl Session<'_> {
 
         last_op.replace(op);
 
-        let result = match essential_constraint_vm::step_op(access, op, stack, memory, **pc, repeat)
-        {
-            Ok(r) => r,
-            Err(e) => {
-                *pos += 1;
-                return Ok(Outcome::Panic(e));
+        let op_gas = gas.constraint_cost(&op);
+        let spent = gas.spend(**pc, **gas_spent, op_gas);
+
+        let before = trace.is_enabled().then(|| trace::Before {
+            pc: **pc,
+            stack: stack.to_vec(),
+            memory: memory_words(memory),
+        });
+
+        // An op that would go over the limit isn't played.
+        let result = match spent {
+            Ok(spent) => {
+                **gas_spent = spent;
+                // Repeat ops use the debugger's own repeat stack.
+                Ok(repeat::step_op(op, **pc, stack, repeat).unwrap_or_else(|| {
+                    essential_constraint_vm::step_op(
+                        access,
+                        op,
+                        stack,
+                        memory,
+                        **pc,
+                        &mut Repeat::new(),
+                    )
+                }))
             }
+            Err(e) => Err(e),
         };
-        *pos += 1;
+        **pos += 1;
 
-        match result {
-            Some(ProgramControlFlow::Pc(new_pc)) => {
-                **pc = new_pc;
-                Ok(Outcome::Step)
-            }
-            Some(ProgramControlFlow::Halt) => Ok(Outcome::ProgramEnd),
-            None => {
-                **pc += 1;
-                Ok(Outcome::Step)
+        let outcome = match result {
+            Err(e) => Outcome::OutOfGas(e),
+            Ok(result) => match result {
+                Ok(Some(ProgramControlFlow::Pc(new_pc))) => {
+                    **pc = new_pc;
+                    Outcome::Step
+                }
+                Ok(Some(ProgramControlFlow::Halt)) => Outcome::ProgramEnd,
+                Ok(None) => {
+                    **pc += 1;
+                    Outcome::Step
+                }
+                Err(e) => Outcome::Panic(e),
+            },
+        };
+        if let Some(before) = before {
+            let memory = memory_words(memory);
+            let repeat = repeat.iter().map(|slot| slot.counter).collect();
+            trace.record(
+                before,
+                trace::After {
+                    pos: **pos,
+                    op,
+                    stack,
+                    memory: &memory,
+                    repeat,
+                    gas: op_gas,
+                    gas_spent: **gas_spent,
+                    outcome: &outcome,
+                },
+            );
+        }
+        if let Outcome::Step = outcome {
+            self.record_checkpoint();
+        }
+        Ok(outcome)
+    }
+
+    fn record_checkpoint(&mut self) {
+        if !self.checkpoints.wants(*self.pos) {
+            return;
+        }
+        self.checkpoints.insert(Checkpoint {
+            pos: *self.pos,
+            pc: *self.pc,
+            stack: self.stack.clone(),
+            memory: memory_words(self.memory),
+            repeat: self.repeat.clone(),
+            last_op: *self.last_op,
+            gas: *self.gas_spent,
+        });
+    }
+
+    /// Play to the ith op starting from the nearest checkpoint
+    /// instead of replaying from the start.
+    pub fn seek(&mut self, i: usize) -> anyhow::Result<Outcome> {
+        let i = i.max(1);
+        let checkpoint = self.checkpoints.nearest(i - 1);
+        let from_current =
+            (1..i).contains(&*self.pos) && checkpoint.is_none_or(|c| c.pos <= *self.pos);
+        if !from_current {
+            match checkpoint {
+                Some(c) => {
+                    c.restore(self.stack, self.memory, self.repeat);
+                    *self.pc = c.pc;
+                    *self.pos = c.pos;
+                    *self.last_op = c.last_op;
+                    *self.gas_spent = c.gas;
+                }
+                None => self.reset_session(),
             }
         }
+        self.play_to(i - *self.pos)
+    }
+
+    pub fn trace(&self) -> &Trace {
+        self.trace
+    }
+
+    pub fn trace_mut(&mut self) -> &mut Trace {
+        self.trace
+    }
+
+    pub fn checkpoints(&self) -> &Checkpoints {
+        self.checkpoints
+    }
+
+    pub fn checkpoints_mut(&mut self) -> &mut Checkpoints {
+        self.checkpoints
     }
 
     pub fn play_to(&mut self, i: usize) -> anyhow::Result<Outcome> {
@@ -591,6 +1337,7 @@ impl Session<'_> {
             match self.step_forward()? {
                 Outcome::ProgramEnd => return Ok(Outcome::ProgramEnd),
                 Outcome::Panic(e) => return Ok(Outcome::Panic(e)),
+                Outcome::OutOfGas(e) => return Ok(Outcome::OutOfGas(e)),
                 Outcome::Step => {
                     out = Some(Outcome::Step);
                 }
@@ -604,11 +1351,11 @@ impl Session<'_> {
 
     pub fn list_range(&self, range: Range<isize>, out: &mut String) {
         use std::fmt::Write;
-        let start = (self.pos as isize).saturating_add(range.start).max(0) as usize;
-        let end = (self.pos as isize).saturating_add(range.end).max(0) as usize;
+        let start = (*self.pos as isize).saturating_add(range.start).max(0) as usize;
+        let end = (*self.pos as isize).saturating_add(range.end).max(0) as usize;
         let len = end.saturating_sub(start);
         let this_op = (start..end)
-            .contains(&self.pos)
+            .contains(&*self.pos)
             .then_some(self.pos.saturating_sub(start));
         if let Some(ops) = &self.code.ops_from(start) {
             *out = ops
@@ -641,7 +1388,7 @@ impl Session<'_> {
                 .ops()
                 .enumerate()
                 .fold(String::new(), |mut out, (i, op)| {
-                    if self.pos == i {
+                    if *self.pos == i {
                         let _ =
                             writeln!(out, "{}:Op: {:?}", i, dialoguer::console::style(op).cyan());
                     } else {
@@ -652,8 +1399,200 @@ impl Session<'_> {
         }
     }
 
-    pub fn parse_type(&self, ty: &str) -> String {
-        parse_types::parse_type(&self.stack[..], ty)
+    /// Show solution data or state without a menu.
+    ///
+    /// e.g. `pre 0`, `post 1`, `dv 2` or `transient 0 1 2`
+    /// where the transient data index is followed by the key.
+    pub fn show(&self, args: &[&str]) -> String {
+        let usage =
+            || "Usage: s <pre | post | dv> <i> or s transient <solution data> <key..>".to_string();
+        let Some((what, args)) = args.split_first() else {
+            return usage();
+        };
+        if *what == "storage" {
+            return match args {
+                [which] => self.show_storage(which),
+                _ => "Usage: s storage <pre | post | mutations>".to_string(),
+            };
+        }
+        if matches!(*what, "dv" | "decision") {
+            match args {
+                [] | ["vars"] => return self.show_decision_vars(),
+                [name] if name.parse::<Word>().is_err() => {
+                    return match self.abi.and_then(|abi| abi.predicate.var(name)) {
+                        Some((i, _)) => self.show_decision_var(i),
+                        None => format!("No decision variable named {}", name),
+                    };
+                }
+                _ => (),
+            }
+        }
+        let Ok(args) = args
+            .iter()
+            .map(|a| a.parse::<Word>())
+            .collect::<Result<Vec<_>, _>>()
+        else {
+            return format!("Invalid index in: {}", args.join(" "));
+        };
+        let slot = args.first().and_then(|i| usize::try_from(*i).ok());
+        match (*what, slot) {
+            ("pre", Some(i)) => match self.pre.get(i) {
+                Some(v) => show_slot("Pre", i, v, self.pre_provenance),
+                None => format!("No pre state slot {}", i),
+            },
+            ("post", Some(i)) => match self.post.get(i) {
+                Some(v) => show_slot("Post", i, v, self.post_provenance),
+                None => format!("No post state slot {}", i),
+            },
+            ("dv" | "decision", Some(i)) => self.show_decision_var(i),
+            ("transient", Some(i)) => {
+                let key = args[1..].to_vec();
+                match u16::try_from(i)
+                    .ok()
+                    .and_then(|i| self.transient_data.get(&i))
+                    .and_then(|t| t.get(&key))
+                {
+                    Some(v) => format!("Transient data: {:?} => {:?}", key, v),
+                    None => format!("No transient data for {:?} in solution data {}", key, i),
+                }
+            }
+            _ => usage(),
+        }
+    }
+
+    /// Show a decision variable, by name and type if the ABI is known.
+    pub fn show_decision_var(&self, i: usize) -> String {
+        let Some(v) = self.data.decision_variables.get(i) else {
+            return format!("No decision variable {}", i);
+        };
+        match self.abi.and_then(|abi| abi.predicate.vars.get(i)) {
+            Some(var) => var.show(v),
+            None => format!("Decision variable {}: {:?}", i, v),
+        }
+    }
+
+    /// Gas spent by this constraint so far.
+    pub fn gas_spent(&self) -> Gas {
+        *self.gas_spent
+    }
+
+    /// Show the gas spent by each state read program and this constraint so far.
+    pub fn show_gas(&self) -> String {
+        format!(
+            "{}Constraint: {}",
+            gas::show_state_read(self.state_read_gas),
+            gas::show_spent(*self.gas_spent, self.gas.limit)
+        )
+    }
+
+    /// Show every decision variable on its own line.
+    pub fn show_decision_vars(&self) -> String {
+        let len = self.data.decision_variables.len();
+        if len == 0 {
+            return "No decision variables".to_string();
+        }
+        (0..len)
+            .map(|i| self.show_decision_var(i))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Show the pre state, post state or mutations of the contract
+    /// being solved, with storage names from the ABI if it is known.
+    pub fn show_storage(&self, which: &str) -> String {
+        let storage = self.abi.map(|abi| &abi.storage[..]).unwrap_or_default();
+        let data = self.data;
+        let contract = &data.predicate_to_solve.contract;
+        match which {
+            "pre" => storage::show_entries(
+                storage,
+                &self.state.get(contract).cloned().unwrap_or_default(),
+            ),
+            "post" => storage::show_entries(
+                storage,
+                &state::post_state(self.solution, self.state)
+                    .remove(contract)
+                    .unwrap_or_default(),
+            ),
+            "mut" | "mutations" => storage::show_entries(
+                storage,
+                data.state_mutations.iter().map(|m| (&m.key, &m.value)),
+            ),
+            _ => format!(
+                "Unknown storage `{}`. Expected pre, post or mutations",
+                which
+            ),
+        }
+    }
+
+    /// Diff the pre and post state, the pre and post state slots or both.
+    pub fn show_diff(&self, which: &str) -> String {
+        let state = || {
+            let storage = self.abi.map(|abi| &abi.storage[..]).unwrap_or_default();
+            let contract = &self.data.predicate_to_solve.contract;
+            let post = state::post_state(self.solution, self.state);
+            diff::diff_state(self.state, &post, contract, storage)
+        };
+        match which {
+            "" => format!(
+                "State:\n{}\nSlots:\n{}",
+                state(),
+                diff::diff_slots(self.pre, self.post)
+            ),
+            "state" => state(),
+            "slots" => diff::diff_slots(self.pre, self.post),
+            _ => format!("Unknown diff `{}`. Expected state or slots", which),
+        }
+    }
+
+    /// Decode words as a type.
+    ///
+    /// The input can start with a target to decode from instead of the stack.
+    /// e.g. `dv[0] int[3]`, `post[2] {b256, int}` or `mem[4] 1 b256`.
+    /// Decision variables can be named if the ABI is known, e.g. `::amount`,
+    /// in which case the type defaults to the one in the ABI.
+    pub fn parse_type(&mut self, input: &str) -> Result<String, TypeError> {
+        let (target, ty) = Target::parse(input)?;
+        let var = match &target {
+            Target::NamedVar(name) => Some(
+                self.abi
+                    .and_then(|abi| abi.predicate.var(name))
+                    .ok_or_else(|| TypeError::MissingTarget(target.clone()))?,
+            ),
+            Target::DecisionVar(i) => self
+                .abi
+                .and_then(|abi| abi.predicate.vars.get(*i))
+                .map(|var| (*i, var)),
+            _ => None,
+        };
+        let default_ty = var.and_then(|(_, var)| var.ty.to_type());
+        let ty = match default_ty {
+            Some(default_ty) if ty.trim().trim_end_matches("HEX").trim().is_empty() => {
+                format!("{} {}", default_ty, ty.trim())
+            }
+            _ => ty.to_string(),
+        };
+        let memory;
+        let words: Option<&[Word]> = match &target {
+            Target::Stack => Some(&self.stack[..]),
+            Target::DecisionVar(i) => self.data.decision_variables.get(*i).map(|v| &v[..]),
+            Target::NamedVar(_) => var
+                .and_then(|(i, _)| self.data.decision_variables.get(i))
+                .map(|v| &v[..]),
+            Target::PreState(i) => self.pre.get(*i).map(|v| &v[..]),
+            Target::PostState(i) => self.post.get(*i).map(|v| &v[..]),
+            Target::Memory(start) => {
+                memory = memory_words(self.memory);
+                memory.get(*start..)
+            }
+            Target::Transient(i, key) => u16::try_from(*i)
+                .ok()
+                .and_then(|i| self.transient_data.get(&i))
+                .and_then(|t| t.get(key))
+                .map(|v| &v[..]),
+        };
+        let words = words.ok_or_else(|| TypeError::MissingTarget(target.clone()))?;
+        decode_type(words, &ty)
     }
 }
 
@@ -1,29 +1,109 @@
-use std::path::PathBuf;
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::IsTerminal,
+    path::PathBuf,
+};
 
+use anyhow::bail;
 use clap::{Parser, Subcommand};
-use essential_types::{
-    contract::{Contract, SignedContract},
-    predicate::Predicate,
-    solution::Solution,
-};
+use essential_debugger::{CheckOutcome, GasConfig, Launch, LaunchArgs};
+use essential_types::{predicate::Predicate, solution::Solution, ContentAddress, Key, Value};
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Cli {
     /// Which solution data index to debug
     #[arg(short, long, default_value_t = 0)]
-    solution_data_index: usize,
+    solution_data_index: u16,
     /// Which predicate to debug
     #[arg(short, long, default_value_t = 0)]
     predicate_index: usize,
     /// Which constraint to debug
     #[arg(short, long, default_value_t = 0)]
     constraint_index: usize,
+    /// Path to a pre-state file encoded in JSON.
+    /// Maps contract addresses to a list of `{ "key": [..], "value": [..] }` entries.
+    #[arg(long)]
+    state: Option<PathBuf>,
+    /// Play the constraint to the end and write a trace of every step
+    /// to this file as JSON Lines instead of starting the debugger.
+    #[arg(long)]
+    trace: Option<PathBuf>,
+    /// Check every constraint of the predicate without starting the debugger.
+    /// Prints a table of results and exits with an error if any fail.
+    #[arg(long)]
+    check: bool,
+    /// With `--check`, check every predicate against the
+    /// solution data that solves it instead of only the selected predicate.
+    #[arg(long, requires = "check")]
+    all_predicates: bool,
+    /// Read debugger commands from this file instead of the terminal, one per line.
+    /// Use `-` to read from stdin. Commands are read from stdin
+    /// automatically when it isn't a terminal.
+    #[arg(long)]
+    script: Option<PathBuf>,
+    /// Path to the contract ABI produced by the Pint compiler.
+    /// Decision variables are shown by name and type.
+    #[arg(long)]
+    abi: Option<PathBuf>,
+    /// Path to the Pint source of the predicate, shown by the `code` command.
+    #[arg(long)]
+    predicate_source: Option<PathBuf>,
+    /// Path to other Pint source to show with `code all`, e.g. constants.
+    #[arg(long, requires = "predicate_source")]
+    other_source: Option<PathBuf>,
+    /// Line number (starting at 1) of the constraint in the predicate source.
+    /// Defaults to the line of the constraint at `--constraint-index`.
+    #[arg(long, requires = "predicate_source")]
+    constraint_line: Option<usize>,
+    /// Path to a source map from ops to spans of the predicate source.
+    /// The expression of the next op is highlighted by the `code` command.
+    #[arg(long, requires = "predicate_source")]
+    source_map: Option<PathBuf>,
+    /// Which predicate in the ABI to use, e.g. `::Transfer`.
+    /// Defaults to the predicate at the same index as `--predicate-index`.
+    #[arg(long, requires = "abi")]
+    abi_predicate: Option<String>,
+    /// Serve the Debug Adapter Protocol over stdio for editors.
+    /// The constraint to debug is given by the client's `launch` request.
+    #[arg(long, exclusive = true)]
+    dap: bool,
+    /// Start a full screen terminal UI with panes for the ops, source, stack,
+    /// memory, repeat counters and state slots instead of the prompt.
+    #[arg(long, conflicts_with_all = ["script", "trace", "check"])]
+    tui: bool,
+    /// Serve newline delimited JSON-RPC 2.0 over stdio instead of starting the debugger.
+    #[arg(long, conflicts_with_all = ["tui", "state_read", "script", "trace", "check"])]
+    rpc: bool,
+    /// Serve newline delimited JSON-RPC 2.0 to the first client
+    /// that connects to a Unix socket at this path.
+    #[cfg(unix)]
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "tui", "rpc", "state_read", "script", "trace", "check"
+        ]
+    )]
+    rpc_socket: Option<PathBuf>,
+    /// The most gas each state read program and constraint may spend.
+    /// Stops with the op that would go over the limit.
+    #[arg(long)]
+    gas_limit: Option<u64>,
+    /// Path to a JSON file of op costs, e.g.
+    /// `{ "default": 1, "constraint": { "Alu::Add": 2 }, "state_read": { "StateRead::KeyRange": 100 } }`.
+    /// Ops not listed cost the default, which is 1 if not given.
+    #[arg(long)]
+    gas_costs: Option<PathBuf>,
+    /// Step through the state read program at this index instead of a constraint.
+    /// Starts reading from the pre state. Use `run post` to read from the post state.
+    #[arg(long, conflicts_with_all = ["tui", "rpc", "trace", "check"])]
+    state_read: Option<usize>,
     /// Path to the solution file encoded in JSON
-    solution: PathBuf,
+    #[arg(required_unless_present = "dap")]
+    solution: Option<PathBuf>,
     /// Select a subcommand to run
     #[command(subcommand)]
-    command: Command,
+    command: Option<Command>,
 }
 
 #[derive(Subcommand)]
@@ -47,44 +127,291 @@ async fn main() {
     let args = Cli::parse();
     if let Err(e) = run(args).await {
         eprintln!("Command failed because: {}", e);
+        std::process::exit(1);
     }
 }
 
 async fn run(args: Cli) -> anyhow::Result<()> {
-    // TODO state.
     let Cli {
         solution_data_index,
         predicate_index,
         constraint_index,
+        state,
+        trace,
+        check,
+        all_predicates,
+        script,
+        predicate_source,
+        other_source,
+        constraint_line,
+        source_map,
+        abi,
+        abi_predicate,
+        dap,
+        tui,
+        rpc,
+        #[cfg(unix)]
+        rpc_socket,
+        gas_limit,
+        gas_costs,
+        state_read,
         solution,
         command,
     } = args;
-    let predicates: Vec<Predicate> = match command {
-        Command::Predicate { predicate } => {
-            vec![serde_json::from_slice(&tokio::fs::read(predicate).await?)?]
-        }
-        Command::Contract { contract } => {
-            let contract: Contract = serde_json::from_slice(&tokio::fs::read(contract).await?)?;
-            contract.predicates
-        }
-        Command::SignedContract { contract } => {
-            let contract: SignedContract =
-                serde_json::from_slice(&tokio::fs::read(contract).await?)?;
-            contract.contract.predicates
-        }
+    if dap {
+        return essential_debugger::run_dap(std::io::stdin().lock(), std::io::stdout().lock())
+            .await;
+    }
+    let (Some(solution), Some(command)) = (solution, command) else {
+        bail!("A solution and a predicate, contract or signed-contract are required");
     };
-
-    let solution: Solution = serde_json::from_slice(&tokio::fs::read(solution).await?)?;
-    let predicate = predicates
-        .get(predicate_index)
-        .ok_or_else(|| anyhow::anyhow!("Predicate not found"))?
-        .clone();
-    essential_debugger::run(
+    let (predicate, contract, signed_contract) = match command {
+        Command::Predicate { predicate } => (Some(predicate), None, None),
+        Command::Contract { contract } => (None, Some(contract), None),
+        Command::SignedContract { contract } => (None, None, Some(contract)),
+    };
+    let launch = LaunchArgs {
         solution,
-        solution_data_index as u16,
         predicate,
+        contract,
+        signed_contract,
+        solution_data_index,
+        predicate_index,
         constraint_index,
-        Default::default(),
-    )
-    .await
+        state,
+        abi,
+        abi_predicate,
+        predicate_source,
+        other_source,
+        constraint_line,
+        source_map,
+        stop_on_entry: None,
+        gas_limit,
+        gas_costs,
+    }
+    .load()
+    .await?;
+
+    if check {
+        // Pairs of predicate index and solution data index to check.
+        let targets: Vec<(usize, u16)> = if all_predicates {
+            launch
+                .predicates
+                .iter()
+                .enumerate()
+                .flat_map(|(p, predicate)| {
+                    let addr = essential_hash::content_addr(predicate);
+                    launch
+                        .solution
+                        .data
+                        .iter()
+                        .enumerate()
+                        .filter(move |(_, data)| data.predicate_to_solve.predicate == addr)
+                        .map(move |(d, _)| (p, d as u16))
+                })
+                .collect()
+        } else {
+            vec![(predicate_index, solution_data_index)]
+        };
+        return check_predicates(
+            &launch.solution,
+            &launch.predicates,
+            &targets,
+            &launch.state,
+            &launch.gas,
+        )
+        .await;
+    }
+
+    let predicate = launch.predicate(predicate_index)?;
+    // Fail before the socket is bound rather than after a client connects.
+    launch.solution_data(solution_data_index)?;
+    let Launch {
+        solution,
+        state,
+        abi,
+        source,
+        gas,
+        ..
+    } = launch;
+    if let Some(path) = trace {
+        let trace = essential_debugger::trace(
+            solution,
+            solution_data_index,
+            predicate,
+            constraint_index,
+            state,
+            gas,
+        )
+        .await?;
+        let file = std::fs::File::create(path)?;
+        return trace.write_jsonl(std::io::BufWriter::new(file));
+    }
+    if rpc {
+        return essential_debugger::run_rpc(
+            solution,
+            solution_data_index,
+            predicate,
+            constraint_index,
+            state,
+            abi,
+            gas,
+            std::io::stdin().lock(),
+            std::io::stdout().lock(),
+        )
+        .await;
+    }
+    #[cfg(unix)]
+    if let Some(path) = rpc_socket {
+        let listener = std::os::unix::net::UnixListener::bind(&path)?;
+        let _socket = SocketFile(&path);
+        let (stream, _) = listener.accept()?;
+        return essential_debugger::run_rpc(
+            solution,
+            solution_data_index,
+            predicate,
+            constraint_index,
+            state,
+            abi,
+            gas,
+            std::io::BufReader::new(stream.try_clone()?),
+            stream,
+        )
+        .await;
+    }
+    if tui {
+        return essential_debugger::run_tui(
+            solution,
+            solution_data_index,
+            predicate,
+            constraint_index,
+            state,
+            source,
+            abi,
+            gas,
+        )
+        .await;
+    }
+    let script = match script {
+        Some(path) if path.as_os_str() == "-" => Some(None),
+        Some(path) => Some(Some(path)),
+        None if !std::io::stdin().is_terminal() => Some(None),
+        None => None,
+    };
+    if let Some(program) = state_read {
+        return match script {
+            Some(path) => {
+                let script: Box<dyn std::io::BufRead> = match path {
+                    Some(path) => Box::new(std::io::BufReader::new(std::fs::File::open(path)?)),
+                    None => Box::new(std::io::stdin().lock()),
+                };
+                essential_debugger::run_state_read_script(
+                    solution,
+                    solution_data_index,
+                    predicate,
+                    program,
+                    state,
+                    gas,
+                    script,
+                    std::io::stdout().lock(),
+                )
+                .await
+            }
+            None => {
+                essential_debugger::run_state_read(
+                    solution,
+                    solution_data_index,
+                    predicate,
+                    program,
+                    state,
+                    gas,
+                )
+                .await
+            }
+        };
+    }
+    match script {
+        Some(path) => {
+            let script: Box<dyn std::io::BufRead> = match path {
+                Some(path) => Box::new(std::io::BufReader::new(std::fs::File::open(path)?)),
+                None => Box::new(std::io::stdin().lock()),
+            };
+            essential_debugger::run_script(
+                solution,
+                solution_data_index,
+                predicate,
+                constraint_index,
+                state,
+                source,
+                abi,
+                gas,
+                script,
+                std::io::stdout().lock(),
+            )
+            .await
+        }
+        None => {
+            essential_debugger::run_with_gas(
+                solution,
+                solution_data_index,
+                predicate,
+                constraint_index,
+                state,
+                source,
+                abi,
+                gas,
+            )
+            .await
+        }
+    }
+}
+
+/// Removes the Unix socket file when dropped, even if serving failed.
+#[cfg(unix)]
+struct SocketFile<'a>(&'a std::path::Path);
+
+#[cfg(unix)]
+impl Drop for SocketFile<'_> {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(self.0);
+    }
+}
+
+async fn check_predicates(
+    solution: &Solution,
+    predicates: &[Predicate],
+    targets: &[(usize, u16)],
+    state: &HashMap<ContentAddress, BTreeMap<Key, Value>>,
+    gas: &GasConfig,
+) -> anyhow::Result<()> {
+    if targets.is_empty() {
+        bail!("No solution data solves any of the predicates");
+    }
+    println!(
+        "{:<10} {:<14} {:<11} result",
+        "predicate", "solution data", "constraint"
+    );
+    let mut total = 0;
+    let mut failed = 0;
+    for &(p, d) in targets {
+        let predicate = predicates
+            .get(p)
+            .ok_or_else(|| anyhow::anyhow!("Predicate not found"))?;
+        let outcomes =
+            essential_debugger::check_predicate(solution, d, predicate, state, gas).await?;
+        for (c, outcome) in outcomes.iter().enumerate() {
+            let result = match outcome {
+                CheckOutcome::Pass => dialoguer::console::style(outcome).green(),
+                _ => dialoguer::console::style(outcome).red(),
+            };
+            println!("{:<10} {:<14} {:<11} {}", p, d, c, result);
+        }
+        total += outcomes.len();
+        failed += outcomes.iter().filter(|o| !o.is_pass()).count();
+    }
+    if failed > 0 {
+        bail!("{} of {} constraints failed", failed, total);
+    }
+    println!("All {} constraints passed", total);
+    Ok(())
 }
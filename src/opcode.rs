@@ -0,0 +1,48 @@
+use std::{collections::BTreeMap, fmt::Debug};
+
+use essential_constraint_asm as constraint_asm;
+use essential_state_asm as state_asm;
+
+#[cfg(test)]
+mod tests;
+
+/// Opcodes named `Category::Variant` as they are written in assembly, e.g. `Alu::Add`.
+///
+/// Gas cost files, op breakpoints and the RPC share these names.
+pub(crate) trait OpName: Sized + Copy + TryFrom<u8> {
+    fn name(&self) -> String;
+
+    /// Every opcode by name.
+    fn by_name() -> BTreeMap<String, Self> {
+        (0..=u8::MAX)
+            .filter_map(|b| Self::try_from(b).ok())
+            .map(|o| (o.name(), o))
+            .collect()
+    }
+}
+
+impl OpName for constraint_asm::Opcode {
+    fn name(&self) -> String {
+        category_variant(self, "")
+    }
+}
+
+impl OpName for state_asm::Opcode {
+    /// Constraint ops are named as they are in constraints.
+    /// State read ops without a category, such as `KeyRange`, are `StateRead` ops.
+    fn name(&self) -> String {
+        match self {
+            Self::Constraint(opcode) => opcode.name(),
+            opcode => category_variant(opcode, "StateRead"),
+        }
+    }
+}
+
+/// Turn the debug name `Category(Variant)` into `Category::Variant`.
+fn category_variant(opcode: &impl Debug, category: &str) -> String {
+    let name = format!("{:?}", opcode);
+    match name.trim_end_matches(')').split_once('(') {
+        Some((category, variant)) => format!("{}::{}", category, variant),
+        None => format!("{}::{}", category, name),
+    }
+}
@@ -0,0 +1,20 @@
+use super::*;
+use constraint_asm::ToOpcode;
+
+#[test]
+fn test_names() {
+    let add = constraint_asm::Op::from(constraint_asm::Alu::Add).to_opcode();
+    assert_eq!(add.name(), "Alu::Add");
+    assert_eq!(constraint_asm::Opcode::by_name()["Alu::Add"], add);
+
+    let state_read = state_asm::Opcode::by_name();
+    assert_eq!(
+        state_read["StateRead::KeyRange"],
+        state_asm::StateRead::KeyRange.to_opcode()
+    );
+    assert_eq!(state_read["Alu::Add"], state_asm::Opcode::Constraint(add));
+    assert_eq!(
+        state_read["StateSlots::AllocSlots"],
+        state_asm::Op::from(state_asm::StateSlots::AllocSlots).to_opcode()
+    );
+}
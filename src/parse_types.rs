@@ -1,134 +1,449 @@
+use std::{fmt::Display, str::FromStr};
+
 use essential_types::{
     convert::{bool_from_word, bytes_from_word},
     Word,
 };
 
-pub fn parse_type(words: &[Word], ty: &str) -> String {
+#[cfg(test)]
+mod tests;
+
+/// A Pint type that can be decoded from words.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    Int,
+    Bool,
+    B256,
+    /// An array of the element type with a fixed length.
+    Array(Box<Type>, usize),
+    /// A tuple with optionally named fields.
+    Tuple(Vec<Field>),
+}
+
+/// Where the words to decode come from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    Stack,
+    DecisionVar(usize),
+    /// A decision variable named in the predicate ABI.
+    NamedVar(String),
+    PreState(usize),
+    PostState(usize),
+    /// Memory starting at this address.
+    Memory(usize),
+    /// Transient data of a solution data index at a key.
+    Transient(usize, Vec<Word>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub name: Option<String>,
+    pub ty: Type,
+}
+
+/// Why words couldn't be decoded as a type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeError {
+    /// The type name is not one of the primitives.
+    UnknownType(String),
+    /// The type or target is malformed.
+    Syntax(String),
+    /// The start position is past the end of the words.
+    OutOfRange { pos: usize, len: usize },
+    /// There are fewer words than the type needs.
+    InsufficientWords {
+        ty: Type,
+        needed: usize,
+        found: usize,
+    },
+    /// A word that isn't 0 or 1 was decoded as a bool.
+    InvalidBool(Word),
+    /// The type needs more words than can be counted.
+    TooLarge(Type),
+    /// An array of a type with no words can't be split into elements.
+    ZeroSizedElement(Type),
+    /// The target to decode from doesn't exist.
+    MissingTarget(Target),
+}
+
+/// Decode words as a type.
+///
+/// The type can be preceded by a start position and followed by `HEX`
+/// to force hex formatting. e.g. `1 {int, bool}[2] HEX`
+pub fn decode_type(words: &[Word], ty: &str) -> Result<String, TypeError> {
     let ty = ty.trim();
     let force_hex = ty.ends_with("HEX");
     let ty = ty.trim_end_matches("HEX").trim();
-    if ty.starts_with(|a: char| a.is_ascii_digit()) {
-        let Some(pos) = ty
-            .split(' ')
-            .next()
-            .and_then(|pos| pos.parse::<usize>().ok())
-        else {
-            return String::new();
-        };
-        let ty = ty.trim_start_matches(|a: char| a.is_ascii_digit()).trim();
+    let (words, ty) = if ty.starts_with(|a: char| a.is_ascii_digit()) {
+        let (pos, ty) = ty.split_once(' ').unwrap_or((ty, ""));
+        let pos = pos
+            .parse::<usize>()
+            .map_err(|_| TypeError::Syntax(format!("invalid position `{}`", pos)))?;
         if pos >= words.len() {
-            return String::new();
+            return Err(TypeError::OutOfRange {
+                pos,
+                len: words.len(),
+            });
         }
-        parse_ty(&words[pos..], ty, force_hex)
+        (&words[pos..], ty.trim())
     } else {
-        parse_ty(words, ty, force_hex)
-    }
+        (words, ty)
+    };
+    ty.parse::<Type>()?.decode(words, force_hex)
 }
 
-fn parse_ty(words: &[Word], ty: &str, force_hex: bool) -> String {
-    let ty = ty.trim();
-    if ty.starts_with(|a: char| a.is_ascii_alphabetic()) && ty.ends_with(']') {
-        let mut iter = ty.trim_end_matches(']').split('[');
-        let Some(ty) = iter.next() else {
-            return String::new();
+impl Target {
+    /// Split a target such as `dv[0]` or `post[2]` off the front of the input.
+    ///
+    /// Input without a target refers to the stack.
+    /// Decision variables can also be named, e.g. `dv[amount]` or `::amount`.
+    pub fn parse(input: &str) -> Result<(Target, &str), TypeError> {
+        let input = input.trim_start();
+        if input.starts_with("::") {
+            let (name, rest) = input.split_once(' ').unwrap_or((input, ""));
+            return Ok((Target::NamedVar(name.to_string()), rest));
+        }
+        let Some((name, rest)) = input.split_once('[') else {
+            return Ok((Target::Stack, input));
+        };
+        let (index, rest) = rest
+            .split_once(']')
+            .ok_or_else(|| TypeError::Syntax(format!("expected `]` after `{}[`", name)))?;
+        let parse_index = |i: &str| {
+            i.trim().parse::<usize>().map_err(|_| {
+                TypeError::Syntax(format!("invalid index `{}` for `{}`", i.trim(), name))
+            })
+        };
+        let target = match name.trim() {
+            "dv" => match index.trim().parse::<usize>() {
+                Ok(i) => Target::DecisionVar(i),
+                Err(_) if is_name(index.trim()) => Target::NamedVar(index.trim().to_string()),
+                Err(_) => Target::DecisionVar(parse_index(index)?),
+            },
+            "pre" => Target::PreState(parse_index(index)?),
+            "post" => Target::PostState(parse_index(index)?),
+            "mem" | "memory" => Target::Memory(parse_index(index)?),
+            "tr" | "transient" => {
+                let (key, r) = rest
+                    .trim_start()
+                    .strip_prefix('[')
+                    .and_then(|r| r.split_once(']'))
+                    .ok_or_else(|| {
+                        TypeError::Syntax("expected a key e.g. `transient[0][1, 2]`".to_string())
+                    })?;
+                let key = key
+                    .split([',', ' '])
+                    .filter(|k| !k.is_empty())
+                    .map(|k| {
+                        k.parse::<Word>()
+                            .map_err(|_| TypeError::Syntax(format!("invalid key word `{}`", k)))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                return Ok((Target::Transient(parse_index(index)?, key), r));
+            }
+            // Not a target so this must be an array type on the stack.
+            _ => return Ok((Target::Stack, input)),
         };
-        let Some(len) = iter.next().and_then(|len| len.parse::<usize>().ok()) else {
-            return String::new();
+        Ok((target, rest))
+    }
+}
+
+/// A variable name, optionally with a leading `::`.
+fn is_name(s: &str) -> bool {
+    let s = s.trim_start_matches("::");
+    s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+}
+
+impl Type {
+    /// Number of words this type occupies.
+    pub fn size(&self) -> Result<usize, TypeError> {
+        let size = match self {
+            Type::Int | Type::Bool => Some(1),
+            Type::B256 => Some(4),
+            Type::Array(ty, len) => ty.size()?.checked_mul(*len),
+            Type::Tuple(fields) => {
+                let mut size = Some(0usize);
+                for field in fields {
+                    let field_size = field.ty.size()?;
+                    size = size.and_then(|s| s.checked_add(field_size));
+                }
+                size
+            }
         };
-        let size = get_size(ty);
-        let total_size = size * len;
-        if words.len() < total_size {
-            return String::new();
+        size.ok_or_else(|| TypeError::TooLarge(self.clone()))
+    }
+
+    /// Decode the words as this type.
+    pub fn decode(&self, words: &[Word], force_hex: bool) -> Result<String, TypeError> {
+        let size = self.size()?;
+        if words.len() < size {
+            return Err(TypeError::InsufficientWords {
+                ty: self.clone(),
+                needed: size,
+                found: words.len(),
+            });
         }
-        let out = words
-            .chunks_exact(size)
-            .take(len)
-            .map(|words| parse_primitive(words, ty, force_hex))
-            .collect::<Vec<_>>()
-            .join(", ");
-        let out = out.trim().trim_end_matches(',');
-        format!("[{}]", out)
-    } else if ty.starts_with('{') && ty.ends_with('}') {
-        let tys: Vec<&str> = ty
-            .trim_start_matches('{')
-            .trim_end_matches('}')
-            .split(',')
-            .collect();
-        if words.len() < get_total_size(&tys) {
-            return String::new();
+        match self {
+            Type::Int => {
+                let word = words[0];
+                if force_hex {
+                    Ok(hex::encode_upper(bytes_from_word(word)))
+                } else {
+                    Ok(word.to_string())
+                }
+            }
+            Type::Bool => {
+                let word = words[0];
+                if force_hex {
+                    Ok(hex::encode_upper(bytes_from_word(word)))
+                } else {
+                    bool_from_word(word)
+                        .map(|b| b.to_string())
+                        .ok_or(TypeError::InvalidBool(word))
+                }
+            }
+            Type::B256 => {
+                let bytes: Vec<u8> = words[..4]
+                    .iter()
+                    .flat_map(|w| bytes_from_word(*w))
+                    .collect();
+                Ok(hex::encode_upper(bytes))
+            }
+            Type::Array(_, 0) => Ok("[]".to_string()),
+            Type::Array(ty, len) => {
+                let size = ty.size()?;
+                if size == 0 {
+                    return Err(TypeError::ZeroSizedElement((**ty).clone()));
+                }
+                let out = words
+                    .chunks(size)
+                    .take(*len)
+                    .map(|words| ty.decode(words, force_hex))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(format!("[{}]", out.join(", ")))
+            }
+            Type::Tuple(fields) => {
+                let mut start = 0;
+                let out = fields
+                    .iter()
+                    .map(|field| {
+                        let end = start + field.ty.size()?;
+                        let value = field.ty.decode(&words[start..end], force_hex)?;
+                        start = end;
+                        Ok(match &field.name {
+                            Some(name) => format!("{}: {}", name, value),
+                            None => value,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(format!("{{ {} }}", out.join(", ")))
+            }
         }
-        let mut start = 0;
-        let out = tys
-            .iter()
-            .map(|ty| {
-                let end = start + get_size(ty);
-                let o = parse_primitive(&words[start..end], ty, force_hex);
-                start = end;
-                o
-            })
-            .collect::<Vec<_>>()
-            .join(", ");
-        let out = out.trim().trim_end_matches(',');
-        format!("{{ {} }}", out)
-    } else {
-        parse_primitive(words, ty, force_hex)
     }
 }
 
-fn get_total_size(tys: &[&str]) -> usize {
-    tys.iter().map(|ty| get_size(ty)).sum()
-}
+impl FromStr for Type {
+    type Err = TypeError;
 
-fn get_size(ty: &str) -> usize {
-    match ty.trim() {
-        "int" => 1,
-        "bool" => 1,
-        "b256" => 4,
-        _ => 0,
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { s, pos: 0 };
+        let ty = parser.ty()?;
+        parser.skip_whitespace();
+        if parser.pos < s.len() {
+            return Err(TypeError::Syntax(format!(
+                "unexpected `{}`",
+                &s[parser.pos..]
+            )));
+        }
+        Ok(ty)
     }
 }
 
-fn parse_primitive(words: &[Word], ty: &str, force_hex: bool) -> String {
-    match ty.trim() {
-        "int" => {
-            let Some(word) = words.first() else {
-                return String::new();
-            };
-            if force_hex {
-                hex::encode_upper(bytes_from_word(*word))
-            } else {
-                format!("{}", word)
+struct Parser<'a> {
+    s: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn rest(&self) -> &str {
+        &self.s[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Consume `c` if it is the next non-whitespace character.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), TypeError> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(TypeError::Syntax(format!(
+                "expected `{}` at `{}`",
+                c,
+                self.rest()
+            )))
+        }
+    }
+
+    /// Consume an identifier or number.
+    fn word(&mut self) -> &str {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        let start = self.pos;
+        self.pos += len;
+        &self.s[start..self.pos]
+    }
+
+    fn ty(&mut self) -> Result<Type, TypeError> {
+        let mut ty = if self.eat('{') {
+            self.tuple()?
+        } else {
+            match self.word() {
+                "int" => Type::Int,
+                "bool" => Type::Bool,
+                "b256" => Type::B256,
+                "" => {
+                    return Err(TypeError::Syntax(format!(
+                        "expected a type at `{}`",
+                        self.rest()
+                    )))
+                }
+                other => return Err(TypeError::UnknownType(other.to_string())),
             }
+        };
+        // Dimensions are outermost first, so `int[2][3]` is two `int[3]`.
+        let mut dims = Vec::new();
+        while self.eat('[') {
+            let len = self.word();
+            let len = len
+                .parse::<usize>()
+                .map_err(|_| TypeError::Syntax(format!("invalid array length `{}`", len)))?;
+            self.expect(']')?;
+            dims.push(len);
         }
-        "bool" => {
-            let Some(word) = words.first() else {
-                return String::new();
-            };
-            if force_hex {
-                hex::encode_upper(bytes_from_word(*word))
+        for len in dims.into_iter().rev() {
+            ty = Type::Array(Box::new(ty), len);
+        }
+        Ok(ty)
+    }
+
+    fn tuple(&mut self) -> Result<Type, TypeError> {
+        let mut fields = Vec::new();
+        while !self.eat('}') {
+            let start = self.pos;
+            let name = self.word().to_string();
+            let name = if !name.is_empty() && self.eat(':') {
+                Some(name)
             } else {
-                bool_from_word(*word)
-                    .map(|b| b.to_string())
-                    .unwrap_or_default()
+                self.pos = start;
+                None
+            };
+            let ty = self.ty()?;
+            fields.push(Field { name, ty });
+            if !self.eat(',') {
+                self.expect('}')?;
+                break;
             }
         }
-        "b256" => {
-            if words.len() < 4 {
-                return String::new();
+        if fields.is_empty() {
+            return Err(TypeError::Syntax(
+                "tuples must have at least one field".to_string(),
+            ));
+        }
+        Ok(Type::Tuple(fields))
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Bool => write!(f, "bool"),
+            Type::B256 => write!(f, "b256"),
+            Type::Array(ty, len) => {
+                // Print dimensions outermost first.
+                let mut dims = vec![*len];
+                let mut inner = &**ty;
+                while let Type::Array(ty, len) = inner {
+                    dims.push(*len);
+                    inner = ty;
+                }
+                write!(f, "{}", inner)?;
+                for len in dims {
+                    write!(f, "[{}]", len)?;
+                }
+                Ok(())
+            }
+            Type::Tuple(fields) => {
+                write!(f, "{{")?;
+                for (i, field) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    if let Some(name) = &field.name {
+                        write!(f, "{}: ", name)?;
+                    }
+                    write!(f, "{}", field.ty)?;
+                }
+                write!(f, "}}")
             }
-            let words = &words[..4];
-            let bytes: Vec<u8> = words.iter().flat_map(|w| bytes_from_word(*w)).collect();
-            hex::encode_upper(bytes)
         }
-        _ => {
-            dialoguer::console::Term::stdout()
-                .write_line(&format!(
-                    "{}, is not one of the valid types: {:?}",
-                    dialoguer::console::style(ty.trim()).red(),
-                    super::PRIMITIVES
-                ))
-                .ok();
-            String::new()
+    }
+}
+
+impl Display for TypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TypeError::UnknownType(ty) => write!(
+                f,
+                "{} is not one of the valid types: {:?}",
+                ty,
+                super::PRIMITIVES
+            ),
+            TypeError::Syntax(e) => write!(f, "Invalid type: {}", e),
+            TypeError::OutOfRange { pos, len } => {
+                write!(f, "Position {} is out of range for {} words", pos, len)
+            }
+            TypeError::InsufficientWords { ty, needed, found } => write!(
+                f,
+                "{} needs {} words but only {} are available",
+                ty, needed, found
+            ),
+            TypeError::InvalidBool(word) => write!(f, "{} is not a valid bool", word),
+            TypeError::TooLarge(ty) => write!(f, "{} is too large to decode", ty),
+            TypeError::ZeroSizedElement(ty) => {
+                write!(f, "Can't decode an array of {} as it has no words", ty)
+            }
+            TypeError::MissingTarget(target) => match target {
+                Target::Stack => write!(f, "The stack is empty"),
+                Target::DecisionVar(i) => write!(f, "No decision variable {}", i),
+                Target::NamedVar(name) => write!(f, "No decision variable named {}", name),
+                Target::PreState(i) => write!(f, "No pre state slot {}", i),
+                Target::PostState(i) => write!(f, "No post state slot {}", i),
+                Target::Memory(i) => write!(f, "Memory address {} is out of bounds", i),
+                Target::Transient(i, key) => {
+                    write!(f, "No transient data for {:?} in solution data {}", key, i)
+                }
+            },
         }
     }
 }
+
+impl std::error::Error for TypeError {}
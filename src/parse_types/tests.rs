@@ -0,0 +1,161 @@
+use super::*;
+
+fn b256(w: Word) -> [Word; 4] {
+    [0, 0, 0, w]
+}
+
+#[test]
+fn test_parse() {
+    let ty: Type = "{contract: b256, addr: b256}".parse().unwrap();
+    assert_eq!(
+        ty,
+        Type::Tuple(vec![
+            Field {
+                name: Some("contract".to_string()),
+                ty: Type::B256
+            },
+            Field {
+                name: Some("addr".to_string()),
+                ty: Type::B256
+            },
+        ])
+    );
+    assert_eq!(ty.size(), Ok(8));
+
+    let ty: Type = "int[2][3]".parse().unwrap();
+    assert_eq!(
+        ty,
+        Type::Array(Box::new(Type::Array(Box::new(Type::Int), 3)), 2)
+    );
+    assert_eq!(ty.to_string(), "int[2][3]");
+    assert_eq!(ty.size(), Ok(6));
+
+    let ty: Type = " { int , { bool, b256[2] } [2] , } ".parse().unwrap();
+    assert_eq!(ty.to_string(), "{int, {bool, b256[2]}[2]}");
+    assert_eq!(ty.size(), Ok(1 + 2 * (1 + 8)));
+
+    assert!("".parse::<Type>().is_err());
+    assert!("foo".parse::<Type>().is_err());
+    assert!("int[".parse::<Type>().is_err());
+    assert!("int[x]".parse::<Type>().is_err());
+    assert!("{}".parse::<Type>().is_err());
+    assert!("{int bool}".parse::<Type>().is_err());
+    assert!("int int".parse::<Type>().is_err());
+}
+
+#[test]
+fn test_parse_type() {
+    let words = [1, 0, 2, 3];
+    assert_eq!(decode_type(&words, "int").unwrap(), "1");
+    assert_eq!(decode_type(&words, "1 bool").unwrap(), "false");
+    assert_eq!(decode_type(&words, "int[2]").unwrap(), "[1, 0]");
+    assert_eq!(decode_type(&words, "{int, bool}").unwrap(), "{ 1, false }");
+    assert_eq!(
+        decode_type(&words, "int[2][2]").unwrap(),
+        "[[1, 0], [2, 3]]"
+    );
+    assert_eq!(
+        decode_type(&[1, 0, 2, 1], "{int, bool}[2]").unwrap(),
+        "[{ 1, false }, { 2, true }]"
+    );
+    assert_eq!(
+        decode_type(&words, "{int, bool}[2]"),
+        Err(TypeError::InvalidBool(3))
+    );
+    assert_eq!(
+        decode_type(&words, "3 int HEX").unwrap(),
+        "0000000000000003"
+    );
+    assert_eq!(
+        decode_type(&words, "int[5]"),
+        Err(TypeError::InsufficientWords {
+            ty: "int[5]".parse().unwrap(),
+            needed: 5,
+            found: 4
+        })
+    );
+    assert_eq!(
+        decode_type(&words, "4 int"),
+        Err(TypeError::OutOfRange { pos: 4, len: 4 })
+    );
+    assert_eq!(
+        decode_type(&words, "foo"),
+        Err(TypeError::UnknownType("foo".to_string()))
+    );
+
+    let mut words = b256(1).to_vec();
+    words.extend(b256(2));
+    assert_eq!(
+        decode_type(&words, "{contract: b256, addr: b256}").unwrap(),
+        format!("{{ contract: {:064X}, addr: {:064X} }}", 1, 2)
+    );
+}
+
+#[test]
+fn test_decode_sizes() {
+    let words = [1, 2, 3];
+    assert_eq!(decode_type(&words, "int[0]").unwrap(), "[]");
+    assert_eq!(decode_type(&words, "int[0][0]").unwrap(), "[]");
+    assert_eq!(
+        decode_type(&words, "int[3][0]"),
+        Err(TypeError::ZeroSizedElement("int[0]".parse().unwrap()))
+    );
+    assert_eq!(
+        decode_type(&words, "{int[0]}[2]"),
+        Err(TypeError::ZeroSizedElement("{int[0]}".parse().unwrap()))
+    );
+
+    let ty: Type = "int[9223372036854775807][4]".parse().unwrap();
+    assert_eq!(ty.size(), Err(TypeError::TooLarge(ty.clone())));
+    assert_eq!(
+        decode_type(&words, "int[9223372036854775807][4]"),
+        Err(TypeError::TooLarge(ty))
+    );
+    let ty: Type = "{int[9223372036854775807][4], int}".parse().unwrap();
+    assert!(matches!(ty.size(), Err(TypeError::TooLarge(_))));
+    let ty: Type = "{int[9223372036854775807], int[9223372036854775807][2]}"
+        .parse()
+        .unwrap();
+    assert_eq!(ty.size(), Err(TypeError::TooLarge(ty.clone())));
+}
+
+#[test]
+fn test_parse_target() {
+    assert_eq!(Target::parse("1 int").unwrap(), (Target::Stack, "1 int"));
+    assert_eq!(Target::parse("int[2]").unwrap(), (Target::Stack, "int[2]"));
+    assert_eq!(
+        Target::parse("{int, b256[2]}").unwrap(),
+        (Target::Stack, "{int, b256[2]}")
+    );
+    assert_eq!(
+        Target::parse("dv[0] int[3]").unwrap(),
+        (Target::DecisionVar(0), " int[3]")
+    );
+    assert_eq!(
+        Target::parse("post[2] {b256, int}").unwrap(),
+        (Target::PostState(2), " {b256, int}")
+    );
+    assert_eq!(
+        Target::parse("mem[4] 1 b256").unwrap(),
+        (Target::Memory(4), " 1 b256")
+    );
+    assert_eq!(
+        Target::parse("transient[1][2, 3] int").unwrap(),
+        (Target::Transient(1, vec![2, 3]), " int")
+    );
+    assert_eq!(
+        Target::parse("dv[::amount] int").unwrap(),
+        (Target::NamedVar("::amount".to_string()), " int")
+    );
+    assert_eq!(
+        Target::parse("::amount HEX").unwrap(),
+        (Target::NamedVar("::amount".to_string()), "HEX")
+    );
+    assert_eq!(
+        Target::parse("::amount").unwrap(),
+        (Target::NamedVar("::amount".to_string()), "")
+    );
+    assert!(Target::parse("dv[-1] int").is_err());
+    assert!(Target::parse("pre[x] int").is_err());
+    assert!(Target::parse("transient[0] int").is_err());
+}
@@ -0,0 +1,86 @@
+use essential_constraint_asm::{self as asm, Op};
+use essential_constraint_vm::{
+    error::{OpError, RepeatError, StackError},
+    OpResult, ProgramControlFlow, Stack,
+};
+use essential_types::{convert::bool_from_word, Word};
+
+#[cfg(test)]
+mod tests;
+
+/// A loop being repeated.
+///
+/// The debugger keeps its own repeat stack instead of the VM's `Repeat`
+/// so it can be copied into checkpoints and restored as is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RepeatSlot {
+    pub counter: Word,
+    /// The upper limit if counting up.
+    pub limit: Option<Word>,
+    pub repeat_index: usize,
+}
+
+/// Play an op that uses the repeat stack the way the VM does.
+///
+/// Returns `None` if the op doesn't use the repeat stack.
+pub(crate) fn step_op(
+    op: Op,
+    pc: usize,
+    stack: &mut Stack,
+    repeat: &mut Vec<RepeatSlot>,
+) -> Option<OpResult<Option<ProgramControlFlow>>> {
+    let result = match op {
+        Op::Stack(asm::Stack::Repeat) => start(pc, stack, repeat).map(|_| None),
+        Op::Stack(asm::Stack::RepeatEnd) => end(repeat)
+            .map(|pc| pc.map(ProgramControlFlow::Pc))
+            .map_err(OpError::from),
+        Op::Access(asm::Access::RepeatCounter) => counter(stack, repeat).map(|_| None),
+        _ => return None,
+    };
+    Some(result)
+}
+
+fn start(pc: usize, stack: &mut Stack, repeat: &mut Vec<RepeatSlot>) -> OpResult<()> {
+    let [num_repeats, count_up] = stack.pop2()?;
+    let count_up = bool_from_word(count_up).ok_or(RepeatError::InvalidCountDirection)?;
+    let repeat_index = pc.checked_add(1).ok_or(StackError::IndexOutOfBounds)?;
+    if repeat.len() >= Stack::SIZE_LIMIT {
+        return Err(RepeatError::Overflow.into());
+    }
+    repeat.push(match count_up {
+        true => RepeatSlot {
+            counter: 0,
+            limit: Some(num_repeats),
+            repeat_index,
+        },
+        false => RepeatSlot {
+            counter: num_repeats,
+            limit: None,
+            repeat_index,
+        },
+    });
+    Ok(())
+}
+
+/// The index to jump back to or `None` if the loop is done.
+fn end(repeat: &mut Vec<RepeatSlot>) -> Result<Option<usize>, RepeatError> {
+    let slot = repeat.last_mut().ok_or(RepeatError::Empty)?;
+    let done = match slot.limit {
+        Some(limit) => slot.counter >= limit.saturating_sub(1),
+        None => slot.counter <= 1,
+    };
+    if done {
+        repeat.pop();
+        return Ok(None);
+    }
+    match slot.limit {
+        Some(_) => slot.counter += 1,
+        None => slot.counter -= 1,
+    }
+    Ok(Some(slot.repeat_index))
+}
+
+fn counter(stack: &mut Stack, repeat: &[RepeatSlot]) -> OpResult<()> {
+    let counter = repeat.last().ok_or(RepeatError::NoCounter)?.counter;
+    Ok(stack.push(counter)?)
+}
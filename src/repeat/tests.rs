@@ -0,0 +1,103 @@
+use super::*;
+use essential_constraint_vm::Repeat;
+
+/// Play the ops with the VM's repeat stack and with the debugger's,
+/// checking they stay the same.
+fn check_against_vm(ops: &[Op]) {
+    let mut vm_stack = Stack::default();
+    let mut vm_repeat = Repeat::new();
+    let mut stack = Stack::default();
+    let mut repeat = vec![];
+    for (pc, op) in ops.iter().enumerate() {
+        let expected = match op {
+            Op::Stack(op) => {
+                essential_constraint_vm::step_op_stack(*op, pc, &mut vm_stack, &mut vm_repeat)
+            }
+            // `RepeatCounter` as the VM plays it.
+            _ => vm_repeat
+                .counter()
+                .map_err(OpError::from)
+                .and_then(|c| Ok(vm_stack.push(c)?))
+                .map(|_| None),
+        };
+        let found = match (step_op(*op, pc, &mut stack, &mut repeat), op) {
+            (Some(found), _) => found,
+            (None, Op::Stack(op)) => {
+                essential_constraint_vm::step_op_stack(*op, pc, &mut stack, &mut Repeat::new())
+            }
+            (None, op) => panic!("Unexpected op {:?}", op),
+        };
+        assert_eq!(
+            format!("{:?}", found),
+            format!("{:?}", expected),
+            "op {} {:?}",
+            pc,
+            op
+        );
+        assert_eq!(stack, vm_stack);
+        assert_eq!(repeat.last().map(|s| s.counter), vm_repeat.counter().ok());
+    }
+}
+
+#[test]
+fn test_step_op() {
+    let push = |w| Op::Stack(asm::Stack::Push(w));
+    let start = Op::Stack(asm::Stack::Repeat);
+    let end = Op::Stack(asm::Stack::RepeatEnd);
+    let counter = Op::Access(asm::Access::RepeatCounter);
+    check_against_vm(&[
+        push(3),
+        push(1),
+        start,
+        counter,
+        end,
+        counter,
+        end,
+        end,
+        push(2),
+        push(0),
+        start,
+        counter,
+        end,
+        end,
+        end,
+        counter,
+        push(1),
+        push(2),
+        start,
+    ]);
+    assert!(step_op(push(1), 0, &mut Stack::default(), &mut vec![]).is_none());
+}
+
+#[test]
+fn test_counter_restored_directly() {
+    let mut stack = Stack::default();
+    let mut repeat = vec![RepeatSlot {
+        counter: 1_000_000,
+        limit: Some(1_000_002),
+        repeat_index: 4,
+    }];
+    assert_eq!(
+        step_op(Op::Stack(asm::Stack::RepeatEnd), 9, &mut stack, &mut repeat)
+            .unwrap()
+            .unwrap(),
+        Some(ProgramControlFlow::Pc(4))
+    );
+    assert_eq!(repeat[0].counter, 1_000_001);
+    step_op(
+        Op::Access(asm::Access::RepeatCounter),
+        5,
+        &mut stack,
+        &mut repeat,
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(&stack[..], &[1_000_001]);
+    assert_eq!(
+        step_op(Op::Stack(asm::Stack::RepeatEnd), 9, &mut stack, &mut repeat)
+            .unwrap()
+            .unwrap(),
+        None
+    );
+    assert!(repeat.is_empty());
+}
@@ -0,0 +1,408 @@
+use std::io::{BufRead, Write};
+
+use essential_constraint_asm::{Op, ToBytes, ToOpcode};
+use essential_constraint_vm::OpAccess;
+use essential_types::{Key, Word};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    breakpoint::{BreakpointKind, OpFilter},
+    check::CheckOutcome,
+    condition::Condition,
+    memory_words,
+    opcode::OpName,
+    trace::TraceOutcome,
+    Outcome, Session,
+};
+
+#[cfg(test)]
+mod tests;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// The request was valid but the debugger couldn't carry it out.
+const DEBUGGER_ERROR: i64 = -32000;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    jsonrpc: String,
+    /// Notifications have no id and get no response.
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl From<anyhow::Error> for RpcError {
+    fn from(e: anyhow::Error) -> Self {
+        RpcError {
+            code: DEBUGGER_ERROR,
+            message: e.to_string(),
+        }
+    }
+}
+
+/// The state of the VM after a request.
+#[derive(Debug, Serialize)]
+struct State {
+    pos: usize,
+    pc: usize,
+    /// The op that was played last.
+    last_op: Option<OpJson>,
+    /// The op that will be played next.
+    next_op: Option<OpJson>,
+    stack: Vec<Word>,
+    memory: Vec<Word>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    outcome: Option<TraceOutcome>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    breakpoint: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    check: Option<String>,
+}
+
+/// An op as its opcode and the word it takes from the bytecode, if any.
+#[derive(Debug, Serialize)]
+struct OpJson {
+    /// Named as in op breakpoints, e.g. `Stack::Push`.
+    opcode: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    operand: Option<Word>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct PlayParams {
+    pos: usize,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ListParams {
+    start: usize,
+    end: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ShowParams {
+    what: String,
+    index: Option<usize>,
+    #[serde(default)]
+    key: Key,
+}
+
+#[derive(Debug, Deserialize)]
+struct TypeParams {
+    input: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct BreakParams {
+    pos: Option<usize>,
+    pc: Option<usize>,
+    /// Op filters separated by commas, e.g. `Access::State, Pred::*`.
+    op: Option<String>,
+    condition: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IdParams {
+    id: usize,
+}
+
+/// Serve newline delimited JSON-RPC 2.0 requests until `quit` or the end of the input.
+///
+/// Each request and response is a single line of JSON.
+pub(crate) fn serve(
+    session: &mut Session,
+    input: impl BufRead,
+    mut output: impl Write,
+) -> anyhow::Result<()> {
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (response, quit) = handle_line(session, &line);
+        if let Some(response) = response {
+            serde_json::to_writer(&mut output, &response)?;
+            writeln!(output)?;
+            output.flush()?;
+        }
+        if quit {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Handle a line of input, returning the response and if the client quit.
+fn handle_line(session: &mut Session, line: &str) -> (Option<Value>, bool) {
+    let request = match serde_json::from_str::<Value>(line) {
+        Ok(request) => request,
+        Err(e) => {
+            let e = RpcError {
+                code: PARSE_ERROR,
+                message: e.to_string(),
+            };
+            return (Some(response(Value::Null, Err(e))), false);
+        }
+    };
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let request = match serde_json::from_value::<Request>(request) {
+        Ok(request) if request.jsonrpc == "2.0" => request,
+        Ok(_) => {
+            let e = RpcError {
+                code: INVALID_REQUEST,
+                message: "Expected jsonrpc to be \"2.0\"".to_string(),
+            };
+            return (Some(response(id, Err(e))), false);
+        }
+        Err(e) => {
+            let e = RpcError {
+                code: INVALID_REQUEST,
+                message: e.to_string(),
+            };
+            return (Some(response(id, Err(e))), false);
+        }
+    };
+    let quit = request.method == "quit";
+    let result = handle(session, &request.method, request.params);
+    (request.id.map(|id| response(id, result)), quit)
+}
+
+fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": e.code, "message": e.message },
+        }),
+    }
+}
+
+fn params<T: serde::de::DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(|e| RpcError {
+        code: INVALID_PARAMS,
+        message: e.to_string(),
+    })
+}
+
+fn handle(session: &mut Session, method: &str, p: Value) -> Result<Value, RpcError> {
+    let state = match method {
+        "state" => state(session, None),
+        "step" | "next" => {
+            let outcome = session.step_forward()?;
+            state(session, Some(&outcome))
+        }
+        "back" => {
+            let outcome = session.seek(session.pos.saturating_sub(1))?;
+            state(session, Some(&outcome))
+        }
+        "play" => {
+            let PlayParams { pos } = params(p)?;
+            let outcome = session.seek(pos)?;
+            state(session, Some(&outcome))
+        }
+        "continue" => {
+            let (outcome, breakpoint) = session.run_to_breakpoint()?;
+            State {
+                breakpoint,
+                ..state(session, Some(&outcome))
+            }
+        }
+        "end" => {
+            let check = session.play_till_end()?;
+            let outcome = match &check {
+                CheckOutcome::Panic(e) => TraceOutcome::Panic(e.clone()),
+                CheckOutcome::OutOfGas(e) => TraceOutcome::OutOfGas(e.clone()),
+                _ => TraceOutcome::ProgramEnd,
+            };
+            State {
+                outcome: Some(outcome),
+                check: Some(check.to_string()),
+                ..state(session, None)
+            }
+        }
+        "list" => return list(session, params(p)?),
+        "show" => return show(session, params(p)?),
+        "type" => {
+            let TypeParams { input } = params(p)?;
+            let value = session.parse_type(&input).map_err(|e| RpcError {
+                code: DEBUGGER_ERROR,
+                message: e.to_string(),
+            })?;
+            return Ok(json!({ "value": value }));
+        }
+        "break" => return add_breakpoint(session, params(p)?),
+        "delete" => {
+            let IdParams { id } = params(p)?;
+            return Ok(json!({ "removed": session.breakpoints.remove(id) }));
+        }
+        "breakpoints" => {
+            let breakpoints = session
+                .breakpoints
+                .iter()
+                .map(|(id, bp)| {
+                    json!({
+                        "id": id,
+                        "kind": bp.kind.to_string(),
+                        "condition": bp.condition.as_ref().map(|c| c.to_string()),
+                        "enabled": bp.enabled,
+                    })
+                })
+                .collect::<Vec<_>>();
+            return Ok(json!(breakpoints));
+        }
+        "quit" => return Ok(Value::Null),
+        _ => {
+            return Err(RpcError {
+                code: METHOD_NOT_FOUND,
+                message: format!("Unknown method `{}`", method),
+            })
+        }
+    };
+    Ok(serde_json::to_value(state).map_err(anyhow::Error::from)?)
+}
+
+fn state(session: &mut Session, outcome: Option<&Outcome>) -> State {
+    State {
+        pos: *session.pos,
+        pc: *session.pc,
+        last_op: session.last_op.as_ref().map(OpJson::from),
+        next_op: match (&*session.code).op_access(*session.pc) {
+            Some(Ok(op)) => Some(OpJson::from(&op)),
+            _ => None,
+        },
+        stack: session.stack.to_vec(),
+        memory: memory_words(session.memory),
+        outcome: outcome.map(TraceOutcome::from),
+        breakpoint: None,
+        check: None,
+    }
+}
+
+/// Ops from `start` up to `end` or the end of the program.
+fn list(session: &Session, ListParams { start, end }: ListParams) -> Result<Value, RpcError> {
+    let ops = session
+        .code
+        .ops_from(start)
+        .map(|ops| {
+            ops.ops()
+                .take(end.map_or(usize::MAX, |end| end.saturating_sub(start)))
+                .enumerate()
+                .map(|(i, op)| json!({ "pc": start + i, "op": OpJson::from(&op) }))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    Ok(json!(ops))
+}
+
+fn show(session: &Session, ShowParams { what, index, key }: ShowParams) -> Result<Value, RpcError> {
+    let missing = |what: &str| RpcError {
+        code: DEBUGGER_ERROR,
+        message: format!("No {} {:?}", what, index),
+    };
+    let data = session.data;
+    let decision_var = |i: usize| -> Option<Value> {
+        let value = data.decision_variables.get(i)?;
+        let var = session.abi.and_then(|abi| abi.predicate.vars.get(i));
+        Some(json!({
+            "index": i,
+            "name": var.map(|v| &v.name),
+            "type": var.and_then(|v| v.ty.to_type()).map(|t| t.to_string()),
+            "decoded": var.map(|v| v.value(value)),
+            "value": value,
+        }))
+    };
+    match (what.as_str(), index) {
+        ("pre", Some(i)) => session
+            .pre
+            .get(i)
+            .map(|v| json!({ "value": v }))
+            .ok_or_else(|| missing("pre state slot")),
+        ("post", Some(i)) => session
+            .post
+            .get(i)
+            .map(|v| json!({ "value": v }))
+            .ok_or_else(|| missing("post state slot")),
+        ("pre", None) => Ok(json!(session.pre)),
+        ("post", None) => Ok(json!(session.post)),
+        ("dv" | "decision", Some(i)) => decision_var(i).ok_or_else(|| missing("decision variable")),
+        ("dv" | "decision", None) => Ok(json!((0..data.decision_variables.len())
+            .filter_map(decision_var)
+            .collect::<Vec<_>>())),
+        ("transient", Some(i)) => session
+            .transient_data
+            .get(&u16::try_from(i).map_err(|_| RpcError {
+                code: INVALID_PARAMS,
+                message: format!("Solution data index {} is out of range", i),
+            })?)
+            .and_then(|t| t.get(&key))
+            .map(|v| json!({ "key": key, "value": v }))
+            .ok_or_else(|| RpcError {
+                code: DEBUGGER_ERROR,
+                message: format!("No transient data for {:?} in solution data {}", key, i),
+            }),
+        _ => Err(RpcError {
+            code: INVALID_PARAMS,
+            message: "Expected what to be pre, post, dv or transient with an index".to_string(),
+        }),
+    }
+}
+
+impl From<&Op> for OpJson {
+    fn from(op: &Op) -> Self {
+        let opcode = op.to_opcode().name();
+        // The opcode is followed by the operand's big endian bytes.
+        let operand = op
+            .to_bytes()
+            .collect::<Vec<_>>()
+            .get(1..)
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(Word::from_be_bytes);
+        OpJson { opcode, operand }
+    }
+}
+
+fn add_breakpoint(session: &mut Session, p: BreakParams) -> Result<Value, RpcError> {
+    let invalid = |message: String| RpcError {
+        code: INVALID_PARAMS,
+        message,
+    };
+    let kind = match (p.pos, p.pc, &p.op) {
+        (Some(pos), None, None) => BreakpointKind::Pos(pos),
+        (None, Some(pc), None) => BreakpointKind::Pc(pc),
+        (None, None, Some(op)) => BreakpointKind::Op(
+            op.split(',')
+                .map(|f| f.trim().parse::<OpFilter>())
+                .collect::<anyhow::Result<_>>()
+                .map_err(|e| invalid(e.to_string()))?,
+        ),
+        (None, None, None) if p.condition.is_some() => BreakpointKind::Any,
+        _ => {
+            return Err(invalid(
+                "Expected one of pos, pc or op, or only a condition".to_string(),
+            ))
+        }
+    };
+    let condition = p
+        .condition
+        .map(|c| c.parse::<Condition>())
+        .transpose()
+        .map_err(|e| invalid(e.to_string()))?;
+    let id = session.breakpoints.add_with_condition(kind, condition);
+    Ok(json!({ "id": id }))
+}
@@ -0,0 +1,206 @@
+use super::*;
+use crate::ConstraintDebugger;
+use essential_constraint_asm as asm;
+use essential_types::{
+    predicate::{Directive, Predicate},
+    solution::{Solution, SolutionData},
+    ContentAddress, PredicateAddress,
+};
+
+async fn debugger() -> ConstraintDebugger {
+    let predicate = Predicate {
+        state_read: vec![],
+        constraints: vec![asm::to_bytes([
+            asm::Stack::Push(1).into(),
+            asm::Stack::Push(2).into(),
+            asm::Alu::Add.into(),
+            asm::Stack::Push(3).into(),
+            asm::Pred::Eq.into(),
+        ])
+        .collect()],
+        directive: Directive::Satisfy,
+    };
+    let solution = Solution {
+        data: vec![SolutionData {
+            predicate_to_solve: PredicateAddress {
+                contract: ContentAddress([0; 32]),
+                predicate: ContentAddress([0; 32]),
+            },
+            decision_variables: vec![vec![4, 5]],
+            state_mutations: vec![],
+            transient_data: vec![],
+        }],
+    };
+    ConstraintDebugger::new(solution, 0, predicate, 0, Default::default())
+        .await
+        .unwrap()
+}
+
+fn call(session: &mut Session, method: &str, params: Value) -> Value {
+    let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+    let (response, _) = handle_line(session, &request.to_string());
+    let response = response.unwrap();
+    assert_eq!(response["id"], 1);
+    response
+}
+
+#[tokio::test]
+async fn test_stepping() {
+    let mut debugger = debugger().await;
+    let mut session = debugger.start_session();
+
+    let r = call(&mut session, "state", Value::Null)["result"].clone();
+    assert_eq!(
+        r,
+        json!({
+            "pos": 0,
+            "pc": 0,
+            "last_op": null,
+            "next_op": { "opcode": "Stack::Push", "operand": 1 },
+            "stack": [],
+            "memory": [],
+        })
+    );
+
+    let r = call(&mut session, "step", Value::Null)["result"].clone();
+    assert_eq!(r["pos"], 1);
+    assert_eq!(
+        r["last_op"],
+        json!({ "opcode": "Stack::Push", "operand": 1 })
+    );
+    assert_eq!(r["stack"], json!([1]));
+    assert_eq!(r["outcome"], "step");
+
+    let r = call(&mut session, "play", json!({ "pos": 3 }))["result"].clone();
+    assert_eq!(r["stack"], json!([3]));
+    assert_eq!(
+        r["next_op"],
+        json!({ "opcode": "Stack::Push", "operand": 3 })
+    );
+
+    let r = call(&mut session, "back", Value::Null)["result"].clone();
+    assert_eq!(r["pos"], 2);
+    assert_eq!(r["stack"], json!([1, 2]));
+
+    let r = call(&mut session, "end", Value::Null)["result"].clone();
+    assert_eq!(r["outcome"], "program_end");
+    assert_eq!(r["check"], "pass");
+    assert_eq!(r["stack"], json!([1]));
+}
+
+#[tokio::test]
+async fn test_breakpoints() {
+    let mut debugger = debugger().await;
+    let mut session = debugger.start_session();
+
+    let r = call(&mut session, "break", json!({ "op": "Alu::Add" }));
+    assert_eq!(r["result"], json!({ "id": 0 }));
+    let r = call(&mut session, "break", json!({ "condition": "top == 3" }));
+    assert_eq!(r["result"], json!({ "id": 1 }));
+    let r = call(&mut session, "break", json!({ "pc": 1, "op": "Alu" }));
+    assert_eq!(r["error"]["code"], INVALID_PARAMS);
+    let r = call(&mut session, "break", json!({ "op": "Foo" }));
+    assert_eq!(r["error"]["code"], INVALID_PARAMS);
+
+    let r = call(&mut session, "breakpoints", Value::Null)["result"].clone();
+    assert_eq!(r[0]["kind"], "op Alu::Add");
+    assert_eq!(r[1]["condition"], "top == 3");
+
+    let r = call(&mut session, "continue", Value::Null)["result"].clone();
+    assert_eq!(r["breakpoint"], 0);
+    assert_eq!(r["pc"], 2);
+    let r = call(&mut session, "continue", Value::Null)["result"].clone();
+    assert_eq!(r["breakpoint"], 1);
+    assert_eq!(r["stack"], json!([3]));
+
+    let r = call(&mut session, "delete", json!({ "id": 1 }));
+    assert_eq!(r["result"], json!({ "removed": true }));
+    let r = call(&mut session, "continue", Value::Null)["result"].clone();
+    assert_eq!(r.get("breakpoint"), None);
+    assert_eq!(r["outcome"], "program_end");
+}
+
+#[tokio::test]
+async fn test_inspect() {
+    let mut debugger = debugger().await;
+    let mut session = debugger.start_session();
+
+    let r = call(&mut session, "list", json!({ "start": 3 }))["result"].clone();
+    assert_eq!(
+        r,
+        json!([
+            { "pc": 3, "op": { "opcode": "Stack::Push", "operand": 3 } },
+            { "pc": 4, "op": { "opcode": "Pred::Eq" } },
+        ])
+    );
+    let r = call(&mut session, "list", json!({ "end": 1 }))["result"].clone();
+    assert_eq!(
+        r,
+        json!([{ "pc": 0, "op": { "opcode": "Stack::Push", "operand": 1 } }])
+    );
+    let op = OpJson::from(&asm::Stack::Push(-2).into());
+    assert_eq!(op.operand, Some(-2));
+
+    let r = call(&mut session, "show", json!({ "what": "dv", "index": 0 }))["result"].clone();
+    assert_eq!(r["value"], json!([4, 5]));
+    assert_eq!(r["name"], Value::Null);
+    let r = call(&mut session, "show", json!({ "what": "dv" }))["result"].clone();
+    assert_eq!(r.as_array().unwrap().len(), 1);
+    let r = call(&mut session, "show", json!({ "what": "pre", "index": 0 }));
+    assert_eq!(r["error"]["code"], DEBUGGER_ERROR);
+    let r = call(
+        &mut session,
+        "show",
+        json!({ "what": "transient", "index": 65536, "key": [0] }),
+    );
+    assert_eq!(r["error"]["code"], INVALID_PARAMS);
+
+    let r = call(&mut session, "type", json!({ "input": "dv[0] 1 int" }));
+    assert_eq!(r["result"], json!({ "value": "5" }));
+    let r = call(&mut session, "type", json!({ "input": "dv[1] int" }));
+    assert_eq!(r["error"]["message"], "No decision variable 1");
+    let r = call(&mut session, "type", json!({}));
+    assert_eq!(r["error"]["code"], INVALID_PARAMS);
+}
+
+#[tokio::test]
+async fn test_protocol() {
+    let mut debugger = debugger().await;
+    let mut session = debugger.start_session();
+
+    let (r, quit) = handle_line(&mut session, "{");
+    assert_eq!(r.unwrap()["error"]["code"], PARSE_ERROR);
+    assert!(!quit);
+    let (r, _) = handle_line(
+        &mut session,
+        r#"{"jsonrpc": "1.0", "id": 2, "method": "state"}"#,
+    );
+    let r = r.unwrap();
+    assert_eq!(r["id"], 2);
+    assert_eq!(r["error"]["code"], INVALID_REQUEST);
+    let (r, _) = handle_line(
+        &mut session,
+        r#"{"jsonrpc": "2.0", "id": 3, "method": "foo"}"#,
+    );
+    assert_eq!(r.unwrap()["error"]["code"], METHOD_NOT_FOUND);
+
+    // Notifications are handled without a response.
+    let (r, _) = handle_line(&mut session, r#"{"jsonrpc": "2.0", "method": "step"}"#);
+    assert_eq!(r, None);
+    assert_eq!(*session.pos, 1);
+
+    let input = r#"{"jsonrpc": "2.0", "id": "a", "method": "state"}
+
+{"jsonrpc": "2.0", "id": "b", "method": "quit"}
+{"jsonrpc": "2.0", "id": "c", "method": "state"}
+"#;
+    let mut output = Vec::new();
+    serve(&mut session, input.as_bytes(), &mut output).unwrap();
+    let lines = String::from_utf8(output).unwrap();
+    let lines = lines.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(
+        serde_json::from_str::<Value>(lines[1]).unwrap(),
+        json!({ "jsonrpc": "2.0", "id": "b", "result": null })
+    );
+}
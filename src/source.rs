@@ -1,4 +1,11 @@
-use std::fmt::Write;
+use std::{
+    fmt::Write,
+    ops::{Range, RangeInclusive},
+    path::{Path, PathBuf},
+};
+
+use dialoguer::console::style;
+use serde::Deserialize;
 
 #[cfg(test)]
 mod tests;
@@ -8,6 +15,43 @@ pub struct Source {
     pub other: String,
     pub predicate: String,
     pub constraint_line: Option<usize>,
+    /// The span of predicate source that each op of the constraint belongs to.
+    pub spans: Vec<Option<Span>>,
+    /// The file the predicate source was read from.
+    pub path: Option<PathBuf>,
+}
+
+/// A byte range of the predicate source.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Maps each op of each constraint to the span of the expression it was compiled from.
+///
+/// ```json
+/// { "constraints": [[{ "start": 120, "end": 136 }, null]] }
+/// ```
+#[derive(Default, Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SourceMap {
+    pub constraints: Vec<Vec<Option<Span>>>,
+}
+
+/// Parse a source map produced by the compiler from JSON.
+pub fn parse_source_map(bytes: &[u8]) -> anyhow::Result<SourceMap> {
+    serde_json::from_slice(bytes)
+        .map_err(|e| anyhow::anyhow!("Failed to parse source map file: {}", e))
+}
+
+/// A line of the predicate source marked for display.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CodeLine<'a> {
+    pub text: &'a str,
+    /// The line is part of the constraint being debugged.
+    pub is_constraint: bool,
+    /// The bytes of the line covered by the expression of the current op.
+    pub highlight: Option<Range<usize>>,
 }
 
 #[derive(Default, Debug, Clone, Copy)]
@@ -19,20 +63,28 @@ pub enum ShowOutput {
     ConstraintOnly,
 }
 
-pub fn show_code(source: &Option<Source>, show: ShowOutput) -> String {
+/// Show the source code, highlighting the expression of the op at `pc` if it is mapped.
+pub fn show_code(source: &Option<Source>, show: ShowOutput, pc: usize) -> String {
     match source {
-        Some(source) => match show {
-            ShowOutput::All => format!(
-                "{}\n{}",
-                source.other,
-                format_predicate(&source.predicate, &source.constraint_line)
-            ),
-            ShowOutput::Predicate => format_predicate(&source.predicate, &source.constraint_line),
-            ShowOutput::Constraint => format_constraint(&source.predicate, &source.constraint_line),
-            ShowOutput::ConstraintOnly => {
-                constraint_only(&source.predicate, &source.constraint_line)
+        Some(source) => {
+            let span = source.span(pc);
+            match show {
+                ShowOutput::All => format!(
+                    "{}\n{}",
+                    source.other,
+                    format_predicate(&source.predicate, &source.constraint_line, span)
+                ),
+                ShowOutput::Predicate => {
+                    format_predicate(&source.predicate, &source.constraint_line, span)
+                }
+                ShowOutput::Constraint => {
+                    format_constraint(&source.predicate, &source.constraint_line, span)
+                }
+                ShowOutput::ConstraintOnly => {
+                    constraint_only(&source.predicate, &source.constraint_line, span)
+                }
             }
-        },
+        }
         None => "No source code available.".to_string(),
     }
 }
@@ -51,16 +103,9 @@ impl Source {
         constraint_num: usize,
     ) -> Self {
         let predicate = predicate.into();
-        let mut count = 0;
-        let constraint_line = predicate.lines().position(|line| {
-            if line.trim().starts_with("constraint ") {
-                let found = count == constraint_num;
-                count += 1;
-                found
-            } else {
-                false
-            }
-        });
+        let constraint_line = constraint_lines(&predicate)
+            .get(constraint_num)
+            .map(|lines| *lines.start());
         Source {
             predicate,
             constraint_line,
@@ -81,60 +126,260 @@ impl Source {
             ..self
         }
     }
+
+    /// Use the spans of the given constraint from the source map.
+    pub fn with_source_map(self, source_map: &SourceMap, constraint: usize) -> Self {
+        Source {
+            spans: source_map
+                .constraints
+                .get(constraint)
+                .cloned()
+                .unwrap_or_default(),
+            ..self
+        }
+    }
+
+    pub fn with_path(self, path: impl Into<PathBuf>) -> Self {
+        Source {
+            path: Some(path.into()),
+            ..self
+        }
+    }
+
+    /// Check if the predicate source was read from this path.
+    pub fn is_path(&self, path: &Path) -> bool {
+        let Some(own) = &self.path else {
+            return false;
+        };
+        own == path
+            || matches!(
+                (own.canonicalize(), path.canonicalize()),
+                (Ok(a), Ok(b)) if a == b
+            )
+    }
+
+    /// The line and column (starting at 0) of the op at `pc` in the predicate.
+    ///
+    /// Without a source map this is the start of the constraint.
+    pub fn location(&self, pc: usize) -> Option<(usize, usize)> {
+        if self.spans.is_empty() {
+            return self.constraint_line.map(|line| (line, 0));
+        }
+        let span = self.span(pc)?;
+        let before = &self.predicate[..span.start];
+        let line = before.matches('\n').count();
+        let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1);
+        Some((line, column))
+    }
+
+    /// The lowest pc of the ops whose span starts on the line (starting at 0).
+    pub fn first_op_on_line(&self, line: usize) -> Option<usize> {
+        (0..self.spans.len()).find(|pc| {
+            self.span(*pc)
+                .is_some_and(|span| self.predicate[..span.start].matches('\n').count() == line)
+        })
+    }
+
+    /// The span of the op at `pc` if it is mapped and within the predicate.
+    pub fn span(&self, pc: usize) -> Option<Span> {
+        self.spans
+            .get(pc)
+            .copied()
+            .flatten()
+            .filter(|s| s.start < s.end && self.predicate.get(s.start..s.end).is_some())
+    }
+
+    /// The source of the expression the op at `pc` belongs to.
+    pub fn expression(&self, pc: usize) -> Option<&str> {
+        self.span(pc).map(|s| &self.predicate[s.start..s.end])
+    }
+
+    /// Lines of the predicate with the constraint and the expression of the op at `pc` marked.
+    pub(crate) fn code_lines(&self, pc: usize) -> Vec<CodeLine<'_>> {
+        let span = self.span(pc);
+        let lines = self
+            .constraint_line
+            .map(|line| constraint_range(&self.predicate, line));
+        lines_with_offsets(&self.predicate)
+            .enumerate()
+            .map(|(i, (offset, text))| CodeLine {
+                text,
+                is_constraint: lines.as_ref().is_some_and(|lines| lines.contains(&i)),
+                highlight: span.and_then(|span| highlight(text, offset, span)),
+            })
+            .collect()
+    }
 }
 
-fn format_predicate(predicate: &str, constraint_line: &Option<usize>) -> String {
-    match constraint_line {
-        Some(line_num) => predicate.lines().enumerate().fold(
-            String::with_capacity(predicate.len()),
-            |mut s, (i, line)| {
-                if i == *line_num {
-                    let _ = writeln!(s, "{}", dialoguer::console::style(line).cyan());
-                } else {
-                    let _ = writeln!(s, "{}", line);
+/// Lines of the source with the byte offset they start at.
+fn lines_with_offsets(source: &str) -> impl Iterator<Item = (usize, &str)> {
+    source.split_inclusive('\n').scan(0, |offset, line| {
+        let start = *offset;
+        *offset += line.len();
+        Some((start, line.trim_end_matches(['\n', '\r'])))
+    })
+}
+
+/// Style a line that starts at `offset`, highlighting the part covered by the span.
+fn style_line(line: &str, offset: usize, span: Option<Span>, is_constraint: bool) -> String {
+    let base = |s: &str| {
+        if is_constraint && !s.is_empty() {
+            style(s).cyan().to_string()
+        } else {
+            s.to_string()
+        }
+    };
+    let Some(Range { start, end }) = span.and_then(|span| highlight(line, offset, span)) else {
+        return base(line);
+    };
+    format!(
+        "{}{}{}",
+        base(&line[..start]),
+        style(&line[start..end]).yellow().bold().underlined(),
+        base(&line[end..])
+    )
+}
+
+/// The bytes of a line that starts at `offset` covered by the span.
+fn highlight(line: &str, offset: usize, span: Span) -> Option<Range<usize>> {
+    let start = span.start.clamp(offset, offset + line.len()) - offset;
+    let end = span.end.clamp(offset, offset + line.len()) - offset;
+    (start < end && line.is_char_boundary(start) && line.is_char_boundary(end))
+        .then_some(start..end)
+}
+
+/// Lines of each constraint in the predicate, from the line with the
+/// `constraint` keyword to the line with the `;` that ends it.
+///
+/// Semicolons nested in parentheses, brackets or braces and
+/// anything after `//` are skipped.
+fn constraint_lines(predicate: &str) -> Vec<RangeInclusive<usize>> {
+    let mut constraints = Vec::new();
+    // The start line and nesting depth of the constraint being scanned.
+    let mut current: Option<(usize, usize)> = None;
+    let mut last = 0;
+    for (i, line) in predicate.lines().enumerate() {
+        last = i;
+        let code = line.split("//").next().unwrap_or_default();
+        let code = match current {
+            Some(_) => code,
+            None => match code.trim_start().strip_prefix("constraint") {
+                Some(rest) if rest.starts_with(|c: char| c.is_whitespace() || c == '(') => {
+                    current = Some((i, 0));
+                    rest
                 }
-                s
+                _ => continue,
             },
-        ),
-        None => predicate.to_string(),
+        };
+        let Some((start, depth)) = current.as_mut() else {
+            continue;
+        };
+        for c in code.chars() {
+            match c {
+                '(' | '[' | '{' => *depth += 1,
+                ')' | ']' | '}' => *depth = depth.saturating_sub(1),
+                ';' if *depth == 0 => {
+                    constraints.push(*start..=i);
+                    current = None;
+                    break;
+                }
+                _ => (),
+            }
+        }
+    }
+    // A constraint without a `;` runs to the end.
+    if let Some((start, _)) = current {
+        constraints.push(start..=last);
     }
+    constraints
 }
 
-fn format_constraint(predicate: &str, constraint_line: &Option<usize>) -> String {
-    match constraint_line {
-        Some(line_num) => predicate.lines().enumerate().fold(
-            String::with_capacity(predicate.len()),
-            |mut s, (i, line)| {
-                if i == *line_num {
-                    let _ = writeln!(s, "{}", line);
-                } else if line.trim().starts_with("constraint ") {
-                } else {
-                    let _ = writeln!(s, "{}", line);
-                }
-                s
-            },
-        ),
-        None => predicate.to_string(),
+/// The lines of the constraint that starts at or contains `line`.
+fn constraint_range(predicate: &str, line: usize) -> RangeInclusive<usize> {
+    constraint_lines(predicate)
+        .into_iter()
+        .find(|lines| lines.contains(&line))
+        .unwrap_or(line..=line)
+}
+
+fn format_predicate(
+    predicate: &str,
+    constraint_line: &Option<usize>,
+    span: Option<Span>,
+) -> String {
+    if constraint_line.is_none() && span.is_none() {
+        return predicate.to_string();
     }
+    let lines = constraint_line.map(|line| constraint_range(predicate, line));
+    lines_with_offsets(predicate).enumerate().fold(
+        String::with_capacity(predicate.len()),
+        |mut s, (i, (offset, line))| {
+            let is_constraint = lines.as_ref().is_some_and(|lines| lines.contains(&i));
+            let _ = writeln!(s, "{}", style_line(line, offset, span, is_constraint));
+            s
+        },
+    )
 }
 
-fn constraint_only(predicate: &str, constraint_line: &Option<usize>) -> String {
+fn format_constraint(
+    predicate: &str,
+    constraint_line: &Option<usize>,
+    span: Option<Span>,
+) -> String {
     match constraint_line {
-        Some(line_num) => match predicate.lines().nth(*line_num) {
-            Some(line) => line.trim().to_string(),
-            None => predicate.to_string(),
-        },
-        None => predicate.to_string(),
+        Some(line_num) => {
+            let lines = constraint_range(predicate, *line_num);
+            let others = constraint_lines(predicate);
+            lines_with_offsets(predicate).enumerate().fold(
+                String::with_capacity(predicate.len()),
+                |mut s, (i, (offset, line))| {
+                    if lines.contains(&i) {
+                        let _ = writeln!(s, "{}", style_line(line, offset, span, false));
+                    } else if others.iter().any(|other| other.contains(&i)) {
+                    } else {
+                        let _ = writeln!(s, "{}", line);
+                    }
+                    s
+                },
+            )
+        }
+        None => format_predicate(predicate, constraint_line, span),
     }
 }
 
+fn constraint_only(predicate: &str, constraint_line: &Option<usize>, span: Option<Span>) -> String {
+    let Some(line_num) = constraint_line else {
+        return predicate.to_string();
+    };
+    let lines = constraint_range(predicate, *line_num);
+    let mut constraint = lines_with_offsets(predicate)
+        .skip(*lines.start())
+        .take(lines.end() - lines.start() + 1)
+        .peekable();
+    let Some((_, first)) = constraint.peek() else {
+        return predicate.to_string();
+    };
+    // Remove the indentation of the first line from every line.
+    let indent = &first[..first.len() - first.trim_start().len()];
+    constraint
+        .map(|(offset, line)| {
+            let trimmed = line.strip_prefix(indent).unwrap_or(line.trim_start());
+            let offset = offset + (line.len() - trimmed.len());
+            style_line(trimmed.trim_end(), offset, span, false)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 impl From<Option<&str>> for ShowOutput {
     fn from(value: Option<&str>) -> Self {
         match value {
             Some("a") | Some("all") => ShowOutput::All,
             Some("p") | Some("predicate") => ShowOutput::Predicate,
             Some("c") | Some("constraint") => ShowOutput::Constraint,
-            Some("co") | Some("constraint only") => ShowOutput::ConstraintOnly,
+            Some("co") | Some("constraint_only") | Some("constraint only") => {
+                ShowOutput::ConstraintOnly
+            }
             _ => ShowOutput::default(),
         }
     }
@@ -61,6 +61,185 @@ predicate ::Transfer {
         .with_other_code(other.to_string())
         .with_predicate_find_line(predicate.to_string(), 4);
 
-    let out = show_code(&Some(source), ShowOutput::ConstraintOnly);
+    let out = show_code(&Some(source), ShowOutput::ConstraintOnly, 0);
     assert_eq!(out, "constraint (((__state_len(::nonce) == 0) && (::nonce' == 1)) || ((::nonce' - ::nonce) == 1));")
 }
+
+#[test]
+fn test_source_map() {
+    dialoguer::console::set_colors_enabled(false);
+    let predicate =
+        "predicate ::P {\n    constraint ::a > 0;\n    constraint (::b' - ::b) == 1;\n}\n";
+    let b_post = predicate.find("::b'").unwrap();
+    let source_map = parse_source_map(
+        format!(
+            r#"{{ "constraints": [[], [{{ "start": {}, "end": {} }}, null, {{ "start": 0, "end": 1000 }}]] }}"#,
+            b_post,
+            b_post + 4
+        )
+        .as_bytes(),
+    )
+    .unwrap();
+    let source = Source::default()
+        .with_predicate_find_line(predicate, 1)
+        .with_source_map(&source_map, 1);
+
+    assert_eq!(source.expression(0), Some("::b'"));
+    // Unmapped, out of bounds or past the end of the source.
+    assert_eq!(source.expression(1), None);
+    assert_eq!(source.expression(2), None);
+    assert_eq!(source.expression(3), None);
+
+    let source = Some(source);
+    assert_eq!(
+        show_code(&source, ShowOutput::ConstraintOnly, 0),
+        "constraint (::b' - ::b) == 1;"
+    );
+    assert_eq!(show_code(&source, ShowOutput::Predicate, 0), predicate);
+    assert_eq!(
+        show_code(&source, ShowOutput::Constraint, 1),
+        "predicate ::P {\n    constraint (::b' - ::b) == 1;\n}\n"
+    );
+    assert!(parse_source_map(b"{}").is_err());
+}
+
+#[test]
+fn test_lines_with_offsets() {
+    let lines = lines_with_offsets("ab\r\ncd\n\nef").collect::<Vec<_>>();
+    assert_eq!(lines, [(0, "ab"), (4, "cd"), (7, ""), (8, "ef")]);
+}
+
+#[test]
+fn test_multi_line_constraints() {
+    let predicate = r#"predicate ::P {
+    var ::a: int;
+    constraint ::a > 0;
+    constraint (::a == 1) // a comment; with a semicolon
+        || (::a == {
+            2;
+        }.0);
+    constraint ::a < 10
+        && ::a != 5;
+    var ::b: int;
+    constraint(::b >= 0);
+}
+"#;
+    assert_eq!(
+        constraint_lines(predicate),
+        vec![2..=2, 3..=6, 7..=8, 10..=10]
+    );
+
+    let source = Some(Source::default().with_predicate_find_line(predicate, 1));
+    assert_eq!(
+        show_code(&source, ShowOutput::ConstraintOnly, 0),
+        "constraint (::a == 1) // a comment; with a semicolon\n    || (::a == {\n        2;\n    }.0);"
+    );
+    assert_eq!(
+        show_code(&source, ShowOutput::Constraint, 0),
+        r#"predicate ::P {
+    var ::a: int;
+    constraint (::a == 1) // a comment; with a semicolon
+        || (::a == {
+            2;
+        }.0);
+    var ::b: int;
+}
+"#
+    );
+    assert_eq!(
+        show_code(&source, Some("constraint_only").into(), 0),
+        show_code(&source, ShowOutput::ConstraintOnly, 0)
+    );
+
+    let source = Some(Source::default().with_predicate_find_line(predicate, 3));
+    assert_eq!(
+        show_code(&source, ShowOutput::ConstraintOnly, 0),
+        "constraint(::b >= 0);"
+    );
+
+    // A line inside a constraint selects the whole constraint.
+    let source = Some(
+        Source::default()
+            .with_predicate(predicate)
+            .with_constraint_line_number(8),
+    );
+    assert_eq!(
+        show_code(&source, ShowOutput::ConstraintOnly, 0),
+        "constraint ::a < 10\n    && ::a != 5;"
+    );
+
+    // Without a `;` the constraint runs to the end.
+    assert_eq!(constraint_lines("constraint (1\n== 1)\n"), vec![0..=1]);
+}
+
+#[test]
+fn test_location() {
+    let predicate = "predicate ::P {\n    constraint ::a\n        == 1;\n}\n";
+    let a = predicate.find("::a").unwrap();
+    let eq = predicate.find("==").unwrap();
+    let source = Source::default().with_predicate_find_line(predicate, 0);
+    // Without a source map every op is at the constraint.
+    assert_eq!(source.location(3), Some((1, 0)));
+    assert_eq!(source.first_op_on_line(1), None);
+
+    let source_map = SourceMap {
+        constraints: vec![vec![
+            None,
+            Some(Span {
+                start: a,
+                end: a + 3,
+            }),
+            Some(Span {
+                start: eq,
+                end: eq + 2,
+            }),
+            Some(Span {
+                start: a,
+                end: eq + 4,
+            }),
+        ]],
+    };
+    let source = source.with_source_map(&source_map, 0);
+    assert_eq!(source.location(0), None);
+    assert_eq!(source.location(1), Some((1, 15)));
+    assert_eq!(source.location(2), Some((2, 8)));
+    assert_eq!(source.first_op_on_line(1), Some(1));
+    assert_eq!(source.first_op_on_line(2), Some(2));
+    assert_eq!(source.first_op_on_line(3), None);
+
+    let source = source.with_path("p.pnt");
+    assert!(source.is_path(std::path::Path::new("p.pnt")));
+    assert!(!source.is_path(std::path::Path::new("q.pnt")));
+}
+
+#[test]
+fn test_code_lines() {
+    let predicate = "predicate ::P {\n    constraint ::a\n        == 1;\n}\n";
+    let a = predicate.find("::a").unwrap();
+    let source = Source::default()
+        .with_predicate_find_line(predicate, 0)
+        .with_source_map(
+            &SourceMap {
+                constraints: vec![vec![Some(Span {
+                    start: a,
+                    end: predicate.find(';').unwrap(),
+                })]],
+            },
+            0,
+        );
+    let lines = source.code_lines(0);
+    assert_eq!(lines.len(), 4);
+    assert_eq!(
+        lines[1],
+        CodeLine {
+            text: "    constraint ::a",
+            is_constraint: true,
+            highlight: Some(15..18),
+        }
+    );
+    assert_eq!(lines[2].highlight, Some(0..12));
+    assert!(lines[2].is_constraint);
+    assert_eq!(lines[3].highlight, None);
+    assert!(!lines[0].is_constraint);
+    assert!(source.code_lines(1).iter().all(|l| l.highlight.is_none()));
+}
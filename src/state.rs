@@ -1,41 +1,245 @@
 use std::{
+    cell::RefCell,
     collections::{BTreeMap, HashMap},
-    future::{self, Ready},
+    fmt::Display,
+    future::{self, Future, Ready},
+    ops::Deref,
+    pin::Pin,
+    task::{ready, Context, Poll},
 };
 
+use anyhow::bail;
 use essential_constraint_vm::{
     mut_keys_set, transient_data, Access, BytecodeMapped, SolutionAccess, StateSlots,
 };
-use essential_state_read_vm::{GasLimit, StateRead};
+use essential_state_asm::{Op, StateSlots as SlotOp};
+use essential_state_read_vm::{
+    error::{OpError, StateReadError},
+    OpAccess, StateRead, Vm,
+};
 use essential_types::{
     predicate::Predicate,
-    solution::{Solution, SolutionDataIndex},
+    solution::{Mutation, Solution, SolutionData, SolutionDataIndex},
     ContentAddress, Key, Value, Word,
 };
 
+use crate::gas::{Gas, GasConfig, OutOfGas, StateReadGas};
+
+#[cfg(test)]
+mod tests;
+
 pub struct Slots {
     pub pre: Vec<Value>,
     pub post: Vec<Value>,
+    /// Gas spent by each state read program.
+    pub gas: Vec<StateReadGas>,
+    /// Where each pre state slot was read from.
+    pub pre_provenance: Vec<Option<SlotProvenance>>,
+    /// Where each post state slot was read from.
+    pub post_provenance: Vec<Option<SlotProvenance>>,
+}
+
+/// The key a state slot was read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotProvenance {
+    /// The state read program that read the slot.
+    pub program: usize,
+    pub contract: ContentAddress,
+    /// The first key of the range read by the op.
+    pub start_key: Key,
+    /// The number of keys read by the op.
+    pub count: usize,
+    /// The position of this slot's key in the range.
+    pub index: usize,
+    pub key: Key,
+    /// The key isn't in state so the slot was left empty.
+    pub missing: bool,
 }
 
-struct State(HashMap<ContentAddress, BTreeMap<Key, Value>>);
+/// Gives the executor a single op so it stops after one step.
+struct OneOp<'a> {
+    code: &'a BytecodeMapped<Op, Vec<u8>>,
+    done: bool,
+}
+
+/// Records the key ranges read through it.
+struct Recorder<'a, S> {
+    state: &'a S,
+    reads: &'a RefCell<Vec<KeyRangeRead>>,
+}
+
+struct KeyRangeRead {
+    contract: ContentAddress,
+    start_key: Key,
+    /// Each key read and whether it was missing.
+    keys: Vec<(Key, bool)>,
+}
+
+/// A key range read that is recorded once it is done.
+struct RecordedRead<'a, F> {
+    read: F,
+    contract: ContentAddress,
+    key: Key,
+    num_values: usize,
+    reads: &'a RefCell<Vec<KeyRangeRead>>,
+}
+
+/// The pre state with the solution's mutations layered on top.
+pub(crate) struct PostState<P> {
+    pre: P,
+    /// The mutated values of each contract. Empty values are deletions.
+    mutations: HashMap<ContentAddress, BTreeMap<Key, Value>>,
+}
+
+/// A pre state read with the mutated values swapped in.
+pub(crate) struct MutatedRead<F> {
+    read: F,
+    /// The value of each key in the range if the contract has mutations.
+    mutated: Option<Vec<Option<Value>>>,
+}
+
+pub(crate) struct State(HashMap<ContentAddress, BTreeMap<Key, Value>>);
+
+/// A state read implementation that can be kept without naming its type.
+pub(crate) struct AnyState(Box<dyn DynStateRead>);
+
+type AnyRead = Pin<Box<dyn Future<Output = Result<Vec<Value>, String>>>>;
+
+trait DynStateRead {
+    fn key_range(&self, set_addr: ContentAddress, key: Key, num_words: usize) -> AnyRead;
+}
+
+/// Parse a pre-state map from JSON.
+///
+/// The expected format maps each contract address (hex encoded)
+/// to a list of key value pairs:
+/// ```json
+/// {
+///   "8BD1FFCF63EBDBDC33B1D063FC1F95A256FF077750E03961BC60BFA0324D9340": [
+///     { "key": [0, 0, 0, 0], "value": [42] }
+///   ]
+/// }
+/// ```
+pub fn parse_state(bytes: &[u8]) -> anyhow::Result<HashMap<ContentAddress, BTreeMap<Key, Value>>> {
+    let raw: HashMap<String, Vec<Mutation>> = serde_json::from_slice(bytes)
+        .map_err(|e| anyhow::anyhow!("Failed to parse state file: {}", e))?;
+
+    let mut state: HashMap<ContentAddress, BTreeMap<Key, Value>> = HashMap::new();
+    for (addr, entries) in raw {
+        let contract: ContentAddress = addr
+            .trim()
+            .trim_start_matches("0x")
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Invalid contract address `{}`: {}", addr, e))?;
+        let set = state.entry(contract).or_default();
+        for Mutation { key, value } in entries {
+            if key.is_empty() {
+                bail!("Empty key found in state for contract `{}`", addr);
+            }
+            if set.insert(key.clone(), value).is_some() {
+                bail!(
+                    "Duplicate key {:?} found in state for contract `{}`",
+                    key,
+                    addr
+                );
+            }
+        }
+    }
+    Ok(state)
+}
+
+/// The state after the mutations in the solution are applied.
+pub fn post_state(
+    solution: &Solution,
+    pre: &HashMap<ContentAddress, BTreeMap<Key, Value>>,
+) -> HashMap<ContentAddress, BTreeMap<Key, Value>> {
+    let mut state = State(pre.clone());
+    state.apply_mutations(solution);
+    state.0
+}
 
 pub async fn read_state(
     solution: &Solution,
     index: SolutionDataIndex,
     predicate: &Predicate,
     state: HashMap<ContentAddress, BTreeMap<Key, Value>>,
+    gas: &GasConfig,
+) -> anyhow::Result<Slots> {
+    read_state_from(solution, index, predicate, &State::pre(state), gas).await
+}
+
+/// Read the value of each key the solution mutates from the pre state,
+/// so the state before and after the mutations can be shown.
+///
+/// Missing keys are left out.
+pub(crate) async fn read_mutated<S: StateRead>(
+    solution: &Solution,
+    pre_state: &S,
+) -> anyhow::Result<HashMap<ContentAddress, BTreeMap<Key, Value>>> {
+    let mut state: HashMap<_, BTreeMap<_, _>> = HashMap::new();
+    for data in &solution.data {
+        let contract = &data.predicate_to_solve.contract;
+        for Mutation { key, .. } in &data.state_mutations {
+            let value = pre_state
+                .key_range(contract.clone(), key.clone(), 1)
+                .await
+                .map_err(|e| {
+                    anyhow::anyhow!(
+                        "Failed to read mutated key {:?} of contract {}: {}",
+                        key,
+                        contract,
+                        e
+                    )
+                })?;
+            if let Some(value) = value.into_iter().next().filter(|v| !v.is_empty()) {
+                state
+                    .entry(contract.clone())
+                    .or_default()
+                    .insert(key.clone(), value);
+            }
+        }
+    }
+    Ok(state)
+}
+
+/// The solution data being solved, or an error if the index is out of range.
+pub(crate) fn solution_data(
+    solution: &Solution,
+    index: SolutionDataIndex,
+) -> anyhow::Result<&SolutionData> {
+    match solution.data.get(index as usize) {
+        Some(data) => Ok(data),
+        None => bail!(
+            "No solution data at index {}. The solution has {}",
+            index,
+            solution.data.len()
+        ),
+    }
+}
+
+/// Read the pre state from any state read implementation.
+///
+/// The post state is the pre state with the solution's mutations layered on top.
+pub async fn read_state_from<S: StateRead>(
+    solution: &Solution,
+    index: SolutionDataIndex,
+    predicate: &Predicate,
+    pre_state: &S,
+    gas: &GasConfig,
 ) -> anyhow::Result<Slots> {
-    let pre_state = State(state.clone());
-    // Apply mutations
-    let mut post_state = State(state);
-    post_state.apply_mutations(solution);
+    solution_data(solution, index)?;
+    let post_state = PostState::new(pre_state, solution);
 
     let mut pre_slots: Vec<Vec<Word>> = Vec::new();
     let mut post_slots: Vec<Vec<Word>> = Vec::new();
+    let mut pre_provenance = Vec::new();
+    let mut post_provenance = Vec::new();
+    let mut spent = Vec::with_capacity(predicate.state_read.len());
     let mutable_keys = mut_keys_set(solution, index);
     let transient_data = transient_data(solution);
-    for sr in &predicate.state_read {
+    for (i, sr) in predicate.state_read.iter().enumerate() {
+        let bc: BytecodeMapped<Op, Vec<u8>> = BytecodeMapped::try_from_bytes(sr.clone())?;
+
         let access = Access {
             solution: SolutionAccess::new(solution, index, &mutable_keys, &transient_data),
             state_slots: StateSlots {
@@ -43,19 +247,9 @@ pub async fn read_state(
                 post: &post_slots,
             },
         };
-        let mut vm = essential_state_read_vm::Vm::default();
-        let bc: BytecodeMapped<essential_state_asm::Op, Vec<u8>> =
-            BytecodeMapped::try_from_bytes(sr.clone())?;
-        vm.exec_bytecode(
-            &bc,
-            access,
-            &pre_state,
-            &|_: &essential_state_asm::Op| 1,
-            GasLimit::UNLIMITED,
-        )
-        .await?;
-
-        pre_slots.extend(vm.into_state_slots());
+        let (slots, provenance, pre) = exec_program(i, "pre", &bc, access, pre_state, gas).await?;
+        pre_slots.extend(slots);
+        pre_provenance.extend(provenance);
 
         let access = Access {
             solution: SolutionAccess::new(solution, index, &mutable_keys, &transient_data),
@@ -64,27 +258,348 @@ pub async fn read_state(
                 post: &post_slots,
             },
         };
-        let mut vm = essential_state_read_vm::Vm::default();
-        let bc: BytecodeMapped<essential_state_asm::Op, Vec<u8>> =
-            BytecodeMapped::try_from_bytes(sr.clone())?;
-        vm.exec_bytecode(
-            &bc,
-            access,
-            &post_state,
-            &|_: &essential_state_asm::Op| 1,
-            GasLimit::UNLIMITED,
-        )
-        .await?;
+        let (slots, provenance, post) =
+            exec_program(i, "post", &bc, access, &post_state, gas).await?;
+        post_slots.extend(slots);
+        post_provenance.extend(provenance);
 
-        post_slots.extend(vm.into_state_slots());
+        spent.push(StateReadGas { pre, post });
     }
 
     Ok(Slots {
         pre: pre_slots,
         post: post_slots,
+        gas: spent,
+        pre_provenance,
+        post_provenance,
     })
 }
 
+/// Run a state read program one op at a time, recording
+/// which key each slot written by a key range read came from.
+async fn exec_program<S: StateRead>(
+    program: usize,
+    run: &str,
+    code: &BytecodeMapped<Op, Vec<u8>>,
+    access: Access<'_>,
+    state: &S,
+    gas: &GasConfig,
+) -> anyhow::Result<(Vec<Value>, Vec<Option<SlotProvenance>>, Gas)> {
+    let reads = RefCell::default();
+    let recorder = Recorder {
+        state,
+        reads: &reads,
+    };
+    let mut vm = Vm::default();
+    let mut spent = 0;
+    let mut provenance: Vec<Option<SlotProvenance>> = Vec::new();
+    loop {
+        let op = code.op(vm.pc);
+        // Key range reads write to the slot index on top of the stack.
+        let read_into = match op {
+            Some(Op::KeyRange | Op::KeyRangeExtern) => vm.stack.last().copied(),
+            _ => None,
+        };
+        let before = matches!(
+            op,
+            Some(Op::StateSlots(
+                SlotOp::Store | SlotOp::StoreWord | SlotOp::Clear | SlotOp::ClearRange
+            ))
+        )
+        .then(|| vm.state_slots_mut.to_vec());
+
+        let halted = step(&mut vm, code, access, &recorder, gas, &mut spent)
+            .await
+            .map_err(|e| state_read_error(program, run, spent, e))?;
+
+        provenance.resize(vm.state_slots_mut.len(), None);
+        if let Some(before) = before {
+            // Slots written by the program no longer hold what was read.
+            for (i, p) in provenance.iter_mut().enumerate() {
+                if before.get(i) != vm.state_slots_mut.get(i) {
+                    *p = None;
+                }
+            }
+        }
+        let read = reads.borrow_mut().pop();
+        if let (Some(slot), Some(read)) = (read_into, read) {
+            let count = read.keys.len();
+            for (j, (key, missing)) in read.keys.into_iter().enumerate() {
+                if let Some(p) = provenance.get_mut(slot as usize + j) {
+                    *p = Some(SlotProvenance {
+                        program,
+                        contract: read.contract.clone(),
+                        start_key: read.start_key.clone(),
+                        count,
+                        index: j,
+                        key,
+                        missing,
+                    });
+                }
+            }
+        }
+
+        if halted {
+            return Ok((vm.into_state_slots(), provenance, spent));
+        }
+    }
+}
+
+/// Play the op at the program counter, charging its gas to `spent`.
+///
+/// The VM has no way to play a single op, so it is given one op at a
+/// time. Returns whether the op halted the program.
+pub(crate) async fn step<S: StateRead>(
+    vm: &mut Vm,
+    code: &BytecodeMapped<Op, Vec<u8>>,
+    access: Access<'_>,
+    state: &S,
+    gas: &GasConfig,
+    spent: &mut Gas,
+) -> Result<bool, StateReadError<S::Error>> {
+    let Some(op) = code.op(vm.pc) else {
+        return Err(StateReadError::PcOutOfRange(vm.pc));
+    };
+    let result = vm
+        .exec(
+            access,
+            state,
+            OneOp::new(code),
+            &|op: &Op| gas.state_read_cost(op),
+            gas.state_read_limit(*spent),
+        )
+        .await;
+    match result {
+        // Only `Halt` finishes within a single op.
+        Ok(g) => {
+            *spent += g;
+            Ok(true)
+        }
+        // The executor ran out of ops after the one it was given.
+        Err(StateReadError::PcOutOfRange(_)) => {
+            *spent += gas.state_read_cost(&op);
+            Ok(false)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Name the program and the state it was reading if it ran out of gas.
+fn state_read_error<E: Display>(
+    program: usize,
+    run: &str,
+    spent: Gas,
+    e: StateReadError<E>,
+) -> anyhow::Error {
+    match e {
+        StateReadError::Op(pc, OpError::OutOfGas(e)) => anyhow::anyhow!(
+            "State read program {} reading the {} state ran {}",
+            program,
+            run,
+            OutOfGas::from_state_read(pc, spent, &e)
+        ),
+        e => anyhow::anyhow!("{}", e),
+    }
+}
+
+impl<'a> OneOp<'a> {
+    fn new(code: &'a BytecodeMapped<Op, Vec<u8>>) -> Self {
+        Self { code, done: false }
+    }
+}
+
+impl OpAccess for OneOp<'_> {
+    type Op = Op;
+    type Error = std::convert::Infallible;
+
+    fn op_access(&mut self, index: usize) -> Option<Result<Op, Self::Error>> {
+        if std::mem::replace(&mut self.done, true) {
+            return None;
+        }
+        self.code.op(index).map(Ok)
+    }
+}
+
+impl<'a, S: StateRead> StateRead for Recorder<'a, S> {
+    type Error = S::Error;
+    type Future = RecordedRead<'a, S::Future>;
+    fn key_range(&self, set_addr: ContentAddress, key: Key, num_words: usize) -> Self::Future {
+        RecordedRead {
+            read: self
+                .state
+                .key_range(set_addr.clone(), key.clone(), num_words),
+            contract: set_addr,
+            key,
+            num_values: num_words,
+            reads: self.reads,
+        }
+    }
+}
+
+impl<F, E> Future for RecordedRead<'_, F>
+where
+    F: Future<Output = Result<Vec<Value>, E>> + Unpin,
+{
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let values = ready!(Pin::new(&mut this.read).poll(cx))?;
+        // An empty value can't be told apart from a missing key.
+        let keys = keys(this.key.clone(), this.num_values)
+            .enumerate()
+            .map(|(i, key)| (key, values.get(i).is_none_or(Vec::is_empty)))
+            .collect();
+        this.reads.borrow_mut().push(KeyRangeRead {
+            contract: this.contract.clone(),
+            start_key: this.key.clone(),
+            keys,
+        });
+        Poll::Ready(Ok(values))
+    }
+}
+
+impl<P> PostState<P> {
+    pub(crate) fn new(pre: P, solution: &Solution) -> Self {
+        let mut mutations: HashMap<_, BTreeMap<_, _>> = HashMap::new();
+        for data in &solution.data {
+            for Mutation { key, value } in &data.state_mutations {
+                mutations
+                    .entry(data.predicate_to_solve.contract.clone())
+                    .or_default()
+                    .insert(key.clone(), value.clone());
+            }
+        }
+        Self { pre, mutations }
+    }
+}
+
+impl<P> StateRead for PostState<P>
+where
+    P: Deref,
+    P::Target: StateRead,
+{
+    type Error = <P::Target as StateRead>::Error;
+    type Future = MutatedRead<<P::Target as StateRead>::Future>;
+    fn key_range(&self, set_addr: ContentAddress, key: Key, num_words: usize) -> Self::Future {
+        let mutated = self.mutations.get(&set_addr).map(|set| {
+            keys(key.clone(), num_words)
+                .map(|k| set.get(&k).cloned())
+                .collect()
+        });
+        MutatedRead {
+            read: self.pre.key_range(set_addr, key, num_words),
+            mutated,
+        }
+    }
+}
+
+impl AnyState {
+    pub(crate) fn new<S>(state: S) -> Self
+    where
+        S: StateRead + 'static,
+    {
+        Self(Box::new(state))
+    }
+}
+
+impl<S> DynStateRead for S
+where
+    S: StateRead + 'static,
+{
+    fn key_range(&self, set_addr: ContentAddress, key: Key, num_words: usize) -> AnyRead {
+        let read = StateRead::key_range(self, set_addr, key, num_words);
+        Box::pin(async move { read.await.map_err(|e| e.to_string()) })
+    }
+}
+
+impl StateRead for AnyState {
+    type Error = String;
+    type Future = AnyRead;
+    fn key_range(&self, set_addr: ContentAddress, key: Key, num_words: usize) -> Self::Future {
+        self.0.key_range(set_addr, key, num_words)
+    }
+}
+
+impl<F, E> Future for MutatedRead<F>
+where
+    F: Future<Output = Result<Vec<Value>, E>> + Unpin,
+{
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let mut values = ready!(Pin::new(&mut this.read).poll(cx))?;
+        if let Some(mutated) = this.mutated.take() {
+            // A contract with mutations has a value for every key, even if
+            // the pre state doesn't know about the contract.
+            if values.len() < mutated.len() {
+                values.resize(mutated.len(), vec![]);
+            }
+            for (value, m) in values.iter_mut().zip(mutated) {
+                if let Some(m) = m {
+                    *value = m;
+                }
+            }
+        }
+        Poll::Ready(Ok(values))
+    }
+}
+
+impl Slots {
+    /// The pre state read into slots that the programs didn't change afterwards.
+    pub(crate) fn pre_entries(&self) -> HashMap<ContentAddress, BTreeMap<Key, Value>> {
+        let mut state: HashMap<_, BTreeMap<_, _>> = HashMap::new();
+        for (value, p) in self.pre.iter().zip(&self.pre_provenance) {
+            if let Some(p) = p.as_ref().filter(|p| !p.missing) {
+                state
+                    .entry(p.contract.clone())
+                    .or_default()
+                    .insert(p.key.clone(), value.clone());
+            }
+        }
+        state
+    }
+}
+
+/// Get the key that follows this one.
+fn next_key(mut key: Key) -> Option<Key> {
+    for w in key.iter_mut().rev() {
+        match *w {
+            Word::MAX => *w = Word::MIN,
+            _ => {
+                *w += 1;
+                return Some(key);
+            }
+        }
+    }
+    None
+}
+
+/// The keys in a range of `num` keys starting at `key`.
+fn keys(key: Key, num: usize) -> impl Iterator<Item = Key> {
+    std::iter::successors(Some(key), |k| next_key(k.clone())).take(num)
+}
+
+impl Display for SlotProvenance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Read by state read program {} from contract {}\n  Key {:?} ({} of {} read from {:?})",
+            self.program,
+            self.contract,
+            self.key,
+            self.index + 1,
+            self.count,
+            self.start_key
+        )?;
+        if self.missing {
+            write!(f, "\n  The key isn't in state so the slot is empty")?;
+        }
+        Ok(())
+    }
+}
+
 impl StateRead for State {
     type Error = anyhow::Error;
     type Future = Ready<Result<Vec<Vec<Word>>, Self::Error>>;
@@ -94,26 +609,17 @@ impl StateRead for State {
 }
 
 impl State {
+    /// The state before the mutations in the solution are applied.
+    pub(crate) fn pre(state: HashMap<ContentAddress, BTreeMap<Key, Value>>) -> Self {
+        State(state)
+    }
+
     fn key_range(
         &self,
         set_addr: ContentAddress,
         mut key: Key,
         num_words: usize,
     ) -> anyhow::Result<Vec<Value>> {
-        // Get the key that follows this one.
-        fn next_key(mut key: Key) -> Option<Key> {
-            for w in key.iter_mut().rev() {
-                match *w {
-                    Word::MAX => *w = Word::MIN,
-                    _ => {
-                        *w += 1;
-                        return Some(key);
-                    }
-                }
-            }
-            None
-        }
-
         let Some(set) = self.0.get(&set_addr) else {
             return Ok(vec![]);
         };
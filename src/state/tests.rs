@@ -0,0 +1,187 @@
+use super::*;
+
+const ADDR: &str = "8BD1FFCF63EBDBDC33B1D063FC1F95A256FF077750E03961BC60BFA0324D9340";
+
+#[test]
+fn test_parse_state() {
+    let json = format!(
+        r#"{{ "{}": [{{ "key": [0, 0, 0, 0], "value": [42] }}, {{ "key": [1], "value": [1, 2] }}] }}"#,
+        ADDR
+    );
+    let state = parse_state(json.as_bytes()).unwrap();
+    let set = &state[&ADDR.parse::<ContentAddress>().unwrap()];
+    assert_eq!(set[&vec![0, 0, 0, 0]], vec![42]);
+    assert_eq!(set[&vec![1]], vec![1, 2]);
+}
+
+#[test]
+fn test_parse_state_errors() {
+    let err = parse_state(br#"{ "1234": [] }"#).unwrap_err();
+    assert!(err.to_string().contains("Invalid contract address `1234`"));
+
+    let json = format!(r#"{{ "{}": [{{ "key": [], "value": [1] }}] }}"#, ADDR);
+    let err = parse_state(json.as_bytes()).unwrap_err();
+    assert!(err.to_string().contains("Empty key"));
+
+    let json = format!(
+        r#"{{ "{}": [{{ "key": [1], "value": [1] }}, {{ "key": [1], "value": [2] }}] }}"#,
+        ADDR
+    );
+    let err = parse_state(json.as_bytes()).unwrap_err();
+    assert!(err.to_string().contains("Duplicate key"));
+
+    let json = format!(r#"{{ "{}": [{{ "key": ["a"], "value": [1] }}] }}"#, ADDR);
+    let err = parse_state(json.as_bytes()).unwrap_err();
+    assert!(err.to_string().contains("Failed to parse state file"));
+}
+
+#[tokio::test]
+async fn test_read_state_provenance() {
+    use essential_state_asm as asm;
+    use essential_types::{
+        predicate::Directive,
+        solution::{Mutation, SolutionData},
+        PredicateAddress,
+    };
+
+    let contract: ContentAddress = ADDR.parse().unwrap();
+    // Reads keys `[0, 0, 0, 1]` and `[0, 0, 0, 2]` into two new slots.
+    let read_two = asm::to_bytes([
+        asm::Stack::Push(2).into(),
+        asm::StateSlots::AllocSlots.into(),
+        asm::Stack::Push(0).into(),
+        asm::Stack::Push(0).into(),
+        asm::Stack::Push(0).into(),
+        asm::Stack::Push(1).into(),
+        asm::Stack::Push(4).into(),
+        asm::Stack::Push(2).into(),
+        asm::Stack::Push(0).into(),
+        asm::StateRead::KeyRange,
+        asm::TotalControlFlow::Halt.into(),
+    ])
+    .collect();
+    // Reads key `[0, 0, 0, 1]` then clears the slot.
+    let read_and_clear = asm::to_bytes([
+        asm::Stack::Push(1).into(),
+        asm::StateSlots::AllocSlots.into(),
+        asm::Stack::Push(0).into(),
+        asm::Stack::Push(0).into(),
+        asm::Stack::Push(0).into(),
+        asm::Stack::Push(1).into(),
+        asm::Stack::Push(4).into(),
+        asm::Stack::Push(1).into(),
+        asm::Stack::Push(0).into(),
+        asm::StateRead::KeyRange,
+        asm::Stack::Push(0).into(),
+        asm::StateSlots::Clear.into(),
+        asm::TotalControlFlow::Halt.into(),
+    ])
+    .collect();
+    let predicate = Predicate {
+        state_read: vec![read_two, read_and_clear],
+        constraints: vec![
+            essential_constraint_asm::to_bytes([asm::Stack::Push(1).into()]).collect(),
+        ],
+        directive: Directive::Satisfy,
+    };
+    let solution = Solution {
+        data: vec![SolutionData {
+            predicate_to_solve: PredicateAddress {
+                contract: contract.clone(),
+                predicate: contract.clone(),
+            },
+            decision_variables: vec![],
+            state_mutations: vec![Mutation {
+                key: vec![0, 0, 0, 2],
+                value: vec![5],
+            }],
+            transient_data: vec![],
+        }],
+    };
+    let state = HashMap::from([(
+        contract.clone(),
+        BTreeMap::from([(vec![0, 0, 0, 1], vec![8])]),
+    )]);
+    let slots = read_state(&solution, 0, &predicate, state.clone(), &Default::default())
+        .await
+        .unwrap();
+    assert_eq!(slots.pre, vec![vec![8], vec![], vec![]]);
+    assert_eq!(slots.post, vec![vec![8], vec![5], vec![]]);
+
+    let provenance = |index: usize, missing| SlotProvenance {
+        program: 0,
+        contract: contract.clone(),
+        start_key: vec![0, 0, 0, 1],
+        count: 2,
+        index,
+        key: vec![0, 0, 0, 1 + index as Word],
+        missing,
+    };
+    assert_eq!(
+        slots.pre_provenance,
+        vec![Some(provenance(0, false)), Some(provenance(1, true)), None]
+    );
+    assert_eq!(
+        slots.post_provenance,
+        vec![Some(provenance(0, false)), Some(provenance(1, false)), None]
+    );
+    assert_eq!(
+        provenance(1, true).to_string(),
+        format!(
+            "Read by state read program 0 from contract {}\n  Key [0, 0, 0, 2] (2 of 2 read from [0, 0, 0, 1])\n  The key isn't in state so the slot is empty",
+            ADDR
+        )
+    );
+
+    let mut debugger = crate::ConstraintDebugger::new(solution, 0, predicate, 0, state)
+        .await
+        .unwrap();
+    let session = debugger.start_session();
+    assert_eq!(
+        session.show(&["post", "1"]),
+        format!(
+            "Post state slot 1: [5]\n  Read by state read program 0 from contract {}\n  Key [0, 0, 0, 2] (2 of 2 read from [0, 0, 0, 1])",
+            ADDR
+        )
+    );
+    assert_eq!(
+        session.show(&["pre", "2"]),
+        "Pre state slot 2: []\n  Not read from state"
+    );
+}
+
+#[tokio::test]
+async fn test_solution_data_index_out_of_range() {
+    let predicate = Predicate {
+        state_read: vec![],
+        constraints: vec![essential_constraint_asm::to_bytes([
+            essential_constraint_asm::Stack::Push(1).into(),
+        ])
+        .collect()],
+        directive: essential_types::predicate::Directive::Satisfy,
+    };
+    let solution = Solution { data: vec![] };
+    let e = read_state(
+        &solution,
+        0,
+        &predicate,
+        Default::default(),
+        &Default::default(),
+    )
+    .await
+    .err()
+    .unwrap();
+    assert_eq!(
+        e.to_string(),
+        "No solution data at index 0. The solution has 0"
+    );
+
+    let e = crate::ConstraintDebugger::new(solution, 3, predicate, 0, Default::default())
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(
+        e.to_string(),
+        "No solution data at index 3. The solution has 0"
+    );
+}
@@ -0,0 +1,602 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    io::{BufRead, Write},
+    rc::Rc,
+    str::FromStr,
+};
+
+use anyhow::bail;
+use essential_constraint_vm::{
+    mut_keys_set, transient_data, Access, SolutionAccess, Stack, StateSlots,
+};
+use essential_state_asm::Op;
+use essential_state_read_vm::{
+    error::{OpError, StateReadError},
+    BytecodeMapped, StateRead, StateSlotsMut, Vm,
+};
+use essential_types::{
+    predicate::Predicate,
+    solution::{Solution, SolutionDataIndex},
+    ContentAddress, Key, Value, Word,
+};
+
+use crate::{
+    checkpoint::restore_memory,
+    gas, memory_words, prompt_loop, script_loop,
+    state::{self, read_state_from, AnyState, PostState, Slots, State},
+    Checkpoints, Gas, GasConfig, OutOfGas, Snapshot, StateReadGas, TraceOutcome, PROMPT,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Which state a state read program reads from.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StateRun {
+    /// The state before the solution's mutations.
+    #[default]
+    Pre,
+    /// The state after the solution's mutations.
+    Post,
+}
+
+/// Steps through a state read program one op at a time,
+/// reading from either the pre or post state.
+///
+/// The program sees the slots read by the programs before it,
+/// as it would when the constraints are checked.
+pub struct StateReadDebugger {
+    solution: Solution,
+    index: SolutionDataIndex,
+    predicate: Predicate,
+    program: usize,
+    run: StateRun,
+    code: BytecodeMapped,
+    pre: Rc<AnyState>,
+    post: PostState<Rc<AnyState>>,
+    /// Slots read by the programs before this one. The post run
+    /// also sees the pre state slots read by this program.
+    slots: Slots,
+    gas: GasConfig,
+    gas_spent: Gas,
+    vm: Vm,
+    pos: usize,
+    last_op: Option<Op>,
+    outcome: Option<TraceOutcome>,
+    checkpoints: Checkpoints<VmCheckpoint>,
+}
+
+/// A snapshot of the VM taken outside of repeat loops,
+/// as the VM's repeat stack can't be rebuilt.
+struct VmCheckpoint {
+    pos: usize,
+    pc: usize,
+    stack: Stack,
+    memory: Vec<Word>,
+    slots: StateSlotsMut,
+    last_op: Option<Op>,
+    gas: Gas,
+}
+
+/// Run the state read debugger with an interactive prompt.
+pub async fn run_state_read(
+    solution: Solution,
+    index: SolutionDataIndex,
+    predicate: Predicate,
+    program: usize,
+    state: HashMap<ContentAddress, BTreeMap<Key, Value>>,
+    gas: GasConfig,
+) -> anyhow::Result<()> {
+    let mut debugger =
+        StateReadDebugger::new(solution, index, predicate, program, state, gas).await?;
+    prompt_loop(
+        &mut debugger,
+        StateReadDebugger::prompt,
+        async |debugger: &mut StateReadDebugger, command: &str, out: &mut String, _: &mut _| {
+            debugger.execute(command, out).await
+        },
+    )
+    .await
+}
+
+/// Run the state read debugger with commands read from `script`, one per line.
+///
+/// Each command is echoed to `output` followed by its result.
+/// Empty lines and lines starting with `#` are skipped.
+#[allow(clippy::too_many_arguments)]
+pub async fn run_state_read_script(
+    solution: Solution,
+    index: SolutionDataIndex,
+    predicate: Predicate,
+    program: usize,
+    state: HashMap<ContentAddress, BTreeMap<Key, Value>>,
+    gas: GasConfig,
+    script: impl BufRead,
+    output: impl Write,
+) -> anyhow::Result<()> {
+    let mut debugger =
+        StateReadDebugger::new(solution, index, predicate, program, state, gas).await?;
+    script_loop(
+        &mut debugger,
+        script,
+        output,
+        StateReadDebugger::prompt,
+        async |debugger: &mut StateReadDebugger, command: &str, out: &mut String| {
+            debugger.execute(command, out).await
+        },
+    )
+    .await
+}
+
+fn help_msg() -> String {
+    r#"Commands:
+    n | next: Step forward
+    b | back: Step back
+    p | play [i]: Play to ith op
+    e | end: Play till the program halts or an error is hit
+    l | list: List the ops of the program
+    s | slots: Show the slots read by earlier programs
+    g | gas: Show the gas spent by this and earlier programs
+    r | run <pre | post>: Restart reading from the pre or post state
+    pr | program <i>: Restart with the ith state read program
+    q | quit | exit: Quit
+    h | help: Show this message
+    "#
+    .to_string()
+}
+
+impl StateReadDebugger {
+    /// Earlier programs are charged from `gas` too,
+    /// so one that runs out of gas fails to start the debugger.
+    pub async fn new(
+        solution: Solution,
+        index: SolutionDataIndex,
+        predicate: Predicate,
+        program: usize,
+        state: HashMap<ContentAddress, BTreeMap<Key, Value>>,
+        gas: GasConfig,
+    ) -> anyhow::Result<Self> {
+        Self::new_with_state_read(solution, index, predicate, program, State::pre(state), gas).await
+    }
+
+    /// Read the pre state from any state read implementation instead of a map.
+    ///
+    /// The implementation is kept to read state as the program is played.
+    pub async fn new_with_state_read<S>(
+        solution: Solution,
+        index: SolutionDataIndex,
+        predicate: Predicate,
+        program: usize,
+        pre_state: S,
+        gas: GasConfig,
+    ) -> anyhow::Result<Self>
+    where
+        S: StateRead + 'static,
+    {
+        let Some(code) = predicate.state_read.get(program).cloned() else {
+            bail!("No state read program found");
+        };
+        let code = BytecodeMapped::try_from_bytes(code)?;
+        let pre = Rc::new(AnyState::new(pre_state));
+        let slots = read_state_from(
+            &solution,
+            index,
+            &programs_before(&predicate, program),
+            &*pre,
+            &gas,
+        )
+        .await?;
+        Ok(Self {
+            post: PostState::new(pre.clone(), &solution),
+            pre,
+            solution,
+            index,
+            predicate,
+            program,
+            run: StateRun::Pre,
+            code,
+            slots,
+            gas,
+            gas_spent: 0,
+            vm: Vm::default(),
+            pos: 0,
+            last_op: None,
+            outcome: None,
+            checkpoints: Checkpoints::default(),
+        })
+    }
+
+    /// Restart with another program or run.
+    pub async fn select(&mut self, program: usize, run: StateRun) -> anyhow::Result<()> {
+        let Some(code) = self.predicate.state_read.get(program).cloned() else {
+            bail!("No state read program {}", program);
+        };
+        let before = read_state_from(
+            &self.solution,
+            self.index,
+            &programs_before(&self.predicate, program),
+            &*self.pre,
+            &self.gas,
+        )
+        .await?;
+        self.slots = match run {
+            StateRun::Pre => before,
+            StateRun::Post => {
+                let with_this = read_state_from(
+                    &self.solution,
+                    self.index,
+                    &programs_before(&self.predicate, program + 1),
+                    &*self.pre,
+                    &self.gas,
+                )
+                .await?;
+                Slots {
+                    pre: with_this.pre,
+                    post: before.post,
+                    gas: before.gas,
+                    pre_provenance: with_this.pre_provenance,
+                    post_provenance: before.post_provenance,
+                }
+            }
+        };
+        self.code = BytecodeMapped::try_from_bytes(code)?;
+        self.program = program;
+        self.run = run;
+        self.checkpoints = Checkpoints::default();
+        self.reset();
+        Ok(())
+    }
+
+    /// Go back to the start of the program.
+    pub fn reset(&mut self) {
+        self.vm = Vm::default();
+        self.gas_spent = 0;
+        self.pos = 0;
+        self.last_op = None;
+        self.outcome = None;
+    }
+
+    /// Play the next op, awaiting state reads.
+    pub async fn step(&mut self) -> anyhow::Result<TraceOutcome> {
+        if let Some(outcome) = self.outcome.as_ref().filter(|o| **o != TraceOutcome::Step) {
+            return Ok(outcome.clone());
+        }
+        let Some(op) = self.code.op(self.vm.pc) else {
+            let e = StateReadError::<anyhow::Error>::PcOutOfRange(self.vm.pc);
+            let outcome = TraceOutcome::Panic(e.to_string());
+            self.outcome = Some(outcome.clone());
+            return Ok(outcome);
+        };
+        let mutable_keys = mut_keys_set(&self.solution, self.index);
+        let transient_data = transient_data(&self.solution);
+        let access = Access {
+            solution: SolutionAccess::new(
+                &self.solution,
+                self.index,
+                &mutable_keys,
+                &transient_data,
+            ),
+            state_slots: StateSlots {
+                pre: &self.slots.pre,
+                post: &self.slots.post,
+            },
+        };
+        let (vm, code, gas, spent) = (&mut self.vm, &self.code, &self.gas, &mut self.gas_spent);
+        let result = match self.run {
+            StateRun::Pre => state::step(vm, code, access, &*self.pre, gas, spent).await,
+            StateRun::Post => state::step(vm, code, access, &self.post, gas, spent).await,
+        };
+        self.pos += 1;
+        self.last_op = Some(op);
+        let outcome = match result {
+            Ok(true) => TraceOutcome::ProgramEnd,
+            Ok(false) => TraceOutcome::Step,
+            Err(StateReadError::Op(pc, OpError::OutOfGas(e))) => {
+                TraceOutcome::OutOfGas(OutOfGas::from_state_read(pc, self.gas_spent, &e))
+            }
+            Err(e) => TraceOutcome::Panic(e.to_string()),
+        };
+        if outcome == TraceOutcome::Step
+            && self.checkpoints.wants(self.pos)
+            && self.vm.repeat == Default::default()
+        {
+            let checkpoint = VmCheckpoint {
+                pos: self.pos,
+                pc: self.vm.pc,
+                stack: self.vm.stack.clone(),
+                memory: memory_words(&mut self.vm.temp_memory),
+                slots: self.vm.state_slots_mut.clone(),
+                last_op: self.last_op,
+                gas: self.gas_spent,
+            };
+            self.checkpoints.insert(checkpoint);
+        }
+        self.outcome = Some(outcome.clone());
+        Ok(outcome)
+    }
+
+    /// Play to the ith op, going back to the nearest checkpoint if it has
+    /// already been played.
+    pub async fn seek(&mut self, i: usize) -> anyhow::Result<TraceOutcome> {
+        let i = i.max(1);
+        let ended = self
+            .outcome
+            .as_ref()
+            .is_some_and(|o| *o != TraceOutcome::Step);
+        if i < self.pos || ended {
+            match self.checkpoints.nearest(i) {
+                Some(checkpoint) => {
+                    self.vm = Vm {
+                        pc: checkpoint.pc,
+                        stack: checkpoint.stack.clone(),
+                        temp_memory: restore_memory(&checkpoint.memory),
+                        repeat: Default::default(),
+                        state_slots_mut: checkpoint.slots.clone(),
+                    };
+                    self.pos = checkpoint.pos;
+                    self.last_op = checkpoint.last_op;
+                    self.gas_spent = checkpoint.gas;
+                    self.outcome = Some(TraceOutcome::Step);
+                }
+                None => self.reset(),
+            }
+        }
+        let mut outcome = self.outcome.clone().unwrap_or(TraceOutcome::Step);
+        while self.pos < i && outcome == TraceOutcome::Step {
+            outcome = self.step().await?;
+        }
+        Ok(outcome)
+    }
+
+    /// Go back to before the last op was played.
+    pub async fn back(&mut self) -> anyhow::Result<TraceOutcome> {
+        self.seek(self.pos.saturating_sub(1)).await
+    }
+
+    /// Play until the program halts or panics.
+    pub async fn play_till_end(&mut self) -> anyhow::Result<TraceOutcome> {
+        loop {
+            let outcome = self.step().await?;
+            if outcome != TraceOutcome::Step {
+                return Ok(outcome);
+            }
+        }
+    }
+
+    pub fn program(&self) -> usize {
+        self.program
+    }
+
+    pub fn run(&self) -> StateRun {
+        self.run
+    }
+
+    /// The number of ops played.
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    pub fn pc(&self) -> usize {
+        self.vm.pc
+    }
+
+    /// The op that will be played next, if the program hasn't ended.
+    pub fn op(&self) -> Option<Op> {
+        match self.outcome {
+            // `Halt` leaves the program counter where it was.
+            Some(TraceOutcome::ProgramEnd) => None,
+            _ => self.code.op(self.vm.pc),
+        }
+    }
+
+    /// The op that was played last.
+    pub fn last_op(&self) -> Option<Op> {
+        self.last_op
+    }
+
+    /// The outcome of the last step, if anything has been played.
+    pub fn outcome(&self) -> Option<&TraceOutcome> {
+        self.outcome.as_ref()
+    }
+
+    pub fn stack(&self) -> &[Word] {
+        &self.vm.stack[..]
+    }
+
+    /// All words in temporary memory.
+    pub fn memory(&mut self) -> Vec<Word> {
+        memory_words(&mut self.vm.temp_memory)
+    }
+
+    /// The slots this program is filling.
+    pub fn slots(&self) -> &[Vec<Word>] {
+        &self.vm.state_slots_mut
+    }
+
+    /// The pre and post state slots read by earlier programs.
+    pub fn earlier_slots(&self) -> (&[Vec<Word>], &[Vec<Word>]) {
+        (&self.slots.pre, &self.slots.post)
+    }
+
+    /// Gas spent by this program so far.
+    pub fn gas_spent(&self) -> Gas {
+        self.gas_spent
+    }
+
+    /// Gas spent by the programs before this one.
+    pub fn earlier_gas(&self) -> &[StateReadGas] {
+        &self.slots.gas
+    }
+
+    fn prompt(&self) -> String {
+        format!("{}::state_read[{}]::{}", PROMPT, self.program, self.run)
+    }
+
+    fn list(&self) -> String {
+        use std::fmt::Write;
+        self.code
+            .ops_from(0)
+            .map(|ops| {
+                ops.ops()
+                    .enumerate()
+                    .fold(String::new(), |mut out, (i, op)| {
+                        if i == self.vm.pc {
+                            let _ = writeln!(
+                                out,
+                                "{}:Op: {:?}",
+                                i,
+                                dialoguer::console::style(op).cyan()
+                            );
+                        } else {
+                            let _ = writeln!(out, "{}:Op: {:?}", i, op);
+                        }
+                        out
+                    })
+            })
+            .unwrap_or_default()
+    }
+
+    fn show_slots(&self) -> String {
+        let (pre, post) = self.earlier_slots();
+        format!(
+            "Pre state slots: {:?}\nPost state slots: {:?}\nSlots being read: {:?}",
+            pre,
+            post,
+            self.slots()
+        )
+    }
+
+    fn show_gas(&self) -> String {
+        format!(
+            "{}State read program {} reading the {} state: {}",
+            gas::show_state_read(self.earlier_gas()),
+            self.program,
+            self.run,
+            gas::show_spent(self.gas_spent, self.gas.limit)
+        )
+    }
+
+    /// Run a single command, writing the result to `out`.
+    ///
+    /// Returns false if the command quits the debugger.
+    async fn execute(&mut self, command: &str, out: &mut String) -> anyhow::Result<bool> {
+        let mut c = command.split(' ').filter(|s| !s.is_empty());
+        let outcome = match (c.next(), c.next()) {
+            (Some("n" | "next"), None) => self.step().await?,
+            (Some("b" | "back"), None) => self.back().await?,
+            (Some("p" | "play"), i) => {
+                let i = i.and_then(|i| i.parse().ok()).unwrap_or_default();
+                self.seek(i).await?
+            }
+            (Some("e" | "end"), None) => self.play_till_end().await?,
+            (Some("q" | "quit" | "exit"), None) => return Ok(false),
+            (Some("h" | "help"), None) => {
+                *out = help_msg();
+                return Ok(true);
+            }
+            (Some("l" | "list"), None) => {
+                *out = self.list();
+                return Ok(true);
+            }
+            (Some("s" | "slots"), None) => {
+                *out = self.show_slots();
+                return Ok(true);
+            }
+            (Some("g" | "gas"), None) => {
+                *out = self.show_gas();
+                return Ok(true);
+            }
+            (Some("r" | "run"), Some(run)) => {
+                *out = match run.parse() {
+                    Ok(run) => {
+                        self.select(self.program, run).await?;
+                        format!("Reading from the {} state", run)
+                    }
+                    Err(e) => e.to_string(),
+                };
+                return Ok(true);
+            }
+            (Some("pr" | "program"), Some(i)) => {
+                *out = match i.parse() {
+                    Ok(i) if i < self.predicate.state_read.len() => {
+                        self.select(i, self.run).await?;
+                        format!("Debugging state read program {}", i)
+                    }
+                    _ => format!("No state read program {}", i),
+                };
+                return Ok(true);
+            }
+            _ => {
+                *out = format!("Unknown command: {}", command);
+                return Ok(true);
+            }
+        };
+        *out = self.to_string();
+        match outcome {
+            TraceOutcome::Step => (),
+            TraceOutcome::ProgramEnd => out.push_str("\nProgram ended"),
+            TraceOutcome::Panic(e) => *out = format!("Program panic: {}\n{}", e, out),
+            TraceOutcome::OutOfGas(e) => *out = format!("Program ran {}\n{}", e, out),
+        }
+        Ok(true)
+    }
+}
+
+impl Snapshot for VmCheckpoint {
+    fn pos(&self) -> usize {
+        self.pos
+    }
+
+    fn size(&self) -> usize {
+        let words =
+            self.stack.len() + self.memory.len() + self.slots.iter().map(Vec::len).sum::<usize>();
+        std::mem::size_of::<Self>()
+            + words * std::mem::size_of::<Word>()
+            + self.slots.len() * std::mem::size_of::<Vec<Word>>()
+    }
+}
+
+/// The predicate with only the state read programs before `program`.
+fn programs_before(predicate: &Predicate, program: usize) -> Predicate {
+    Predicate {
+        state_read: predicate.state_read[..program.min(predicate.state_read.len())].to_vec(),
+        ..predicate.clone()
+    }
+}
+
+impl Display for StateReadDebugger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(op) = &self.last_op {
+            writeln!(f, "Op: {:?}", op)?;
+        }
+        writeln!(
+            f,
+            "  ├── {:?}\n  ├── {:?}\n  └── Slots({:?})",
+            self.vm.stack,
+            self.vm.temp_memory,
+            &self.vm.state_slots_mut[..]
+        )
+    }
+}
+
+impl Display for StateRun {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StateRun::Pre => write!(f, "pre"),
+            StateRun::Post => write!(f, "post"),
+        }
+    }
+}
+
+impl FromStr for StateRun {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pre" => Ok(StateRun::Pre),
+            "post" => Ok(StateRun::Post),
+            _ => bail!("Unknown run `{}`. Expected pre or post", s),
+        }
+    }
+}
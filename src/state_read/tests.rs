@@ -0,0 +1,185 @@
+use super::*;
+use essential_state_asm as asm;
+use essential_types::{
+    predicate::Directive,
+    solution::{Mutation, SolutionData},
+    PredicateAddress,
+};
+
+/// Reads one word at `[0, 0, 0, key]` into a new slot.
+fn read_key(key: Word) -> Vec<u8> {
+    asm::to_bytes([
+        asm::Stack::Push(1).into(),
+        asm::StateSlots::AllocSlots.into(),
+        asm::Stack::Push(0).into(),
+        asm::Stack::Push(0).into(),
+        asm::Stack::Push(0).into(),
+        asm::Stack::Push(key).into(),
+        asm::Stack::Push(4).into(),
+        asm::Stack::Push(1).into(),
+        asm::Stack::Push(0).into(),
+        asm::StateRead::KeyRange,
+        asm::TotalControlFlow::Halt.into(),
+    ])
+    .collect()
+}
+
+async fn debugger() -> StateReadDebugger {
+    debugger_with(vec![
+        read_key(0),
+        read_key(1),
+        asm::to_bytes([asm::Stack::Pop.into()]).collect(),
+    ])
+    .await
+}
+
+async fn debugger_with(state_read: Vec<Vec<u8>>) -> StateReadDebugger {
+    let predicate = Predicate {
+        state_read,
+        constraints: vec![],
+        directive: Directive::Satisfy,
+    };
+    let contract = ContentAddress([0; 32]);
+    let solution = Solution {
+        data: vec![SolutionData {
+            predicate_to_solve: PredicateAddress {
+                contract: contract.clone(),
+                predicate: ContentAddress([0; 32]),
+            },
+            decision_variables: vec![],
+            state_mutations: vec![Mutation {
+                key: vec![0, 0, 0, 0],
+                value: vec![42],
+            }],
+            transient_data: vec![],
+        }],
+    };
+    let state = HashMap::from([(
+        contract,
+        BTreeMap::from([(vec![0, 0, 0, 0], vec![7]), (vec![0, 0, 0, 1], vec![8])]),
+    )]);
+    StateReadDebugger::new(solution, 0, predicate, 0, state, Default::default())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_step() {
+    let mut debugger = debugger().await;
+    assert_eq!(debugger.op(), Some(asm::Stack::Push(1).into()));
+    assert_eq!(debugger.outcome(), None);
+
+    assert_eq!(debugger.step().await.unwrap(), TraceOutcome::Step);
+    assert_eq!(debugger.stack(), &[1]);
+    assert_eq!(debugger.step().await.unwrap(), TraceOutcome::Step);
+    assert_eq!(debugger.slots(), &[Vec::<Word>::new()]);
+    assert_eq!(debugger.stack(), &[] as &[Word]);
+
+    assert_eq!(debugger.seek(9).await.unwrap(), TraceOutcome::Step);
+    assert_eq!(debugger.pc(), 9);
+    assert_eq!(debugger.stack(), &[0, 0, 0, 0, 4, 1, 0]);
+
+    // The key range is read within a single step.
+    assert_eq!(debugger.step().await.unwrap(), TraceOutcome::Step);
+    assert_eq!(debugger.last_op(), Some(asm::StateRead::KeyRange));
+    assert_eq!(debugger.slots(), &[vec![7]]);
+    assert_eq!(debugger.stack(), &[] as &[Word]);
+
+    assert_eq!(debugger.back().await.unwrap(), TraceOutcome::Step);
+    assert_eq!(debugger.pos(), 9);
+    assert_eq!(debugger.slots(), &[Vec::<Word>::new()]);
+
+    assert_eq!(
+        debugger.play_till_end().await.unwrap(),
+        TraceOutcome::ProgramEnd
+    );
+    assert_eq!(debugger.op(), None);
+    assert_eq!(debugger.step().await.unwrap(), TraceOutcome::ProgramEnd);
+    assert_eq!(debugger.pos(), 11);
+}
+
+#[tokio::test]
+async fn test_seek_checkpoints() {
+    let ops = (0..40)
+        .map(|i| asm::Stack::Push(i).into())
+        .chain([asm::TotalControlFlow::Halt.into()]);
+    let mut debugger = debugger_with(vec![asm::to_bytes(ops).collect()]).await;
+
+    assert_eq!(debugger.seek(40).await.unwrap(), TraceOutcome::Step);
+    assert_eq!(debugger.checkpoints.len(), 2);
+    assert_eq!(debugger.checkpoints.nearest(39).unwrap().pos, 32);
+
+    assert_eq!(debugger.back().await.unwrap(), TraceOutcome::Step);
+    assert_eq!(debugger.pos(), 39);
+    assert_eq!(debugger.stack(), (0..39).collect::<Vec<_>>());
+    assert_eq!(debugger.last_op(), Some(asm::Stack::Push(38).into()));
+
+    // Going back from the end restores a checkpoint too.
+    assert_eq!(
+        debugger.play_till_end().await.unwrap(),
+        TraceOutcome::ProgramEnd
+    );
+    assert_eq!(debugger.seek(20).await.unwrap(), TraceOutcome::Step);
+    assert_eq!(debugger.pos(), 20);
+    assert_eq!(debugger.stack(), (0..20).collect::<Vec<_>>());
+    assert_eq!(debugger.outcome(), Some(&TraceOutcome::Step));
+}
+
+#[tokio::test]
+async fn test_runs() {
+    let mut debugger = debugger().await;
+    debugger.select(0, StateRun::Post).await.unwrap();
+    debugger.play_till_end().await.unwrap();
+    assert_eq!(debugger.slots(), &[vec![42]]);
+
+    // Later programs see the slots read before them.
+    debugger.select(1, StateRun::Pre).await.unwrap();
+    assert_eq!(debugger.earlier_slots(), (&[vec![7]][..], &[vec![42]][..]));
+    debugger.play_till_end().await.unwrap();
+    assert_eq!(debugger.slots(), &[vec![8]]);
+
+    debugger.select(1, StateRun::Post).await.unwrap();
+    assert_eq!(
+        debugger.earlier_slots(),
+        (&[vec![7], vec![8]][..], &[vec![42]][..])
+    );
+
+    debugger.select(2, StateRun::Pre).await.unwrap();
+    let TraceOutcome::Panic(e) = debugger.step().await.unwrap() else {
+        panic!("Expected a panic");
+    };
+    assert!(e.contains("attempted to pop an empty stack"), "{}", e);
+    assert!(debugger.select(3, StateRun::Pre).await.is_err());
+}
+
+#[tokio::test]
+async fn test_commands() {
+    let mut debugger = debugger().await;
+    let mut out = String::new();
+    assert!(debugger.execute("p 10", &mut out).await.unwrap());
+    assert_eq!(
+        out,
+        "Op: KeyRange\n  ├── Stack([])\n  ├── Memory([])\n  └── Slots([[7]])\n"
+    );
+    assert!(debugger.execute("e", &mut out).await.unwrap());
+    assert!(out.ends_with("\nProgram ended"));
+
+    assert!(debugger.execute("run post", &mut out).await.unwrap());
+    assert_eq!(out, "Reading from the post state");
+    assert_eq!(
+        debugger.prompt(),
+        format!("{}::state_read[0]::post", PROMPT)
+    );
+    assert!(debugger.execute("run mid", &mut out).await.unwrap());
+    assert_eq!(out, "Unknown run `mid`. Expected pre or post");
+
+    assert!(debugger.execute("program 1", &mut out).await.unwrap());
+    assert!(debugger.execute("s", &mut out).await.unwrap());
+    assert_eq!(
+        out,
+        "Pre state slots: [[7], [8]]\nPost state slots: [[42]]\nSlots being read: []"
+    );
+    assert!(debugger.execute("program 5", &mut out).await.unwrap());
+    assert_eq!(out, "No state read program 5");
+    assert!(!debugger.execute("q", &mut out).await.unwrap());
+}
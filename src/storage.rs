@@ -0,0 +1,103 @@
+use essential_types::{Key, Value, Word};
+
+use crate::{
+    abi::{TypeAbi, VarAbi},
+    parse_types::Type,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Decode a key into a storage path such as `storage::balances[0x8BD1..]`
+/// using the storage layout from the ABI.
+///
+/// The first word of the key is the index of the storage variable.
+/// Map keys follow as the words of the key type. Tuple fields and
+/// array elements that are stored under their own key follow as an index.
+///
+/// Returns the path and the type of the value if it is known.
+pub fn decode_key(storage: &[VarAbi], key: &[Word]) -> Option<(String, Option<Type>)> {
+    let (i, rest) = key.split_first()?;
+    let var = storage.get(usize::try_from(*i).ok()?)?;
+    let mut path = format!("storage::{}", var.name.trim_start_matches("::"));
+    let ty = walk(&mut path, &var.ty, rest)?;
+    Some((path, ty))
+}
+
+fn walk(path: &mut String, ty: &TypeAbi, key: &[Word]) -> Option<Option<Type>> {
+    match ty {
+        TypeAbi::Map { ty_from, ty_to } => {
+            let from = ty_from.to_type()?;
+            let size = from.size().ok()?;
+            if key.len() < size {
+                return None;
+            }
+            let (k, rest) = key.split_at(size);
+            path.push_str(&format!("[{}]", show_key(&from, k)?));
+            walk(path, ty_to, rest)
+        }
+        _ if key.is_empty() => Some(ty.to_type()),
+        TypeAbi::Tuple(fields) => {
+            let (i, rest) = key.split_first()?;
+            let field = fields.get(usize::try_from(*i).ok()?)?;
+            match &field.name {
+                Some(name) => path.push_str(&format!(".{}", name)),
+                None => path.push_str(&format!(".{}", i)),
+            }
+            walk(path, &field.ty, rest)
+        }
+        TypeAbi::Array { ty, size } => {
+            let (i, rest) = key.split_first()?;
+            if *i < 0 || *i >= *size {
+                return None;
+            }
+            path.push_str(&format!("[{}]", i));
+            walk(path, ty, rest)
+        }
+        _ => None,
+    }
+}
+
+/// Map keys of type `b256` are shown as `0x` prefixed hex.
+fn show_key(ty: &Type, words: &[Word]) -> Option<String> {
+    let key = ty.decode(words, false).ok()?;
+    match ty {
+        Type::B256 => Some(format!("0x{}", key)),
+        _ => Some(key),
+    }
+}
+
+/// Show a key and value with its storage path and typed value if possible.
+///
+/// e.g. `storage::balances[0x8BD1..] = 42`
+pub fn show_entry(storage: &[VarAbi], key: &Key, value: &Value) -> String {
+    if value.is_empty() {
+        return match decode_key(storage, key) {
+            Some((path, _)) => format!("{} deleted", path),
+            None => format!("{:?} deleted", key),
+        };
+    }
+    match decode_key(storage, key) {
+        Some((path, Some(ty))) => match ty.decode(value, false) {
+            Ok(value) => format!("{}: {} = {}", path, ty, value),
+            Err(_) => format!("{}: {} = {:?}", path, ty, value),
+        },
+        Some((path, None)) => format!("{} = {:?}", path, value),
+        None => format!("{:?} = {:?}", key, value),
+    }
+}
+
+/// Show each entry on its own line.
+pub fn show_entries<'a>(
+    storage: &[VarAbi],
+    entries: impl IntoIterator<Item = (&'a Key, &'a Value)>,
+) -> String {
+    let lines = entries
+        .into_iter()
+        .map(|(key, value)| show_entry(storage, key, value))
+        .collect::<Vec<_>>();
+    if lines.is_empty() {
+        return "Empty".to_string();
+    }
+    lines.join("\n")
+}
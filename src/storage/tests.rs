@@ -0,0 +1,81 @@
+use super::*;
+use crate::abi::parse_abi;
+
+fn storage() -> Vec<VarAbi> {
+    parse_abi(
+        br#"{
+        "predicates": [],
+        "storage": [
+            { "name": "nonce", "ty": "Int" },
+            { "name": "balances", "ty": { "Map": { "ty_from": "B256", "ty_to": "Int" } } },
+            { "name": "allowed", "ty": { "Map": { "ty_from": "Int", "ty_to":
+                { "Map": { "ty_from": "Int", "ty_to": "Bool" } } } } },
+            { "name": "pair", "ty": { "Tuple": [
+                { "name": "a", "ty": "Int" },
+                { "name": null, "ty": "B256" }
+            ] } },
+            { "name": "ids", "ty": { "Array": { "ty": "Int", "size": 2 } } }
+        ]
+    }"#,
+    )
+    .unwrap()
+    .storage
+}
+
+#[test]
+fn test_decode_key() {
+    let storage = storage();
+    assert_eq!(
+        decode_key(&storage, &[0]),
+        Some(("storage::nonce".to_string(), Some(Type::Int)))
+    );
+    assert_eq!(
+        decode_key(&storage, &[1, 0, 0, 0, 0x2A]),
+        Some((
+            format!("storage::balances[0x{:064X}]", 0x2A),
+            Some(Type::Int)
+        ))
+    );
+    assert_eq!(
+        decode_key(&storage, &[2, 3, 4]).unwrap().0,
+        "storage::allowed[3][4]"
+    );
+    assert_eq!(decode_key(&storage, &[3, 0]).unwrap().0, "storage::pair.a");
+    assert_eq!(
+        decode_key(&storage, &[3, 1]),
+        Some(("storage::pair.1".to_string(), Some(Type::B256)))
+    );
+    assert_eq!(decode_key(&storage, &[4, 1]).unwrap().0, "storage::ids[1]");
+    assert_eq!(
+        decode_key(&storage, &[4]).unwrap().1.unwrap().to_string(),
+        "int[2]"
+    );
+
+    // Not part of the layout.
+    assert_eq!(decode_key(&storage, &[]), None);
+    assert_eq!(decode_key(&storage, &[5]), None);
+    assert_eq!(decode_key(&storage, &[0, 1]), None);
+    assert_eq!(decode_key(&storage, &[1, 0, 0]), None);
+    assert_eq!(decode_key(&storage, &[3, 2]), None);
+    assert_eq!(decode_key(&storage, &[4, 2]), None);
+}
+
+#[test]
+fn test_show_entry() {
+    let storage = storage();
+    assert_eq!(
+        show_entry(&storage, &vec![0], &vec![7]),
+        "storage::nonce: int = 7"
+    );
+    assert_eq!(
+        show_entry(&storage, &vec![2, 1, 2], &vec![3]),
+        "storage::allowed[1][2]: bool = [3]"
+    );
+    assert_eq!(
+        show_entry(&storage, &vec![0], &vec![]),
+        "storage::nonce deleted"
+    );
+    assert_eq!(show_entry(&storage, &vec![9], &vec![1]), "[9] = [1]");
+    assert_eq!(show_entry(&[], &vec![0], &vec![1]), "[0] = [1]");
+    assert_eq!(show_entries(&storage, []), "Empty");
+}
@@ -0,0 +1,145 @@
+use std::io::Write;
+
+use essential_constraint_asm::Op;
+use essential_types::Word;
+use serde::Serialize;
+
+use crate::{Gas, OutOfGas, Outcome};
+
+#[cfg(test)]
+mod tests;
+
+/// Records every step played in a session.
+#[derive(Debug, Default, Clone)]
+pub struct Trace {
+    enabled: bool,
+    steps: Vec<TraceStep>,
+}
+
+/// A single step of execution.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TraceStep {
+    /// Position after this step. The first op played is position 1.
+    pub pos: usize,
+    /// Program counter of the op.
+    pub pc: usize,
+    pub op: String,
+    pub stack_before: Vec<Word>,
+    pub stack_after: Vec<Word>,
+    pub memory_changes: Vec<MemoryChange>,
+    /// Repeat counters from the outermost to innermost loop after this step.
+    pub repeat: Vec<Word>,
+    /// Gas charged for the op.
+    pub gas: Gas,
+    /// Gas spent by the constraint after this step.
+    pub gas_spent: Gas,
+    pub outcome: TraceOutcome,
+}
+
+/// A word in memory that was changed by a step.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MemoryChange {
+    pub index: usize,
+    pub before: Option<Word>,
+    pub after: Option<Word>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TraceOutcome {
+    Step,
+    ProgramEnd,
+    Panic(String),
+    OutOfGas(OutOfGas),
+}
+
+/// The state of the VM before a step.
+pub(crate) struct Before {
+    pub pc: usize,
+    pub stack: Vec<Word>,
+    pub memory: Vec<Word>,
+}
+
+/// The state of the VM after a step.
+pub(crate) struct After<'a> {
+    pub pos: usize,
+    pub op: Op,
+    pub stack: &'a [Word],
+    pub memory: &'a [Word],
+    pub repeat: Vec<Word>,
+    pub gas: Gas,
+    pub gas_spent: Gas,
+    pub outcome: &'a Outcome,
+}
+
+impl Trace {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn steps(&self) -> &[TraceStep] {
+        &self.steps
+    }
+
+    pub fn clear(&mut self) {
+        self.steps.clear();
+    }
+
+    /// Record a step.
+    ///
+    /// Steps at or after this position are replaced so the trace
+    /// always follows the current execution when stepping back.
+    pub(crate) fn record(&mut self, before: Before, after: After) {
+        while self.steps.last().is_some_and(|s| s.pos >= after.pos) {
+            self.steps.pop();
+        }
+        let len = before.memory.len().max(after.memory.len());
+        let memory_changes = (0..len)
+            .filter_map(|index| {
+                let b = before.memory.get(index).copied();
+                let a = after.memory.get(index).copied();
+                (b != a).then_some(MemoryChange {
+                    index,
+                    before: b,
+                    after: a,
+                })
+            })
+            .collect();
+        self.steps.push(TraceStep {
+            pos: after.pos,
+            pc: before.pc,
+            op: format!("{:?}", after.op),
+            stack_before: before.stack,
+            stack_after: after.stack.to_vec(),
+            memory_changes,
+            repeat: after.repeat,
+            gas: after.gas,
+            gas_spent: after.gas_spent,
+            outcome: after.outcome.into(),
+        });
+    }
+
+    /// Write the trace as JSON Lines, one step per line.
+    pub fn write_jsonl(&self, mut w: impl Write) -> anyhow::Result<()> {
+        for step in &self.steps {
+            serde_json::to_writer(&mut w, step)?;
+            writeln!(w)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<&Outcome> for TraceOutcome {
+    fn from(outcome: &Outcome) -> Self {
+        match outcome {
+            Outcome::Step => TraceOutcome::Step,
+            Outcome::ProgramEnd => TraceOutcome::ProgramEnd,
+            Outcome::Panic(e) => TraceOutcome::Panic(format!("{:?}", e)),
+            Outcome::OutOfGas(e) => TraceOutcome::OutOfGas(e.clone()),
+        }
+    }
+}
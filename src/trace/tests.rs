@@ -0,0 +1,67 @@
+use super::*;
+use essential_constraint_asm as asm;
+
+fn step(trace: &mut Trace, pos: usize, stack: &[Word], memory: &[Word]) {
+    trace.record(
+        Before {
+            pc: pos - 1,
+            stack: vec![1],
+            memory: vec![1, 2],
+        },
+        After {
+            pos,
+            op: asm::Stack::Push(2).into(),
+            stack,
+            memory,
+            repeat: vec![3],
+            gas: 1,
+            gas_spent: pos as Gas,
+            outcome: &Outcome::Step,
+        },
+    );
+}
+
+#[test]
+fn test_record() {
+    let mut trace = Trace::default();
+    step(&mut trace, 1, &[1, 2], &[1, 5, 6]);
+    assert_eq!(
+        trace.steps()[0].memory_changes,
+        vec![
+            MemoryChange {
+                index: 1,
+                before: Some(2),
+                after: Some(5)
+            },
+            MemoryChange {
+                index: 2,
+                before: None,
+                after: Some(6)
+            },
+        ]
+    );
+
+    step(&mut trace, 2, &[1, 2], &[1, 2]);
+    step(&mut trace, 3, &[1, 2], &[1, 2]);
+    // Stepping back and replaying replaces the later steps.
+    step(&mut trace, 2, &[7], &[1, 2]);
+    assert_eq!(trace.steps().len(), 2);
+    assert_eq!(trace.steps()[1].stack_after, vec![7]);
+}
+
+#[test]
+fn test_write_jsonl() {
+    let mut trace = Trace::default();
+    step(&mut trace, 1, &[1, 2], &[1, 2]);
+    step(&mut trace, 2, &[1, 2], &[1, 2]);
+    let mut out = Vec::new();
+    trace.write_jsonl(&mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    let mut lines = out.lines();
+    assert_eq!(
+        lines.next().unwrap(),
+        r#"{"pos":1,"pc":0,"op":"Stack(Push(2))","stack_before":[1],"stack_after":[1,2],"memory_changes":[],"repeat":[3],"gas":1,"gas_spent":1,"outcome":"step"}"#
+    );
+    assert!(lines.next().is_some());
+    assert!(lines.next().is_none());
+}
@@ -0,0 +1,321 @@
+use essential_constraint_vm::{OpAccess, StateSlotSlice};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Paragraph},
+    DefaultTerminal, Frame,
+};
+
+use crate::{
+    breakpoint::BreakpointKind, execute, help_msg, memory_words, Outcome, Session, Source,
+};
+
+#[cfg(test)]
+mod tests;
+
+const KEYS: &str = "n next  b back  c continue  e end  r restart  : command  ? help  q quit";
+
+/// What key presses are read as.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+enum Mode {
+    #[default]
+    Keys,
+    /// A debugger command is being typed after `:`.
+    Command(String),
+}
+
+#[derive(Debug, Default)]
+struct App {
+    mode: Mode,
+    /// The result of the last key or command, shown in the status bar.
+    status: String,
+    /// The output of the last command, shown in its own pane.
+    output: String,
+}
+
+/// Run the full screen UI until the user quits.
+pub(crate) fn run(session: &mut Session, source: &Option<Source>) -> anyhow::Result<()> {
+    let mut terminal = ratatui::init();
+    let result = run_app(&mut terminal, session, source);
+    ratatui::restore();
+    result
+}
+
+fn run_app(
+    terminal: &mut DefaultTerminal,
+    session: &mut Session,
+    source: &Option<Source>,
+) -> anyhow::Result<()> {
+    let mut app = App::default();
+    loop {
+        terminal.draw(|frame| draw(frame, session, source, &app))?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match app.handle_key(session, source, key) {
+            Ok(true) => (),
+            Ok(false) => return Ok(()),
+            // Keep the session open so the user can carry on from here.
+            Err(e) => app.status = format!("Error: {}", e),
+        }
+    }
+}
+
+impl App {
+    /// Handle a key press. Returns false if the user quit.
+    fn handle_key(
+        &mut self,
+        session: &mut Session,
+        source: &Option<Source>,
+        key: KeyEvent,
+    ) -> anyhow::Result<bool> {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Ok(false);
+        }
+        if let Mode::Command(command) = &mut self.mode {
+            match key.code {
+                KeyCode::Char(c) => command.push(c),
+                KeyCode::Backspace => {
+                    command.pop();
+                }
+                KeyCode::Esc => self.mode = Mode::Keys,
+                KeyCode::Enter => {
+                    let command = command.trim().to_string();
+                    self.mode = Mode::Keys;
+                    let mut out = String::new();
+                    if !execute(session, source, &command, &mut out, None)? {
+                        return Ok(false);
+                    }
+                    self.output = dialoguer::console::strip_ansi_codes(&out).into_owned();
+                    self.status = format!("Ran `{}`", command);
+                }
+                _ => (),
+            }
+            return Ok(true);
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+            KeyCode::Char('n') | KeyCode::Right => {
+                let outcome = session.step_forward()?;
+                self.status = status(&outcome);
+            }
+            KeyCode::Char('b') | KeyCode::Left => {
+                let outcome = session.seek(session.pos.saturating_sub(1))?;
+                self.status = status(&outcome);
+            }
+            KeyCode::Char('c') => {
+                self.status = match session.run_to_breakpoint()? {
+                    (_, Some(id)) => {
+                        let bp = session.breakpoints.get(id).expect("Breakpoint was hit");
+                        format!("Breakpoint {} hit at {}", id, bp)
+                    }
+                    (outcome, None) => status(&outcome),
+                };
+            }
+            KeyCode::Char('e') => {
+                self.status = format!("Program ended: {}", session.play_till_end()?);
+            }
+            KeyCode::Char('r') => {
+                session.reset_session();
+                *session.last_op = None;
+                self.status = "Restarted".to_string();
+            }
+            KeyCode::Char(':') => self.mode = Mode::Command(String::new()),
+            KeyCode::Char('?') => {
+                self.output = help_msg();
+                self.status = format!("Keys: {}", KEYS);
+            }
+            _ => (),
+        }
+        Ok(true)
+    }
+}
+
+fn status(outcome: &Outcome) -> String {
+    match outcome {
+        Outcome::Step => String::new(),
+        Outcome::ProgramEnd => "Program ended".to_string(),
+        Outcome::Panic(e) => format!("Program panic: {:?}", e),
+        Outcome::OutOfGas(e) => format!("Program ran {}", e),
+    }
+}
+
+fn draw(frame: &mut Frame, session: &mut Session, source: &Option<Source>, app: &App) {
+    let [main, bar] =
+        Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+    let [top, middle, bottom] = Layout::vertical([
+        Constraint::Percentage(50),
+        Constraint::Percentage(25),
+        Constraint::Percentage(25),
+    ])
+    .areas(main);
+    let [ops, code] =
+        Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)]).areas(top);
+    let [stack, memory, repeat] = Layout::horizontal([Constraint::Ratio(1, 3); 3]).areas(middle);
+    let [pre, post, output] = Layout::horizontal([Constraint::Ratio(1, 3); 3]).areas(bottom);
+
+    frame.render_widget(ops_pane(session, ops), ops);
+    frame.render_widget(source_pane(source, *session.pc, code), code);
+    frame.render_widget(
+        words_pane(
+            format!("Stack ({})", session.stack.len()),
+            // The top of the stack comes first.
+            session.stack.iter().copied().enumerate().rev(),
+        ),
+        stack,
+    );
+    let words = memory_words(session.memory);
+    frame.render_widget(
+        words_pane(
+            format!("Memory ({})", words.len()),
+            words.into_iter().enumerate(),
+        ),
+        memory,
+    );
+    frame.render_widget(repeat_pane(session), repeat);
+    frame.render_widget(slots_pane("Pre state", session.pre), pre);
+    frame.render_widget(slots_pane("Post state", session.post), post);
+    frame.render_widget(
+        Paragraph::new(app.output.as_str()).block(Block::bordered().title("Output")),
+        output,
+    );
+
+    match &app.mode {
+        Mode::Command(command) => {
+            frame.render_widget(Paragraph::new(format!(":{}", command)), bar);
+            frame.set_cursor_position((bar.x + 1 + command.chars().count() as u16, bar.y));
+        }
+        Mode::Keys if app.status.is_empty() => {
+            frame.render_widget(Paragraph::new(KEYS.dark_gray()), bar)
+        }
+        Mode::Keys => frame.render_widget(Paragraph::new(app.status.as_str().bold()), bar),
+    }
+}
+
+/// The ops around the program counter, marking the next op and breakpoints.
+fn ops_pane(session: &Session, area: Rect) -> Paragraph<'static> {
+    let pc = *session.pc;
+    let height = area.height.saturating_sub(2) as usize;
+    let start = pc.saturating_sub(height / 2);
+    let breaks = session
+        .breakpoints
+        .iter()
+        .filter(|(_, bp)| bp.enabled)
+        .filter_map(|(_, bp)| match bp.kind {
+            BreakpointKind::Pc(pc) => Some(pc),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let lines = session
+        .code
+        .ops_from(start)
+        .map(|ops| {
+            ops.ops()
+                .take(height)
+                .enumerate()
+                .map(|(i, op)| {
+                    let i = start + i;
+                    let marker = match (i == pc, breaks.contains(&i)) {
+                        (true, _) => ">",
+                        (false, true) => "*",
+                        (false, false) => " ",
+                    };
+                    let line = Line::from(format!("{} {:>4}: {:?}", marker, i, op));
+                    if i == pc {
+                        line.cyan().bold()
+                    } else {
+                        line
+                    }
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let title = match (&*session.code).op_access(pc) {
+        Some(_) => format!("Ops (pos {}, pc {})", session.pos, pc),
+        None => format!("Ops (pos {}, ended)", session.pos),
+    };
+    Paragraph::new(lines).block(Block::bordered().title(title))
+}
+
+/// The predicate source scrolled to the expression of the op at `pc`.
+fn source_pane<'a>(source: &'a Option<Source>, pc: usize, area: Rect) -> Paragraph<'a> {
+    let block = Block::bordered().title("Source");
+    let Some(source) = source else {
+        return Paragraph::new("No source code available.").block(block);
+    };
+    let block = match &source.path {
+        Some(path) => block.title(format!("Source ({})", path.display())),
+        None => block,
+    };
+    let lines = source
+        .code_lines(pc)
+        .into_iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let base = if line.is_constraint {
+                Style::new().cyan()
+            } else {
+                Style::new()
+            };
+            let mut spans = vec![Span::raw(format!("{:>4} ", i + 1)).dark_gray()];
+            match line.highlight {
+                Some(h) => {
+                    spans.push(Span::styled(&line.text[..h.start], base));
+                    spans.push(
+                        Span::raw(&line.text[h.clone()])
+                            .yellow()
+                            .bold()
+                            .underlined(),
+                    );
+                    spans.push(Span::styled(&line.text[h.end..], base));
+                }
+                None => spans.push(Span::styled(line.text, base)),
+            }
+            Line::from(spans)
+        })
+        .collect::<Vec<_>>();
+    let height = area.height.saturating_sub(2) as usize;
+    let line = source.location(pc).map_or(0, |(line, _)| line);
+    let scroll = line.saturating_sub(height / 2) as u16;
+    Paragraph::new(lines).block(block).scroll((scroll, 0))
+}
+
+fn words_pane(
+    title: String,
+    words: impl Iterator<Item = (usize, essential_types::Word)>,
+) -> Paragraph<'static> {
+    let lines = words
+        .map(|(i, word)| Line::from(format!("{:>4}: {}", i, word)))
+        .collect::<Vec<_>>();
+    Paragraph::new(lines).block(Block::bordered().title(title))
+}
+
+/// The counters of the loops being repeated, innermost first.
+fn repeat_pane(session: &Session) -> Paragraph<'static> {
+    let lines = session
+        .repeat
+        .iter()
+        .rev()
+        .map(|slot| {
+            Line::from(match slot.limit {
+                Some(limit) => format!("pc {:>4}: {} / {}", slot.repeat_index, slot.counter, limit),
+                None => format!("pc {:>4}: {} left", slot.repeat_index, slot.counter),
+            })
+        })
+        .collect::<Vec<_>>();
+    Paragraph::new(lines).block(Block::bordered().title("Repeat"))
+}
+
+fn slots_pane<'a>(title: &'a str, slots: &StateSlotSlice) -> Paragraph<'a> {
+    let lines = slots
+        .iter()
+        .enumerate()
+        .map(|(i, slot)| Line::from(format!("{:>4}: {:?}", i, slot)))
+        .collect::<Vec<_>>();
+    Paragraph::new(lines).block(Block::bordered().title(title))
+}
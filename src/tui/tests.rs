@@ -0,0 +1,187 @@
+use super::*;
+use crate::{source::SourceMap, ConstraintDebugger, Span};
+use essential_constraint_asm as asm;
+use essential_types::{
+    predicate::{Directive, Predicate},
+    solution::{Solution, SolutionData},
+    ContentAddress, PredicateAddress,
+};
+use ratatui::{backend::TestBackend, Terminal};
+
+const PREDICATE: &str = "predicate ::P {\n    constraint 3 + 2 == 5;\n}\n";
+
+async fn debugger() -> ConstraintDebugger {
+    let predicate = Predicate {
+        state_read: vec![],
+        constraints: vec![asm::to_bytes([
+            asm::Stack::Push(3).into(),
+            asm::Stack::Push(2).into(),
+            asm::Stack::Push(1).into(),
+            asm::Stack::Repeat.into(),
+            asm::Stack::Push(0).into(),
+            asm::Alu::Add.into(),
+            asm::Stack::RepeatEnd.into(),
+            asm::Stack::Push(2).into(),
+            asm::Alu::Add.into(),
+            asm::Stack::Push(5).into(),
+            asm::Pred::Eq.into(),
+        ])
+        .collect()],
+        directive: Directive::Satisfy,
+    };
+    let solution = Solution {
+        data: vec![SolutionData {
+            predicate_to_solve: PredicateAddress {
+                contract: ContentAddress([0; 32]),
+                predicate: ContentAddress([0; 32]),
+            },
+            decision_variables: vec![vec![4, 5]],
+            state_mutations: vec![],
+            transient_data: vec![],
+        }],
+    };
+    ConstraintDebugger::new(solution, 0, predicate, 0, Default::default())
+        .await
+        .unwrap()
+}
+
+fn source() -> Option<Source> {
+    let start = PREDICATE.find("3 + 2").unwrap();
+    let spans = vec![Some(Span {
+        start,
+        end: start + 5,
+    })];
+    Some(
+        Source::default()
+            .with_predicate_find_line(PREDICATE, 0)
+            .with_source_map(
+                &SourceMap {
+                    constraints: vec![spans],
+                },
+                0,
+            ),
+    )
+}
+
+/// Draw the UI and return the rendered lines.
+fn render(session: &mut Session, source: &Option<Source>, app: &App) -> Vec<String> {
+    let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+    terminal
+        .draw(|frame| draw(frame, session, source, app))
+        .unwrap();
+    let buffer = terminal.backend().buffer();
+    (0..buffer.area.height)
+        .map(|y| {
+            (0..buffer.area.width)
+                .map(|x| buffer[(x, y)].symbol())
+                .collect::<String>()
+        })
+        .collect()
+}
+
+fn press(app: &mut App, session: &mut Session, source: &Option<Source>, keys: &str) -> bool {
+    keys.chars().all(|c| {
+        let code = match c {
+            '\n' => KeyCode::Enter,
+            c => KeyCode::Char(c),
+        };
+        app.handle_key(session, source, KeyEvent::from(code))
+            .unwrap()
+    })
+}
+
+fn contains(lines: &[String], s: &str) -> bool {
+    lines.iter().any(|line| line.contains(s))
+}
+
+#[tokio::test]
+async fn test_panes() {
+    let mut debugger = debugger().await;
+    let mut session = debugger.start_session();
+    let source = source();
+    let mut app = App::default();
+
+    let lines = render(&mut session, &source, &app);
+    assert!(contains(&lines, "Ops (pos 0, pc 0)"));
+    assert!(contains(&lines, ">    0: Stack(Push(3))"));
+    assert!(contains(&lines, "   2     constraint 3 + 2 == 5;"));
+    assert!(contains(&lines, "Stack (0)"));
+    assert!(contains(&lines, "Pre state"));
+    assert!(lines.last().unwrap().starts_with(KEYS));
+
+    assert!(press(&mut app, &mut session, &source, "nnnnn"));
+    let lines = render(&mut session, &source, &app);
+    assert!(contains(&lines, ">    5: Alu(Add)"));
+    assert!(contains(&lines, "Stack (2)"));
+    assert!(contains(&lines, "   1: 0"));
+    assert!(contains(&lines, "   0: 3"));
+    assert!(contains(&lines, "pc    4: 0 / 2"));
+
+    assert!(press(&mut app, &mut session, &source, "e"));
+    assert_eq!(app.status, "Program ended: pass");
+    let lines = render(&mut session, &source, &app);
+    assert!(contains(&lines, "ended)"));
+    assert!(lines.last().unwrap().starts_with("Program ended: pass"));
+}
+
+#[tokio::test]
+async fn test_keys() {
+    let mut debugger = debugger().await;
+    let mut session = debugger.start_session();
+    let source = source();
+    let mut app = App::default();
+
+    assert!(press(&mut app, &mut session, &source, "nnb"));
+    assert_eq!(*session.pos, 1);
+    assert_eq!(&session.stack[..], &[3]);
+
+    session.breakpoints.add(BreakpointKind::Pc(7));
+    let lines = render(&mut session, &source, &app);
+    assert!(contains(&lines, "*    7: Stack(Push(2))"));
+    assert!(press(&mut app, &mut session, &source, "c"));
+    assert_eq!(app.status, "Breakpoint 0 hit at pc 7");
+    assert_eq!(*session.pc, 7);
+
+    assert!(press(&mut app, &mut session, &source, "r"));
+    assert_eq!(*session.pos, 0);
+    assert_eq!(*session.last_op, None);
+
+    assert!(press(&mut app, &mut session, &source, "?"));
+    assert!(app.output.starts_with("Commands:"));
+    assert!(!press(&mut app, &mut session, &source, "q"));
+}
+
+#[tokio::test]
+async fn test_commands() {
+    let mut debugger = debugger().await;
+    let mut session = debugger.start_session();
+    let source = source();
+    let mut app = App::default();
+
+    assert!(press(&mut app, &mut session, &source, ":s dv 1"));
+    assert_eq!(app.mode, Mode::Command("s dv 1".to_string()));
+    let lines = render(&mut session, &source, &app);
+    assert_eq!(lines.last().unwrap().trim_end(), ":s dv 1");
+
+    app.handle_key(&mut session, &source, KeyEvent::from(KeyCode::Backspace))
+        .unwrap();
+    assert!(press(&mut app, &mut session, &source, "0\n"));
+    assert_eq!(app.mode, Mode::Keys);
+    assert_eq!(app.output, "Decision variable 0: [4, 5]");
+    assert_eq!(app.status, "Ran `s dv 0`");
+
+    // Styled output is shown as plain text.
+    assert!(press(&mut app, &mut session, &source, ":n\n"));
+    assert_eq!(
+        app.output,
+        "Op: Stack(Push(3))\n  ├── Stack([3])\n  └── Memory([])\n"
+    );
+
+    assert!(press(&mut app, &mut session, &source, ":n"));
+    app.handle_key(&mut session, &source, KeyEvent::from(KeyCode::Esc))
+        .unwrap();
+    assert_eq!(app.mode, Mode::Keys);
+    assert_eq!(*session.pos, 1);
+
+    assert!(!press(&mut app, &mut session, &source, ":q\n"));
+}
@@ -1,3 +1,5 @@
+use std::collections::{BTreeMap, HashMap};
+
 use essential_constraint_vm as constraint_vm;
 use essential_debugger::Source;
 use essential_sign::secp256k1::{PublicKey, Secp256k1, SecretKey};
@@ -101,9 +103,833 @@ predicate ::Foo {
         .unwrap();
 }
 
+/// Sums the repeat counter over a loop so the stack
+/// depends on the repeat state at every step.
+fn repeat_predicate() -> Predicate {
+    Predicate {
+        state_read: vec![],
+        constraints: vec![constraint_vm::asm::to_bytes([
+            constraint_vm::asm::Stack::Push(0).into(),
+            constraint_vm::asm::Stack::Push(50).into(),
+            constraint_vm::asm::Stack::Push(1).into(),
+            constraint_vm::asm::Stack::Repeat.into(),
+            constraint_vm::asm::Access::RepeatCounter.into(),
+            constraint_vm::asm::Alu::Add.into(),
+            constraint_vm::asm::Stack::RepeatEnd.into(),
+            constraint_vm::asm::Stack::Push(1225).into(),
+            constraint_vm::asm::Pred::Eq.into(),
+        ])
+        .collect()],
+        directive: Directive::Satisfy,
+    }
+}
+
+fn solution_for(predicate: &Predicate, decision_variables: Vec<Vec<i64>>) -> Solution {
+    Solution {
+        data: vec![SolutionData {
+            predicate_to_solve: PredicateAddress {
+                contract: essential_types::ContentAddress([0; 32]),
+                predicate: essential_hash::content_addr(predicate),
+            },
+            decision_variables,
+            state_mutations: vec![],
+            transient_data: vec![],
+        }],
+    }
+}
+
+#[tokio::test]
+async fn test_back_matches_replay() {
+    let predicate = repeat_predicate();
+    let solution = solution_for(&predicate, vec![]);
+
+    let mut debugger =
+        essential_debugger::ConstraintDebugger::new(solution, 0, predicate, 0, Default::default())
+            .await
+            .unwrap();
+    let mut session = debugger.start_session();
+
+    let mut out = String::new();
+    let mut forward = vec![];
+    loop {
+        session.next(&mut out).unwrap();
+        if out.contains("Program ended") {
+            break;
+        }
+        forward.push(out.clone());
+    }
+    assert!(forward.len() > 150);
+
+    for i in (1..=forward.len()).rev() {
+        session.play(i, &mut out).unwrap();
+        assert_eq!(out, forward[i - 1], "play {}", i);
+    }
+    session.play(forward.len(), &mut out).unwrap();
+    for i in (1..forward.len()).rev() {
+        session.back(&mut out).unwrap();
+        assert_eq!(out, forward[i - 1], "back to {}", i);
+    }
+    assert!(!session.checkpoints().is_empty());
+}
+
+#[tokio::test]
+async fn test_trace() {
+    let predicate = repeat_predicate();
+    let solution = solution_for(&predicate, vec![]);
+    let trace = essential_debugger::trace(
+        solution,
+        0,
+        predicate,
+        0,
+        Default::default(),
+        Default::default(),
+    )
+    .await
+    .unwrap();
+    let steps = trace.steps();
+    assert_eq!(steps[0].op, "Stack(Push(0))");
+    assert_eq!(steps[0].stack_after, vec![0]);
+    assert_eq!(steps[4].repeat, vec![0]);
+    let last = steps.last().unwrap();
+    assert_eq!(last.pos, steps.len());
+    assert_eq!(last.stack_after, vec![1]);
+
+    let mut out = Vec::new();
+    trace.write_jsonl(&mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap().lines().count(), steps.len());
+}
+
+#[tokio::test]
+async fn test_check_predicate() {
+    use essential_debugger::CheckOutcome;
+
+    let mut predicate = repeat_predicate();
+    predicate.constraints.extend([
+        constraint_vm::asm::to_bytes([constraint_vm::asm::Stack::Push(0).into()]).collect(),
+        constraint_vm::asm::to_bytes([constraint_vm::asm::Stack::Pop.into()]).collect(),
+        constraint_vm::asm::to_bytes([
+            constraint_vm::asm::Stack::Push(1).into(),
+            constraint_vm::asm::Stack::Push(2).into(),
+        ])
+        .collect(),
+    ]);
+    let solution = solution_for(&predicate, vec![]);
+    let outcomes = essential_debugger::check_predicate(
+        &solution,
+        0,
+        &predicate,
+        &Default::default(),
+        &Default::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(outcomes.len(), 4);
+    assert_eq!(outcomes[0], CheckOutcome::Pass);
+    assert_eq!(outcomes[1], CheckOutcome::False);
+    assert!(matches!(outcomes[2], CheckOutcome::Panic(_)));
+    assert_eq!(outcomes[3], CheckOutcome::UnexpectedStack(vec![1, 2]));
+}
+
+#[tokio::test]
+async fn test_debugger_script() {
+    dialoguer::console::set_colors_enabled(false);
+    let predicate = repeat_predicate();
+    let solution = solution_for(&predicate, vec![vec![7, 8]]);
+    let script = "
+# Step into the loop.
+n
+p 5
+b
+s dv 0
+t 0 int
+t dv[0] 1 int
+t post[0] int
+br pc 6 if top > 10
+co
+q
+n
+";
+    let mut output = Vec::new();
+    essential_debugger::run_script(
+        solution,
+        0,
+        predicate,
+        0,
+        Default::default(),
+        None,
+        None,
+        Default::default(),
+        script.as_bytes(),
+        &mut output,
+    )
+    .await
+    .unwrap();
+    let output = String::from_utf8(output).unwrap();
+    let expected = "\
+<essential-dbg> n
+Op: Stack(Push(0))
+  ├── Stack([0])
+  └── Memory([])
+
+<essential-dbg> p 5
+Op: Access(RepeatCounter)
+  ├── Stack([0, 0])
+  └── Memory([])
+
+<essential-dbg> b
+Op: Stack(Repeat)
+  ├── Stack([0])
+  └── Memory([])
+
+<essential-dbg> s dv 0
+Decision variable 0: [7, 8]
+<essential-dbg> t 0 int
+0
+<essential-dbg> t dv[0] 1 int
+8
+<essential-dbg> t post[0] int
+No post state slot 0
+<essential-dbg> br pc 6 if top > 10
+Breakpoint 0 set: pc 6 if top > 10
+<essential-dbg> co
+Breakpoint 0 hit at pc 6 if top > 10.
+Op: Alu(Add)
+  ├── Stack([15])
+  └── Memory([])
+
+<essential-dbg> q
+";
+    assert_eq!(output, expected);
+}
+
+#[tokio::test]
+async fn test_debugger_script_abi() {
+    dialoguer::console::set_colors_enabled(false);
+    let predicate = repeat_predicate();
+    let mut solution = solution_for(&predicate, vec![vec![5], vec![1, 0, 1]]);
+    solution.data[0].state_mutations = vec![
+        Mutation {
+            key: vec![1, 0, 0, 0, 2],
+            value: vec![40],
+        },
+        Mutation {
+            key: vec![0],
+            value: vec![],
+        },
+    ];
+    let state = HashMap::from([(
+        essential_types::ContentAddress([0; 32]),
+        BTreeMap::from([(vec![0], vec![3]), (vec![1, 0, 0, 0, 2], vec![45])]),
+    )]);
+    let abi = essential_debugger::parse_abi(
+        br#"{
+        "predicates": [{
+            "name": "::Repeat",
+            "vars": [
+                { "name": "::amount", "ty": "Int" },
+                { "name": "::flags", "ty": { "Array": { "ty": "Bool", "size": 3 } } }
+            ]
+        }],
+        "storage": [
+            { "name": "nonce", "ty": "Int" },
+            { "name": "balances", "ty": { "Map": { "ty_from": "B256", "ty_to": "Int" } } }
+        ]
+    }"#,
+    )
+    .unwrap();
+    let script = "
+s dv
+s dv amount
+t ::flags
+t dv[::amount] HEX
+t ::missing int
+s storage pre
+s storage post
+s storage mutations
+";
+    let mut output = Vec::new();
+    essential_debugger::run_script(
+        solution,
+        0,
+        predicate,
+        0,
+        state,
+        None,
+        abi.predicate("Repeat")
+            .cloned()
+            .map(|p| essential_debugger::DebugAbi::new(&abi, p)),
+        Default::default(),
+        script.as_bytes(),
+        &mut output,
+    )
+    .await
+    .unwrap();
+    let output = String::from_utf8(output).unwrap();
+    let expected = "\
+<essential-dbg> s dv
+::amount: int = 5
+::flags: bool[3] = [true, false, true]
+<essential-dbg> s dv amount
+::amount: int = 5
+<essential-dbg> t ::flags
+[true, false, true]
+<essential-dbg> t dv[::amount] HEX
+0000000000000005
+<essential-dbg> t ::missing int
+No decision variable named ::missing
+<essential-dbg> s storage pre
+storage::nonce: int = 3
+storage::balances[0x0000000000000000000000000000000000000000000000000000000000000002]: int = 45
+<essential-dbg> s storage post
+storage::balances[0x0000000000000000000000000000000000000000000000000000000000000002]: int = 40
+<essential-dbg> s storage mutations
+storage::balances[0x0000000000000000000000000000000000000000000000000000000000000002]: int = 40
+storage::nonce deleted
+";
+    assert_eq!(output, expected);
+}
+
+#[tokio::test]
+async fn test_engine() {
+    let predicate = repeat_predicate();
+    let solution = solution_for(&predicate, vec![]);
+    let mut engine = essential_debugger::Engine::new(solution, 0, predicate, 0, Default::default())
+        .await
+        .unwrap();
+    engine
+        .breakpoints_mut()
+        .add(essential_debugger::BreakpointKind::Pc(6));
+    let stop = engine.run_to_breakpoint().unwrap();
+    assert_eq!(stop.breakpoint, Some(0));
+    assert_eq!(engine.stack(), &[0]);
+    assert_eq!(
+        engine.op(),
+        Some(constraint_vm::asm::Stack::RepeatEnd.into())
+    );
+    assert_eq!(engine.repeat()[0].counter, 0);
+
+    engine.breakpoints_mut().remove(0);
+    assert_eq!(
+        engine.play_till_end().unwrap(),
+        essential_debugger::CheckOutcome::Pass
+    );
+    assert_eq!(
+        engine.last_outcome(),
+        Some(&essential_debugger::TraceOutcome::ProgramEnd)
+    );
+    assert_eq!(engine.stack(), &[1]);
+}
+
+#[tokio::test]
+async fn test_rpc() {
+    let predicate = repeat_predicate();
+    let solution = solution_for(&predicate, vec![vec![7, 8]]);
+    let input = r#"{"jsonrpc": "2.0", "id": 1, "method": "break", "params": {"pc": 6, "condition": "top > 10"}}
+{"jsonrpc": "2.0", "id": 2, "method": "continue"}
+{"jsonrpc": "2.0", "id": 3, "method": "back"}
+{"jsonrpc": "2.0", "id": 4, "method": "end"}
+{"jsonrpc": "2.0", "id": 5, "method": "quit"}
+"#;
+    let mut output = Vec::new();
+    essential_debugger::run_rpc(
+        solution,
+        0,
+        predicate,
+        0,
+        Default::default(),
+        None,
+        Default::default(),
+        input.as_bytes(),
+        &mut output,
+    )
+    .await
+    .unwrap();
+    let output = String::from_utf8(output).unwrap();
+    let responses = output
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(responses.len(), 5);
+    assert_eq!(responses[0]["result"], serde_json::json!({ "id": 0 }));
+    let r = &responses[1]["result"];
+    assert_eq!(r["breakpoint"], 0);
+    assert_eq!(r["pc"], 6);
+    assert_eq!(r["last_op"], serde_json::json!({ "opcode": "Alu::Add" }));
+    assert_eq!(r["stack"], serde_json::json!([15]));
+    assert_eq!(responses[2]["result"]["stack"], serde_json::json!([10, 5]));
+    let r = &responses[3]["result"];
+    assert_eq!(r["outcome"], "program_end");
+    assert_eq!(r["check"], "pass");
+    assert_eq!(r["stack"], serde_json::json!([1]));
+    assert_eq!(responses[4]["id"], 5);
+}
+
+#[tokio::test]
+async fn test_rpc_gas_limit() {
+    let predicate = repeat_predicate();
+    let solution = solution_for(&predicate, vec![]);
+    let input = r#"{"jsonrpc": "2.0", "id": 1, "method": "end"}"#;
+    let mut output = Vec::new();
+    essential_debugger::run_rpc(
+        solution,
+        0,
+        predicate,
+        0,
+        Default::default(),
+        None,
+        essential_debugger::GasConfig::default().with_limit(3),
+        input.as_bytes(),
+        &mut output,
+    )
+    .await
+    .unwrap();
+    let response: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(
+        response["result"]["outcome"],
+        serde_json::json!({ "out_of_gas": { "limit": 3, "op_gas": 1, "pc": 3, "spent": 3 } })
+    );
+}
+
+#[tokio::test]
+async fn test_rpc_bad_solution_data_index() {
+    let predicate = repeat_predicate();
+    let solution = solution_for(&predicate, vec![]);
+    let input = r#"{"jsonrpc": "2.0", "id": 1, "method": "state"}"#;
+    let mut output = Vec::new();
+    let e = essential_debugger::run_rpc(
+        solution,
+        1,
+        predicate,
+        0,
+        Default::default(),
+        None,
+        Default::default(),
+        input.as_bytes(),
+        &mut output,
+    )
+    .await
+    .err()
+    .unwrap();
+    assert_eq!(
+        e.to_string(),
+        "No solution data at index 1. The solution has 1"
+    );
+    assert!(output.is_empty());
+}
+
+#[tokio::test]
+async fn test_dap() {
+    let dir = std::env::temp_dir().join(format!("essential-debugger-dap-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let predicate = repeat_predicate();
+    let solution = solution_for(&predicate, vec![]);
+    let source = "predicate ::P {\n    constraint sum(0..50)\n        == 1225;\n}\n";
+    let sum = source.find("sum").unwrap();
+    let eq = source.find("==").unwrap();
+    let source_map = serde_json::json!({
+        "constraints": [(0..9)
+            .map(|pc| match pc {
+                0..=6 => serde_json::json!({ "start": sum, "end": sum + 10 }),
+                _ => serde_json::json!({ "start": eq, "end": eq + 7 }),
+            })
+            .collect::<Vec<_>>()]
+    });
+    let path = |name: &str| dir.join(name).to_string_lossy().to_string();
+    std::fs::write(
+        path("predicate.json"),
+        serde_json::to_vec(&predicate).unwrap(),
+    )
+    .unwrap();
+    std::fs::write(
+        path("solution.json"),
+        serde_json::to_vec(&solution).unwrap(),
+    )
+    .unwrap();
+    std::fs::write(path("p.pnt"), source).unwrap();
+    std::fs::write(path("map.json"), source_map.to_string()).unwrap();
+
+    let requests = [
+        (
+            "initialize",
+            serde_json::json!({ "adapterID": "essential" }),
+        ),
+        (
+            "launch",
+            serde_json::json!({
+                "solution": path("solution.json"),
+                "predicate": path("predicate.json"),
+                "predicateSource": path("p.pnt"),
+                "sourceMap": path("map.json"),
+            }),
+        ),
+        (
+            "setBreakpoints",
+            serde_json::json!({
+                "source": { "path": path("p.pnt") },
+                "breakpoints": [{ "line": 3 }, { "line": 4 }],
+            }),
+        ),
+        (
+            "setFunctionBreakpoints",
+            serde_json::json!({ "breakpoints": [{ "name": "Pred::Eq" }, { "name": "Foo" }] }),
+        ),
+        ("configurationDone", serde_json::json!({})),
+        ("continue", serde_json::json!({ "threadId": 1 })),
+        ("stackTrace", serde_json::json!({ "threadId": 1 })),
+        ("variables", serde_json::json!({ "variablesReference": 1 })),
+        ("continue", serde_json::json!({ "threadId": 1 })),
+        ("next", serde_json::json!({ "threadId": 1 })),
+        ("continue", serde_json::json!({ "threadId": 1 })),
+        ("evaluate", serde_json::json!({ "expression": "t 0 bool" })),
+        ("stepBack", serde_json::json!({ "threadId": 1 })),
+        ("source", serde_json::json!({ "sourceReference": 1 })),
+        ("foo", serde_json::json!({})),
+        ("disconnect", serde_json::json!({})),
+    ];
+    let mut input = Vec::new();
+    for (seq, (command, arguments)) in requests.iter().enumerate() {
+        let request = serde_json::json!({
+            "seq": seq + 1,
+            "type": "request",
+            "command": command,
+            "arguments": arguments,
+        });
+        essential_debugger::write_message(&mut input, &request).unwrap();
+    }
+    let mut output = Vec::new();
+    essential_debugger::run_dap(&input[..], &mut output)
+        .await
+        .unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let mut output = &output[..];
+    let mut messages = Vec::new();
+    while let Some(message) = essential_debugger::read_message(&mut output).unwrap() {
+        messages.push(message);
+    }
+    let summary = messages
+        .iter()
+        .map(|m| match m["type"].as_str().unwrap() {
+            "response" => format!("{} {}", m["command"].as_str().unwrap(), m["success"]),
+            _ => match m["event"].as_str().unwrap() {
+                "stopped" => format!("stopped {}", m["body"]["reason"].as_str().unwrap()),
+                "output" => format!("output {}", m["body"]["output"].as_str().unwrap().trim()),
+                event => event.to_string(),
+            },
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        summary,
+        [
+            "initialize true",
+            "launch true",
+            "initialized",
+            "setBreakpoints true",
+            "setFunctionBreakpoints true",
+            "configurationDone true",
+            "stopped entry",
+            "continue true",
+            "stopped breakpoint",
+            "stackTrace true",
+            "variables true",
+            "continue true",
+            "stopped breakpoint",
+            "next true",
+            "stopped step",
+            "continue true",
+            "output Program ended: pass",
+            "stopped step",
+            "evaluate true",
+            "stepBack true",
+            "stopped step",
+            "source true",
+            "foo false",
+            "disconnect true",
+        ]
+    );
+    let body = |command: &str| {
+        messages
+            .iter()
+            .find(|m| m["command"] == command)
+            .map(|m| m["body"].clone())
+            .unwrap()
+    };
+    let breakpoints = body("setBreakpoints")["breakpoints"].clone();
+    assert_eq!(breakpoints[0]["verified"], true);
+    assert_eq!(breakpoints[1]["verified"], false);
+    let breakpoints = body("setFunctionBreakpoints")["breakpoints"].clone();
+    assert_eq!(breakpoints[0]["verified"], true);
+    assert_eq!(breakpoints[1]["verified"], false);
+
+    // Stopped at the line breakpoint before `Push(1225)`.
+    let frame = body("stackTrace")["stackFrames"][0].clone();
+    assert_eq!(frame["name"], "Stack(Push(1225))");
+    assert_eq!(frame["line"], 3);
+    assert_eq!(frame["column"], 9);
+    assert_eq!(
+        body("variables")["variables"],
+        serde_json::json!([{ "name": "[0]", "value": "1225", "variablesReference": 0 }])
+    );
+    assert_eq!(body("evaluate")["result"], "true");
+    assert!(body("source")["content"]
+        .as_str()
+        .unwrap()
+        .starts_with("0: Stack(Push(0))\n1: Stack(Push(50))\n"));
+}
+
+#[tokio::test]
+async fn test_state_read_script() {
+    dialoguer::console::set_colors_enabled(false);
+    let predicate = Predicate {
+        state_read: vec![state_read_vm::asm::to_bytes([
+            state_read_vm::asm::Stack::Push(1).into(),
+            state_read_vm::asm::StateSlots::AllocSlots.into(),
+            state_read_vm::asm::Stack::Push(0).into(),
+            state_read_vm::asm::Stack::Push(0).into(),
+            state_read_vm::asm::Stack::Push(0).into(),
+            state_read_vm::asm::Stack::Push(0).into(),
+            state_read_vm::asm::Stack::Push(4).into(),
+            state_read_vm::asm::Stack::Push(1).into(),
+            state_read_vm::asm::Stack::Push(0).into(),
+            state_read_vm::asm::StateRead::KeyRange,
+            state_read_vm::asm::TotalControlFlow::Halt.into(),
+        ])
+        .collect()],
+        constraints: vec![],
+        directive: Directive::Satisfy,
+    };
+    let mut solution = solution_for(&predicate, vec![]);
+    solution.data[0].state_mutations = vec![Mutation {
+        key: vec![0, 0, 0, 0],
+        value: vec![42],
+    }];
+    let state = HashMap::from([(
+        essential_types::ContentAddress([0; 32]),
+        BTreeMap::from([(vec![0, 0, 0, 0], vec![7])]),
+    )]);
+    let script = "
+n
+p 10
+e
+run post
+e
+q
+";
+    let mut output = Vec::new();
+    essential_debugger::run_state_read_script(
+        solution,
+        0,
+        predicate,
+        0,
+        state,
+        Default::default(),
+        script.as_bytes(),
+        &mut output,
+    )
+    .await
+    .unwrap();
+    let output = String::from_utf8(output).unwrap();
+    let expected = "\
+<essential-dbg>::state_read[0]::pre n
+Op: Constraint(Stack(Push(1)))
+  ├── Stack([1])
+  ├── Memory([])
+  └── Slots([])
+
+<essential-dbg>::state_read[0]::pre p 10
+Op: KeyRange
+  ├── Stack([])
+  ├── Memory([])
+  └── Slots([[7]])
+
+<essential-dbg>::state_read[0]::pre e
+Op: Constraint(TotalControlFlow(Halt))
+  ├── Stack([])
+  ├── Memory([])
+  └── Slots([[7]])
+
+Program ended
+<essential-dbg>::state_read[0]::pre run post
+Reading from the post state
+<essential-dbg>::state_read[0]::post e
+Op: Constraint(TotalControlFlow(Halt))
+  ├── Stack([])
+  ├── Memory([])
+  └── Slots([[42]])
+
+Program ended
+<essential-dbg>::state_read[0]::post q
+";
+    assert_eq!(output, expected);
+}
+
 pub fn random_keypair(seed: [u8; 32]) -> (SecretKey, PublicKey) {
     use rand::SeedableRng;
     let mut rng = rand::rngs::SmallRng::from_seed(seed);
     let secp = Secp256k1::new();
     secp.generate_keypair(&mut rng)
 }
+
+/// Every key holds the sum of its words plus seven. Counts the reads.
+struct MockStore {
+    reads: std::cell::Cell<usize>,
+    fail: bool,
+}
+
+impl state_read_vm::StateRead for MockStore {
+    type Error = String;
+    type Future = std::future::Ready<Result<Vec<Vec<i64>>, String>>;
+
+    fn key_range(
+        &self,
+        _contract: essential_types::ContentAddress,
+        key: essential_types::Key,
+        num_values: usize,
+    ) -> Self::Future {
+        self.reads.set(self.reads.get() + 1);
+        if self.fail {
+            return std::future::ready(Err("store is down".to_string()));
+        }
+        let start = key.iter().sum::<i64>() + 7;
+        std::future::ready(Ok((0..num_values as i64)
+            .map(|i| vec![start + i])
+            .collect()))
+    }
+}
+
+#[tokio::test]
+async fn test_state_read_provider() {
+    let predicate = Predicate {
+        state_read: vec![state_read_vm::asm::to_bytes([
+            state_read_vm::asm::Stack::Push(2).into(),
+            state_read_vm::asm::StateSlots::AllocSlots.into(),
+            state_read_vm::asm::Stack::Push(0).into(),
+            state_read_vm::asm::Stack::Push(0).into(),
+            state_read_vm::asm::Stack::Push(0).into(),
+            state_read_vm::asm::Stack::Push(0).into(),
+            state_read_vm::asm::Stack::Push(4).into(),
+            state_read_vm::asm::Stack::Push(2).into(),
+            state_read_vm::asm::Stack::Push(0).into(),
+            state_read_vm::asm::StateRead::KeyRange,
+            state_read_vm::asm::TotalControlFlow::Halt.into(),
+        ])
+        .collect()],
+        constraints: vec![constraint_vm::asm::to_bytes(
+            [constraint_vm::asm::Stack::Push(1).into()],
+        )
+        .collect()],
+        directive: Directive::Satisfy,
+    };
+    let mut solution = solution_for(&predicate, vec![]);
+    solution.data[0].state_mutations = vec![
+        Mutation {
+            key: vec![0, 0, 0, 0],
+            value: vec![42],
+        },
+        Mutation {
+            key: vec![0, 0, 0, 5],
+            value: vec![1],
+        },
+    ];
+
+    let store = MockStore {
+        reads: Default::default(),
+        fail: false,
+    };
+    let mut debugger = essential_debugger::ConstraintDebugger::new_with_state_read(
+        solution.clone(),
+        0,
+        predicate.clone(),
+        0,
+        &store,
+        Default::default(),
+    )
+    .await
+    .unwrap();
+    // The post state is read from the store with the mutations on top,
+    // then each mutated key is read so it can be shown.
+    assert_eq!(store.reads.get(), 4);
+    let session = debugger.start_session();
+    assert!(session
+        .show(&["pre", "0"])
+        .starts_with("Pre state slot 0: [7]\n"));
+    assert!(session
+        .show(&["post", "0"])
+        .starts_with("Post state slot 0: [42]\n"));
+    assert!(session
+        .show(&["post", "1"])
+        .starts_with("Post state slot 1: [8]\n"));
+    // Only the keys that were read or mutated are known.
+    assert_eq!(
+        session.show_storage("pre"),
+        "[0, 0, 0, 0] = [7]\n[0, 0, 0, 1] = [8]\n[0, 0, 0, 5] = [12]"
+    );
+    assert_eq!(
+        session.show_storage("post"),
+        "[0, 0, 0, 0] = [42]\n[0, 0, 0, 1] = [8]\n[0, 0, 0, 5] = [1]"
+    );
+    assert_eq!(
+        session.show_diff("state"),
+        format!(
+            "Contract {}\n  ~ [0, 0, 0, 0]: [7] -> [42]\n  ~ [0, 0, 0, 5]: [12] -> [1]",
+            solution.data[0].predicate_to_solve.contract
+        )
+    );
+
+    let mut engine = essential_debugger::Engine::new_with_state_read(
+        solution.clone(),
+        0,
+        predicate.clone(),
+        0,
+        &store,
+        Default::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(engine.pre_state(), &[vec![7], vec![8]]);
+    assert_eq!(
+        engine.step().unwrap(),
+        essential_debugger::TraceOutcome::Step
+    );
+
+    let store = MockStore {
+        reads: Default::default(),
+        fail: false,
+    };
+    let mut debugger = essential_debugger::StateReadDebugger::new_with_state_read(
+        solution.clone(),
+        0,
+        predicate.clone(),
+        0,
+        store,
+        Default::default(),
+    )
+    .await
+    .unwrap();
+    debugger
+        .select(0, essential_debugger::StateRun::Post)
+        .await
+        .unwrap();
+    assert_eq!(
+        debugger.play_till_end().await.unwrap(),
+        essential_debugger::TraceOutcome::ProgramEnd
+    );
+    assert_eq!(debugger.slots(), &[vec![42], vec![8]]);
+
+    let store = MockStore {
+        reads: Default::default(),
+        fail: true,
+    };
+    let e = essential_debugger::ConstraintDebugger::new_with_state_read(
+        solution,
+        0,
+        predicate,
+        0,
+        &store,
+        Default::default(),
+    )
+    .await
+    .err()
+    .unwrap();
+    assert!(e.to_string().contains("store is down"), "{}", e);
+}